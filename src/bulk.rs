@@ -0,0 +1,169 @@
+use momoden_password::*;
+
+use crate::csv::{savedata_header, savedata_record, to_csv};
+use crate::game::load_from_password;
+use crate::query::{normalize_query_input, Query, QueryParseError};
+
+/// 一括デコードの結果 1 件。
+#[derive(Debug)]
+pub(crate) struct BulkDecodeRow {
+    /// 入力行(前後の空白は除去済み)。
+    pub(crate) input: String,
+    pub(crate) result: Result<Savedata, BulkDecodeError>,
+}
+
+/// 複数行のテキストを 1 行 1 パスワードとして一括デコードする。空行は無視する。
+///
+/// 各行は `normalize_query_input()` で正規化してからパースする。
+pub(crate) fn bulk_decode(text: &str, romaji: bool, normalize: bool) -> Vec<BulkDecodeRow> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(|line| BulkDecodeRow {
+            input: line.to_owned(),
+            result: decode_line(&normalize_query_input(line, romaji), normalize),
+        })
+        .collect()
+}
+
+fn decode_line(line: &str, normalize: bool) -> Result<Savedata, BulkDecodeError> {
+    match Query::parse(line).map_err(BulkDecodeError::Parse)? {
+        Query::Password(password) => {
+            load_from_password(&password, normalize).ok_or(BulkDecodeError::InvalidPassword)
+        }
+        Query::Pattern(_) => Err(BulkDecodeError::Pattern),
+    }
+}
+
+/// 一括デコードの結果を CSV 文字列に変換する。
+///
+/// 各行はパスワード、状態、セーブデータの各フィールドからなる。デコードに失敗した行のフィールドは空欄になる。
+pub(crate) fn bulk_decode_csv(rows: &[BulkDecodeRow]) -> String {
+    let header: Vec<String> = ["パスワード", "状態"]
+        .map(str::to_owned)
+        .into_iter()
+        .chain(savedata_header())
+        .collect();
+
+    let records = rows.iter().map(|row| {
+        let mut record = vec![row.input.clone()];
+        match &row.result {
+            Ok(savedata) => {
+                record.push("OK".to_owned());
+                record.extend(savedata_record(savedata));
+            }
+            Err(err) => record.push(err.to_string()),
+        }
+        record.resize(header.len(), String::new());
+        record
+    });
+
+    to_csv(&header, records)
+}
+
+#[derive(Debug)]
+pub(crate) enum BulkDecodeError {
+    Parse(QueryParseError),
+    Pattern,
+    InvalidPassword,
+}
+
+impl std::fmt::Display for BulkDecodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Parse(err) => err.fmt(f),
+            Self::Pattern => f.write_str("'?' を含むパスワードはデコードできない"),
+            Self::InvalidPassword => f.write_str("パスワードが無効"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::game::save_to_password;
+    use crate::preset::{preset_savedata, Preset};
+
+    /// CSV の 1 行をフィールドに分ける(ダブルクォート内のカンマは区切りとみなさない)。
+    fn split_record(line: &str) -> Vec<String> {
+        let mut fields = vec![String::new()];
+        let mut quoted = false;
+        for c in line.chars() {
+            match c {
+                '"' => quoted = !quoted,
+                ',' if !quoted => fields.push(String::new()),
+                _ => fields.last_mut().unwrap().push(c),
+            }
+        }
+        fields
+    }
+
+    #[test]
+    fn test_bulk_decode() {
+        let savedata = preset_savedata(Preset::Hanasaka);
+        let valid = save_to_password(&savedata).display().to_string();
+        let pretty = save_to_password(&savedata).display_pretty().to_string();
+
+        // 末尾の 1 文字を変えるとチェックサムが合わなくなる。
+        let mut invalid: Vec<char> = valid.chars().collect();
+        let last = invalid.last_mut().unwrap();
+        *last = if *last == 'あ' { 'い' } else { 'あ' };
+        let invalid: String = invalid.into_iter().collect();
+
+        let mut pattern: Vec<char> = valid.chars().collect();
+        pattern[3] = '?';
+        let pattern: String = pattern.into_iter().collect();
+
+        let text = format!("  {valid}  \n\n{pretty}\n{invalid}\n   \n{pattern}\nabc\n");
+        let rows = bulk_decode(&text, false, false);
+
+        let inputs: Vec<&str> = rows.iter().map(|row| row.input.as_str()).collect();
+        assert_eq!(inputs, [&valid, &pretty, &invalid, &pattern, "abc"]);
+
+        assert_eq!(rows[0].result.as_ref().unwrap(), &savedata);
+        assert_eq!(rows[1].result.as_ref().unwrap(), &savedata);
+        assert!(matches!(
+            rows[2].result,
+            Err(BulkDecodeError::InvalidPassword)
+        ));
+        assert!(matches!(rows[3].result, Err(BulkDecodeError::Pattern)));
+        assert!(matches!(
+            rows[4].result,
+            Err(BulkDecodeError::Parse(QueryParseError::InvalidChar {
+                pos: 0,
+                ch: 'a'
+            }))
+        ));
+
+        // ローマ字を変換する。
+        let rows = bulk_decode("a", true, false);
+        assert!(!matches!(rows[0].result, Err(BulkDecodeError::Parse(_))));
+    }
+
+    #[test]
+    fn test_bulk_decode_csv() {
+        let savedata = preset_savedata(Preset::Hanasaka);
+        let valid = save_to_password(&savedata).display().to_string();
+        let rows = bulk_decode(&format!("{valid}\nabc\n"), false, false);
+
+        let csv = bulk_decode_csv(&rows);
+        let lines: Vec<&str> = csv.strip_suffix("\r\n").unwrap().split("\r\n").collect();
+        assert_eq!(lines.len(), 3);
+
+        let header = split_record(lines[0]);
+        assert_eq!(header[..2], ["パスワード", "状態"]);
+        assert_eq!(header[2..], savedata_header());
+
+        let ok = split_record(lines[1]);
+        assert_eq!(ok[..2], [valid.as_str(), "OK"]);
+        assert_eq!(ok[2..], savedata_record(&savedata));
+
+        // 失敗した行もヘッダと同じ列数に揃える。
+        let err = split_record(lines[2]);
+        assert_eq!(err.len(), header.len());
+        assert_eq!(err[0], "abc");
+        assert_eq!(err[1], rows[1].result.as_ref().unwrap_err().to_string());
+        assert!(err[2..].iter().all(String::is_empty));
+    }
+}
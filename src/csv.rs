@@ -0,0 +1,219 @@
+use std::borrow::Cow;
+
+use momoden_password::*;
+
+use crate::flags::{Flag, FlagSet};
+use crate::game::*;
+use crate::util::data_url;
+
+/// セーブデータの各フィールドに対応する列名を返す。
+pub(crate) fn savedata_header() -> Vec<String> {
+    let mut header: Vec<String> = ["経験値", "所持金", "預金 (×1000)", "年齢", "タイマー"]
+        .map(str::to_owned)
+        .into();
+
+    header.extend(flag_header::<Spell>("術"));
+    header.extend(flag_header::<Event>("イベント"));
+    header.extend(flag_header::<Treasure>("宝物"));
+    header.extend(flag_header::<Minion>("お供"));
+    header.extend(flag_header::<Bookmark>("ブックマーク"));
+    header.push("復活地点".to_owned());
+    header.extend(EquipmentSlot::all().map(|slot| format!("装備: {}", equipment_slot_name(slot))));
+    header.extend((0..8).map(|i| format!("インベントリ{}", i + 1)));
+
+    header
+}
+
+/// セーブデータの各フィールドの値を返す。並び順は `savedata_header()` と同じ。
+pub(crate) fn savedata_record(savedata: &Savedata) -> Vec<String> {
+    let mut record = vec![
+        savedata.xp.to_string(),
+        savedata.purse.to_string(),
+        savedata.deposit.to_string(),
+        savedata.age.to_string(),
+        savedata.age_timer_hi.to_string(),
+    ];
+
+    record.extend(flag_record::<Spell>(savedata));
+    record.extend(flag_record::<Event>(savedata));
+    record.extend(flag_record::<Treasure>(savedata));
+    record.extend(flag_record::<Minion>(savedata));
+    record.extend(flag_record::<Bookmark>(savedata));
+    record.push(format!(
+        "0x{:X}: {}",
+        savedata.respawn,
+        respawn_name(savedata.respawn)
+    ));
+    record.extend(EquipmentSlot::all().map(|slot| {
+        format!(
+            "0x{:02X}: {}",
+            equipment_index(&savedata.equipment, slot),
+            equipment_index_name(&savedata.equipment, slot)
+        )
+    }));
+    record.extend((0..8).map(|i| {
+        savedata
+            .inventory
+            .get(i)
+            .map_or_else(String::new, |&id| format!("0x{id:02X}: {}", item_name(id)))
+    }));
+
+    record
+}
+
+/// フラグ群 `F` の各フラグに対応する列名を返す。
+fn flag_header<F: Flag>(label: &str) -> impl Iterator<Item = String> + '_ {
+    F::ALL
+        .iter()
+        .map(move |flag| format!("{label}: {}", flag.name()))
+}
+
+/// フラグ群 `F` の各フラグの値を "1" または "0" で返す。
+fn flag_record<F: Flag>(savedata: &Savedata) -> impl Iterator<Item = String> {
+    let flags = FlagSet::<F>::from_savedata(savedata);
+    F::ALL
+        .iter()
+        .map(move |&flag| if flags.contains(flag) { "1" } else { "0" }.to_owned())
+}
+
+/// パスワードたちとそれらをデコードしたセーブデータを CSV 文字列に変換する。無効なパスワードは無視する。
+pub(crate) fn passwords_csv(passwords: &[Password], normalize: bool) -> String {
+    let header: Vec<String> = std::iter::once("パスワード".to_owned())
+        .chain(savedata_header())
+        .collect();
+
+    let records = passwords.iter().filter_map(|password| {
+        let savedata = load_from_password(password, normalize)?;
+        let mut record = vec![password.display_pretty().to_string()];
+        record.extend(savedata_record(&savedata));
+        Some(record)
+    });
+
+    to_csv(&header, records)
+}
+
+/// ヘッダとレコードたちから CSV 文字列を作る。改行は CRLF。
+pub(crate) fn to_csv<R, S>(header: &[String], records: R) -> String
+where
+    R: IntoIterator,
+    R::Item: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    let mut csv = String::new();
+
+    write_record(&mut csv, header);
+    for record in records {
+        write_record(&mut csv, record);
+    }
+
+    csv
+}
+
+fn write_record<I, S>(csv: &mut String, fields: I)
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    for (i, field) in fields.into_iter().enumerate() {
+        if i != 0 {
+            csv.push(',');
+        }
+        csv.push_str(&escape(field.as_ref()));
+    }
+    csv.push_str("\r\n");
+}
+
+/// 必要ならフィールドをダブルクォートで囲む。
+fn escape(field: &str) -> Cow<'_, str> {
+    if field.contains([',', '"', '\r', '\n']) {
+        let mut s = String::with_capacity(field.len() + 2);
+        s.push('"');
+        for c in field.chars() {
+            if c == '"' {
+                s.push('"');
+            }
+            s.push(c);
+        }
+        s.push('"');
+        Cow::Owned(s)
+    } else {
+        Cow::Borrowed(field)
+    }
+}
+
+/// CSV 文字列をダウンロード用の data URL に変換する。
+///
+/// Excel で文字化けしないよう BOM を付ける。
+pub(crate) fn to_data_url(csv: &str) -> String {
+    data_url(
+        "text/csv;charset=utf-8",
+        format!("\u{FEFF}{csv}").as_bytes(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::preset::{preset_savedata, Preset};
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(escape("もも"), "もも");
+        assert_eq!(escape(""), "");
+        assert_eq!(escape("a,b"), "\"a,b\"");
+        assert_eq!(escape("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(escape("a\nb"), "\"a\nb\"");
+        assert_eq!(escape("a\r\nb"), "\"a\r\nb\"");
+    }
+
+    #[test]
+    fn test_to_csv() {
+        let header = ["a".to_owned(), "b,c".to_owned()];
+        let csv = to_csv(&header, [["1", "\"x\""], ["", "y\nz"]]);
+        assert_eq!(csv, "a,\"b,c\"\r\n1,\"\"\"x\"\"\"\r\n,\"y\nz\"\r\n");
+
+        assert_eq!(to_csv(&header, Vec::<Vec<&str>>::new()), "a,\"b,c\"\r\n");
+    }
+
+    #[test]
+    fn test_passwords_csv() {
+        let savedata = preset_savedata(Preset::Hanasaka);
+        let password = save_to_password(&savedata);
+        let invalid = Password::new(&[PasswordChar::A; 5]).unwrap();
+        assert!(!invalid.is_valid());
+
+        let csv = passwords_csv(&[invalid, password.clone()], false);
+        let lines: Vec<&str> = csv.strip_suffix("\r\n").unwrap().split("\r\n").collect();
+
+        // 無効なパスワードは無視する。
+        assert_eq!(lines.len(), 2);
+
+        let header: Vec<&str> = lines[0].split(',').collect();
+        let record: Vec<&str> = lines[1].split(',').collect();
+        assert_eq!(header.len(), record.len());
+        assert_eq!(header.len(), 1 + savedata_header().len());
+
+        let field = |name: &str| record[header.iter().position(|&h| h == name).unwrap()];
+        assert_eq!(field("パスワード"), password.display_pretty().to_string());
+        assert_eq!(field("経験値"), "300");
+        assert_eq!(field("所持金"), "500");
+        assert_eq!(field("預金 (×1000)"), "0");
+        assert_eq!(field("術: きんたん"), "1");
+        assert_eq!(field("術: ろっかく"), "0");
+        assert_eq!(field("お供: 犬"), "1");
+        assert_eq!(field("お供: 猿"), "0");
+        assert_eq!(field("復活地点"), "0x2: 花咲かの村");
+        assert_eq!(
+            field("装備: 武器"),
+            format!(
+                "0x02: {}",
+                equipment_index_name(&savedata.equipment, EquipmentSlot::Weapon)
+            )
+        );
+        assert_eq!(field("インベントリ1"), "0x01: おまんじゅう");
+        assert_eq!(field("インベントリ3"), "0x03: きびだんご");
+        assert_eq!(field("インベントリ4"), "0x07: クモのいと");
+        assert_eq!(field("インベントリ5"), "");
+    }
+}
@@ -0,0 +1,433 @@
+//! 同じセーブデータにデコードされるパスワードたち。
+//!
+//! セーブデータのビット列の後ろには意味を持たないビットがある。また、ビット列のうち
+//! 長さ `SerializedBits::CAPACITY` (162bit) を超える部分はデシリアライズ時に読まれない。
+//! これらのビットは自由に選べるので、1 つのセーブデータに対応するパスワードは一般に複数ある。
+
+use momoden_password::*;
+
+/// デシリアライズ時に読まれるデータ部のバイト数。
+const DATA_LEN_READ: usize = 27;
+
+/// セーブデータ本体(チェックサム以外)のうち、インベントリより前の部分のビット数。
+const FIXED_BIT_LEN: usize = 111;
+
+/// インベントリのアイテム 1 個あたりのビット数。
+const ITEM_BIT_LEN: usize = 6;
+
+/// パスワードのエンコード時に用いる加算値テーブル。
+const ENCODE_ADD_TABLE: [u8; 4] = [0x05, 0x19, 0x32, 0x21];
+
+/// 先頭の文字のエンコード時に「直前の文字」とみなす値。
+pub(crate) const FIRST_PREV: u8 = 0x1F;
+
+/// i 文字目のバイト値 `b` と直前の文字(内部値) `prev` から、i 文字目の文字(内部値)を求める。
+///
+/// XOR は可逆なので、i 文字目の文字から直前の文字を求めるのにも使える。
+pub(crate) fn encode_char(i: usize, b: u8, prev: u8) -> u8 {
+    (b.wrapping_add(ENCODE_ADD_TABLE[i % 4]) & 0x3F) ^ prev
+}
+
+/// i 文字目の文字(内部値) `pc` と直前の文字(内部値) `prev` から、i 文字目のバイト値を求める。
+pub(crate) fn decode_char(i: usize, pc: u8, prev: u8) -> u8 {
+    (pc ^ prev).wrapping_sub(ENCODE_ADD_TABLE[i % 4]) & 0x3F
+}
+
+/// データ部の 1 バイトに対する制約。`mask` の立っているビットは `value` と一致しなければならない。
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) struct ByteConstraint {
+    pub(crate) mask: u8,
+    pub(crate) value: u8,
+}
+
+impl ByteConstraint {
+    const FREE: Self = Self { mask: 0, value: 0 };
+
+    /// バイト値 `b` が制約を満たすかどうかを返す。
+    pub(crate) fn accepts(self, b: u8) -> bool {
+        b & self.mask == self.value
+    }
+
+    /// 制約を満たすバイト値を列挙する。
+    pub(crate) fn candidates(self) -> impl Iterator<Item = u8> {
+        (0..=0x3F).filter(move |&b| self.accepts(b))
+    }
+}
+
+/// セーブデータのビット列(パディングを含まない)を返す。
+///
+/// エンコードとデコードで元に戻らないセーブデータ(インベントリにアイテム 0 を含むなど)には `None` を返す。
+fn savedata_bits(savedata: &Savedata) -> Option<Vec<bool>> {
+    let bytes = SerializedBytes::from_savedata(savedata);
+    if bytes.to_savedata().as_ref() != Some(savedata) {
+        return None;
+    }
+
+    let inventory_len = savedata.inventory.len();
+    let terminator_len = if savedata.inventory.is_full() {
+        0
+    } else {
+        ITEM_BIT_LEN
+    };
+    let bit_len = FIXED_BIT_LEN + ITEM_BIT_LEN * inventory_len + terminator_len;
+
+    let bits = bytes[2..]
+        .iter()
+        .flat_map(|b| (0..6).rev().map(move |i| b.get() >> i & 1 != 0))
+        .take(bit_len)
+        .collect();
+
+    Some(bits)
+}
+
+/// セーブデータ `savedata` にデコードされる長さ `len` のパスワードについて、
+/// データ部(先頭 2 バイトのチェックサムを除く)の各バイトに対する制約を返す。
+///
+/// 長さ `len` のパスワードが存在しえないなら `None` を返す。`len` は 3 以上でなければならない。
+pub(crate) fn data_constraints(savedata: &Savedata, len: usize) -> Option<Vec<ByteConstraint>> {
+    assert!(matches!(len, 3..=Password::MAX_LEN));
+
+    let bits = savedata_bits(savedata)?;
+    let data_len = len - 2;
+
+    // 足りないビットは 1 として読まれるので、切り捨てられる部分は全て 1 でなければならない。
+    if bits.iter().skip(6 * data_len).any(|&bit| !bit) {
+        return None;
+    }
+
+    let constraints = (0..data_len)
+        .map(|i| {
+            if i >= DATA_LEN_READ {
+                return ByteConstraint::FREE;
+            }
+            let mut c = ByteConstraint::FREE;
+            for (j, &bit) in bits.iter().skip(6 * i).take(6).enumerate() {
+                c.mask |= 0x20 >> j;
+                if bit {
+                    c.value |= 0x20 >> j;
+                }
+            }
+            c
+        })
+        .collect();
+
+    Some(constraints)
+}
+
+/// データ部を指定して、チェックサムを補ったバイト列を作る。
+pub(crate) fn serialized_with_checksum(data: &[u8]) -> SerializedBytes {
+    let inner: Vec<SerializedByte> = [0, 0]
+        .iter()
+        .chain(data)
+        .map(|&b| SerializedByte::new(b).expect("serialized byte must be 6bit"))
+        .collect();
+    let mut bytes = SerializedBytes::new(&inner).expect("length must be valid");
+
+    let checksum = bytes.checksum_calculated();
+    bytes[0] = checksum.sum_add();
+    bytes[1] = checksum.sum_xor();
+
+    bytes
+}
+
+/// 長さ 2 以下で、セーブデータ `savedata` にデコードされるパスワードを列挙する。
+///
+/// この長さではデータ部がなく、全ビットが 1 として読まれる。
+pub(crate) fn short_equivalent_passwords(savedata: &Savedata) -> Vec<Password> {
+    let max = SerializedByte::MAX;
+    [&[max][..], &[max, max][..]]
+        .into_iter()
+        .map(|buf| SerializedBytes::new(buf).unwrap())
+        .filter(|bytes| bytes.to_savedata().as_ref() == Some(savedata))
+        .map(|bytes| bytes.to_password())
+        .collect()
+}
+
+/// セーブデータ `savedata` にデコードされるパスワードの個数を、長さごとに返す。
+///
+/// 長さの昇順に、個数が 0 でない長さのみを返す。
+pub(crate) fn equivalent_password_counts(savedata: &Savedata) -> Vec<(usize, u128)> {
+    let mut res: Vec<(usize, u128)> = short_equivalent_passwords(savedata)
+        .into_iter()
+        .map(|password| (password.len(), 1))
+        .collect();
+
+    for len in 3..=Password::MAX_LEN {
+        let Some(constraints) = data_constraints(savedata, len) else {
+            continue;
+        };
+        let free_bits: u32 = constraints.iter().map(|c| 6 - c.mask.count_ones()).sum();
+        res.push((len, 1 << free_bits));
+    }
+
+    res
+}
+
+/// セーブデータ `savedata` にデコードされるパスワードを、長さの昇順に全て列挙する。
+///
+/// 個数は膨大になりうるので、必要な分だけ取り出すこと。
+pub(crate) fn equivalent_passwords(savedata: &Savedata) -> impl Iterator<Item = Password> + '_ {
+    let short = short_equivalent_passwords(savedata);
+    let long = (3..=Password::MAX_LEN)
+        .filter_map(|len| data_constraints(savedata, len))
+        .flat_map(|constraints| DataIter::new(&constraints))
+        .map(|data| serialized_with_checksum(&data).to_password());
+
+    short.into_iter().chain(long)
+}
+
+/// 制約を満たすデータ部を辞書順に列挙するイテレータ。
+#[derive(Debug)]
+struct DataIter {
+    candidates: Vec<Vec<u8>>,
+    indices: Vec<usize>,
+    done: bool,
+}
+
+impl DataIter {
+    fn new(constraints: &[ByteConstraint]) -> Self {
+        Self {
+            candidates: constraints
+                .iter()
+                .map(|c| c.candidates().collect())
+                .collect(),
+            indices: vec![0; constraints.len()],
+            done: false,
+        }
+    }
+}
+
+impl Iterator for DataIter {
+    type Item = Vec<u8>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        let data = self
+            .indices
+            .iter()
+            .zip(&self.candidates)
+            .map(|(&i, cands)| cands[i])
+            .collect();
+
+        // 末尾から繰り上げる。
+        self.done = true;
+        for (i, cands) in self.indices.iter_mut().zip(&self.candidates).rev() {
+            *i += 1;
+            if *i < cands.len() {
+                self.done = false;
+                break;
+            }
+            *i = 0;
+        }
+
+        Some(data)
+    }
+}
+
+/// データ部を先頭から決めていき、最後にチェックサムを合わせる DP の状態数。
+///
+/// 状態は (直前の文字, 残りの加算チェックサム, 残りの XOR チェックサム) の組。
+/// 先頭 2 バイト(チェックサム)を先に全通り決め、データ部を決めるたびにその分を差し引いていく。
+pub(crate) const STATE_COUNT: usize = 64 * 64 * 64;
+
+pub(crate) fn state(pc: u8, sum_add: u8, sum_xor: u8) -> usize {
+    (usize::from(pc) << 12) | (usize::from(sum_add) << 6) | usize::from(sum_xor)
+}
+
+pub(crate) fn unpack_state(s: usize) -> (u8, u8, u8) {
+    ((s >> 12) as u8, (s >> 6 & 0x3F) as u8, (s & 0x3F) as u8)
+}
+
+/// DP の結果から、残りチェックサムが 0 の状態のうちコスト最小のものを選んでパスワードを復元する。
+///
+/// `choices[i][s]` は i 番目のデータバイトを決めて状態 s に至ったときのバイト値。
+pub(crate) fn restore<C: Copy + Ord>(cost: &[C], inf: C, choices: &[Vec<u8>]) -> Option<Password> {
+    let (mut s, _) = (0..64)
+        .map(|pc| state(pc, 0, 0))
+        .map(|s| (s, cost[s]))
+        .filter(|&(_, c)| c != inf)
+        .min_by_key(|&(_, c)| c)?;
+
+    // データ部を後ろから復元する。
+    let mut data = vec![0; choices.len()];
+    for (i, choice) in choices.iter().enumerate().rev() {
+        let b = choice[s];
+        data[i] = b;
+        let (pc, sum_add, sum_xor) = unpack_state(s);
+        let prev = encode_char(i + 2, b, pc);
+        s = state(prev, sum_add.wrapping_add(b) & 0x3F, sum_xor ^ b);
+    }
+
+    Some(serialized_with_checksum(&data).to_password())
+}
+
+/// パスワードの各位置について、その文字だけを変えてもチェックサムの修正(先頭 2 文字の変更)のみで
+/// 同じセーブデータにデコードされるような代わりの文字と、そのときのパスワードを列挙する。
+///
+/// 先頭 2 文字(チェックサム)については、1 文字目は単独で、2 文字目は 1 文字目の修正を許して調べる。
+/// パスワードが無効なら `None` を返す。
+pub(crate) fn char_alternatives(password: &Password) -> Option<Vec<Vec<(PasswordChar, Password)>>> {
+    let savedata = SerializedBytes::from_password(password).to_savedata()?;
+    let chars: Vec<u8> = password.iter().map(|pc| pc.to_inner()).collect();
+    let len = chars.len();
+
+    let decodes_same = |cs: &[u8]| {
+        let password = to_password(cs);
+        let ok = SerializedBytes::from_password(&password)
+            .to_savedata()
+            .as_ref()
+            == Some(&savedata);
+        ok.then_some(password)
+    };
+
+    let mut res = vec![vec![]; len];
+    for alt in (0..=0x3F).filter(|&alt| alt != chars[0]) {
+        let mut cs = chars.clone();
+        cs[0] = alt;
+        if let Some(password) = decodes_same(&cs) {
+            res[0].push((to_char(alt), password));
+        }
+    }
+    if len >= 2 {
+        for alt in (0..=0x3F).filter(|&alt| alt != chars[1]) {
+            let found = fix_up_order(chars[0]).find_map(|c0| {
+                let mut cs = chars.clone();
+                cs[0] = c0;
+                cs[1] = alt;
+                decodes_same(&cs)
+            });
+            if let Some(password) = found {
+                res[1].push((to_char(alt), password));
+            }
+        }
+    }
+    if len >= 3 {
+        let constraints = data_constraints(&savedata, len).unwrap();
+        for i in 2..len {
+            for alt in (0..=0x3F).filter(|&alt| alt != chars[i]) {
+                let mut cs = chars.clone();
+                cs[i] = alt;
+                if fix_up_checksum(&mut cs, &constraints) {
+                    res[i].push((to_char(alt), to_password(&cs)));
+                }
+            }
+        }
+    }
+
+    Some(res)
+}
+
+/// 3 文字目以降を固定したまま、先頭 2 文字を変えてセーブデータの制約とチェックサムを満たすようにする。
+/// 満たせなければ偽を返す。
+fn fix_up_checksum(chars: &mut [u8], constraints: &[ByteConstraint]) -> bool {
+    let len = chars.len();
+
+    // 4 バイト目以降は先頭 2 文字によらない。
+    let mut sum_add: u8 = 0;
+    let mut sum_xor: u8 = 0;
+    for i in 3..len {
+        let b = decode_char(i, chars[i], chars[i - 1]);
+        if !constraints[i - 2].accepts(b) {
+            return false;
+        }
+        sum_add = sum_add.wrapping_add(b);
+        sum_xor ^= b;
+    }
+
+    for c1 in fix_up_order(chars[1]) {
+        let b2 = decode_char(2, chars[2], c1);
+        if !constraints[0].accepts(b2) {
+            continue;
+        }
+        let b0 = sum_add.wrapping_add(b2) & 0x3F;
+        let b1 = sum_xor ^ b2;
+        let c0 = encode_char(0, b0, FIRST_PREV);
+        if decode_char(1, c1, c0) == b1 {
+            chars[0] = c0;
+            chars[1] = c1;
+            return true;
+        }
+    }
+
+    false
+}
+
+/// 修正する文字の候補を、元の文字を先頭にして列挙する。
+fn fix_up_order(orig: u8) -> impl Iterator<Item = u8> {
+    std::iter::once(orig).chain((0..=0x3F).filter(move |&c| c != orig))
+}
+
+fn to_char(inner: u8) -> PasswordChar {
+    PasswordChar::from_inner(inner).unwrap()
+}
+
+fn to_password(chars: &[u8]) -> Password {
+    let chars: Vec<PasswordChar> = chars.iter().map(|&c| to_char(c)).collect();
+    Password::new(&chars).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::preset::{preset_savedata, Preset};
+
+    fn decode(password: &Password) -> Option<Savedata> {
+        SerializedBytes::from_password(password).to_savedata()
+    }
+
+    #[test]
+    fn test_encode_decode_char() {
+        for i in 0..4 {
+            for b in 0..=0x3F {
+                for prev in 0..=0x3F {
+                    let pc = encode_char(i, b, prev);
+                    assert!(pc <= 0x3F);
+                    assert_eq!(decode_char(i, pc, prev), b);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_equivalent_passwords() {
+        for preset in Preset::all() {
+            let savedata = preset_savedata(preset);
+            let counts = equivalent_password_counts(&savedata);
+            let (len_min, count_min) = counts[0];
+
+            let passwords: Vec<Password> = equivalent_passwords(&savedata).take(100).collect();
+            assert_eq!(passwords[0].len(), len_min);
+            assert_eq!(
+                passwords.iter().filter(|p| p.len() == len_min).count() as u128,
+                count_min.min(100)
+            );
+            for password in &passwords {
+                assert!(password.is_valid());
+                assert_eq!(decode(password).as_ref(), Some(&savedata));
+            }
+        }
+    }
+
+    #[test]
+    fn test_char_alternatives() {
+        let savedata = preset_savedata(Preset::Hanasaka);
+        let password = SerializedBytes::from_savedata(&savedata).to_password();
+        let alternatives = char_alternatives(&password).unwrap();
+        assert_eq!(alternatives.len(), password.len());
+
+        for (i, alts) in alternatives.iter().enumerate() {
+            for (pc, alt) in alts {
+                assert_eq!(alt[i], *pc);
+                assert_eq!(decode(alt).as_ref(), Some(&savedata));
+                // 変わるのはその位置と先頭 2 文字(チェックサム)のみ。
+                for (j, (a, b)) in password.iter().zip(alt.iter()).enumerate() {
+                    assert!(a == b || j == i || j < 2);
+                }
+            }
+        }
+    }
+}
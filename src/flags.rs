@@ -0,0 +1,283 @@
+//! 術、イベント、宝物、お供、ブックマークといったフラグ群を一様に扱うための集合型。
+
+use std::marker::PhantomData;
+
+use int_enum::IntEnum;
+
+use momoden_password::*;
+
+/// フラグ群の 1 要素(`Spell`, `Event` など)。
+pub(crate) trait Flag: Copy + Eq + IntEnum<Int = u8> + 'static {
+    /// このフラグをまとめた `Savedata` 内の構造体。
+    type Group: Copy + std::ops::IndexMut<Self, Output = bool>;
+
+    /// 全ての値(値の昇順)。
+    const ALL: &'static [Self];
+
+    fn name(self) -> &'static str;
+
+    fn group(savedata: &Savedata) -> &Self::Group;
+
+    fn group_mut(savedata: &mut Savedata) -> &mut Self::Group;
+}
+
+/// フラグ `F` の集合。
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub(crate) struct FlagSet<F> {
+    bits: u16,
+    _marker: PhantomData<F>,
+}
+
+impl<F: Flag> FlagSet<F> {
+    fn from_bits(bits: u16) -> Self {
+        Self {
+            bits,
+            _marker: PhantomData,
+        }
+    }
+
+    fn bit(flag: F) -> u16 {
+        1 << flag.int_value()
+    }
+
+    pub(crate) fn empty() -> Self {
+        Self::from_bits(0)
+    }
+
+    pub(crate) fn full() -> Self {
+        F::ALL.iter().copied().collect()
+    }
+
+    /// フラグ群の構造体 `group` で立っているフラグの集合を返す。
+    pub(crate) fn from_group(group: &F::Group) -> Self {
+        F::ALL.iter().copied().filter(|&flag| group[flag]).collect()
+    }
+
+    /// セーブデータ `savedata` で立っているフラグの集合を返す。
+    pub(crate) fn from_savedata(savedata: &Savedata) -> Self {
+        Self::from_group(F::group(savedata))
+    }
+
+    /// セーブデータ `savedata` のフラグ群をこの集合の内容で上書きする。
+    pub(crate) fn write_savedata(self, savedata: &mut Savedata) {
+        let group = F::group_mut(savedata);
+        for &flag in F::ALL {
+            group[flag] = self.contains(flag);
+        }
+    }
+
+    pub(crate) fn contains(self, flag: F) -> bool {
+        self.bits & Self::bit(flag) != 0
+    }
+
+    pub(crate) fn insert(&mut self, flag: F) {
+        self.bits |= Self::bit(flag);
+    }
+
+    /// 要素を値の昇順に列挙する。
+    pub(crate) fn iter(self) -> impl Iterator<Item = F> {
+        F::ALL
+            .iter()
+            .copied()
+            .filter(move |&flag| self.contains(flag))
+    }
+
+    pub(crate) fn count(self) -> usize {
+        self.bits.count_ones() as usize
+    }
+
+    pub(crate) fn is_empty(self) -> bool {
+        self.bits == 0
+    }
+}
+
+impl<F: Flag> Default for FlagSet<F> {
+    fn default() -> Self {
+        Self::empty()
+    }
+}
+
+impl<F: Flag> FromIterator<F> for FlagSet<F> {
+    fn from_iter<I: IntoIterator<Item = F>>(iter: I) -> Self {
+        let mut set = Self::empty();
+        for flag in iter {
+            set.insert(flag);
+        }
+        set
+    }
+}
+
+/// 和集合。
+impl<F: Flag> std::ops::BitOr for FlagSet<F> {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self::Output {
+        Self::from_bits(self.bits | rhs.bits)
+    }
+}
+
+/// 共通部分。
+impl<F: Flag> std::ops::BitAnd for FlagSet<F> {
+    type Output = Self;
+
+    fn bitand(self, rhs: Self) -> Self::Output {
+        Self::from_bits(self.bits & rhs.bits)
+    }
+}
+
+/// 差集合。
+impl<F: Flag> std::ops::Sub for FlagSet<F> {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        Self::from_bits(self.bits & !rhs.bits)
+    }
+}
+
+/// 補集合。
+impl<F: Flag> std::ops::Not for FlagSet<F> {
+    type Output = Self;
+
+    fn not(self) -> Self::Output {
+        Self::full() - self
+    }
+}
+
+/// 要素の名前を読点区切りで並べる。空なら "なし"。
+impl<F: Flag> std::fmt::Display for FlagSet<F> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        if self.is_empty() {
+            return f.write_str("なし");
+        }
+
+        let names: Vec<_> = self.iter().map(F::name).collect();
+        f.write_str(&names.join("、"))
+    }
+}
+
+/// フラグ群全体に対する一括操作。
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum FlagOp {
+    SetAll,
+    ClearAll,
+    Invert,
+}
+
+impl FlagOp {
+    pub(crate) fn all() -> impl Iterator<Item = Self> {
+        [Self::SetAll, Self::ClearAll, Self::Invert].into_iter()
+    }
+
+    pub(crate) fn apply<F: Flag>(self, flags: FlagSet<F>) -> FlagSet<F> {
+        match self {
+            Self::SetAll => FlagSet::full(),
+            Self::ClearAll => FlagSet::empty(),
+            Self::Invert => !flags,
+        }
+    }
+}
+
+pub(crate) fn flag_op_name(op: FlagOp) -> &'static str {
+    match op {
+        FlagOp::SetAll => "全て",
+        FlagOp::ClearAll => "なし",
+        FlagOp::Invert => "反転",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::game::*;
+    use crate::preset::{preset_savedata, Preset};
+
+    #[test]
+    fn test_savedata_roundtrip() {
+        let savedata = preset_savedata(Preset::Kintaro);
+        let minions = FlagSet::<Minion>::from_savedata(&savedata);
+        assert_eq!(
+            minions.iter().collect::<Vec<_>>(),
+            [Minion::Dog, Minion::Monkey]
+        );
+
+        let mut written = Savedata::default();
+        minions.write_savedata(&mut written);
+        assert_eq!(written.minions, savedata.minions);
+        assert_eq!(FlagSet::<Minion>::from_savedata(&written), minions);
+
+        // 他のフラグ群には触れない。
+        assert_eq!(written.events, Savedata::default().events);
+
+        // 全てのフラグ群で往復できる。
+        let savedata = preset_savedata(Preset::Endgame);
+        let mut written = Savedata::default();
+        FlagSet::<Spell>::from_savedata(&savedata).write_savedata(&mut written);
+        FlagSet::<Event>::from_savedata(&savedata).write_savedata(&mut written);
+        FlagSet::<Treasure>::from_savedata(&savedata).write_savedata(&mut written);
+        FlagSet::<Minion>::from_savedata(&savedata).write_savedata(&mut written);
+        FlagSet::<Bookmark>::from_savedata(&savedata).write_savedata(&mut written);
+        assert_eq!(written.spells, savedata.spells);
+        assert_eq!(written.events, savedata.events);
+        assert_eq!(written.treasures, savedata.treasures);
+        assert_eq!(written.minions, savedata.minions);
+        assert_eq!(written.bookmarks, savedata.bookmarks);
+    }
+
+    #[test]
+    fn test_ops() {
+        let dog_monkey: FlagSet<Minion> = [Minion::Dog, Minion::Monkey].into_iter().collect();
+        let dog_pheasant: FlagSet<Minion> = [Minion::Dog, Minion::Pheasant].into_iter().collect();
+
+        assert_eq!((dog_monkey | dog_pheasant), FlagSet::full());
+        assert_eq!(
+            (dog_monkey & dog_pheasant).iter().collect::<Vec<_>>(),
+            [Minion::Dog]
+        );
+        assert_eq!(
+            (dog_monkey - dog_pheasant).iter().collect::<Vec<_>>(),
+            [Minion::Monkey]
+        );
+        assert_eq!((!dog_monkey).iter().collect::<Vec<_>>(), [Minion::Pheasant]);
+
+        // 補集合は F::ALL の範囲外のビットを立てない。
+        assert_eq!(!FlagSet::<Minion>::empty(), FlagSet::full());
+        assert_eq!((!FlagSet::<Minion>::empty()).count(), Minion::ALL.len());
+        assert_eq!(!FlagSet::<Minion>::full(), FlagSet::empty());
+        assert_eq!(!!dog_monkey, dog_monkey);
+        assert_eq!((!FlagSet::<Spell>::empty()).count(), Spell::ALL.len());
+    }
+
+    #[test]
+    fn test_from_iter_count_iter() {
+        // 重複や順序は集合に影響しない。
+        let set: FlagSet<Minion> = [Minion::Monkey, Minion::Dog, Minion::Monkey]
+            .into_iter()
+            .collect();
+        assert_eq!(set.count(), 2);
+        assert!(set.contains(Minion::Dog));
+        assert!(!set.contains(Minion::Pheasant));
+        assert_eq!(
+            set.iter().collect::<Vec<_>>(),
+            [Minion::Dog, Minion::Monkey]
+        );
+
+        let empty: FlagSet<Minion> = std::iter::empty().collect();
+        assert!(empty.is_empty());
+        assert_eq!(empty, FlagSet::default());
+        assert_eq!(empty.count(), 0);
+        assert_eq!(empty.iter().count(), 0);
+
+        assert_eq!(FlagSet::<Event>::full().count(), Event::ALL.len());
+        assert!(FlagSet::<Event>::full()
+            .iter()
+            .eq(Event::ALL.iter().copied()));
+    }
+
+    #[test]
+    fn test_display() {
+        assert_eq!(FlagSet::<Minion>::empty().to_string(), "なし");
+        assert_eq!(FlagSet::from_iter([Minion::Dog]).to_string(), "犬");
+        assert_eq!(FlagSet::<Minion>::full().to_string(), "犬、キジ、猿");
+    }
+}
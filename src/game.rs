@@ -2,6 +2,137 @@ use int_enum::IntEnum;
 
 use momoden_password::*;
 
+use crate::flags::{Flag, FlagOp, FlagSet};
+
+/// パスワードをデコードしてセーブデータを得る。パスワードが無効なら `None` を返す。
+///
+/// `normalize` が真ならば、実際にロードした後の状態に正規化する。
+pub(crate) fn load_from_password(password: &Password, normalize: bool) -> Option<Savedata> {
+    let bytes = SerializedBytes::from_password(password);
+    let savedata = bytes.to_savedata()?;
+
+    Some(if normalize {
+        savedata.normalize()
+    } else {
+        savedata
+    })
+}
+
+/// セーブデータをパスワードにエンコードする。
+pub(crate) fn save_to_password(savedata: &Savedata) -> Password {
+    let bytes = SerializedBytes::from_savedata(savedata);
+    bytes.to_password()
+}
+
+/// セーブデータ内のフラグ群。
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum FlagGroup {
+    Spells,
+    Events,
+    Treasures,
+    Minions,
+    Bookmarks,
+}
+
+/// フラグ群 `$group` のフラグの型を `$flag` として式 `$body` を評価する。
+macro_rules! with_flag_type {
+    ($group:expr, |$flag:ident| $body:expr) => {
+        match $group {
+            FlagGroup::Spells => {
+                type $flag = Spell;
+                $body
+            }
+            FlagGroup::Events => {
+                type $flag = Event;
+                $body
+            }
+            FlagGroup::Treasures => {
+                type $flag = Treasure;
+                $body
+            }
+            FlagGroup::Minions => {
+                type $flag = Minion;
+                $body
+            }
+            FlagGroup::Bookmarks => {
+                type $flag = Bookmark;
+                $body
+            }
+        }
+    };
+}
+
+impl FlagGroup {
+    pub(crate) fn all() -> impl Iterator<Item = Self> {
+        [
+            Self::Spells,
+            Self::Events,
+            Self::Treasures,
+            Self::Minions,
+            Self::Bookmarks,
+        ]
+        .into_iter()
+    }
+
+    /// セーブデータ `savedata` のこのフラグ群に一括操作 `op` を施す。
+    pub(crate) fn update(self, savedata: &mut Savedata, op: FlagOp) {
+        with_flag_type!(self, |F| op
+            .apply(FlagSet::<F>::from_savedata(savedata))
+            .write_savedata(savedata))
+    }
+}
+
+/// `FlagGroup` のいずれかに属するフラグ。
+pub(crate) trait GroupFlag: Flag {
+    const GROUP: FlagGroup;
+}
+
+/// フラグ群の構造体 `$group` (`Savedata` のフィールド `$field`、`FlagGroup::$group`)をフラグ `$flag` で
+/// 添字付けできるようにし、`$flag` に `Flag` と `GroupFlag` を実装する。
+macro_rules! impl_flag {
+    ($flag:ident, $group:ident, $field:ident, $name:ident, { $($variant:ident => $member:ident),* $(,)? }) => {
+        impl std::ops::Index<$flag> for $group {
+            type Output = bool;
+
+            fn index(&self, flag: $flag) -> &Self::Output {
+                match flag {
+                    $($flag::$variant => &self.$member,)*
+                }
+            }
+        }
+
+        impl std::ops::IndexMut<$flag> for $group {
+            fn index_mut(&mut self, flag: $flag) -> &mut Self::Output {
+                match flag {
+                    $($flag::$variant => &mut self.$member,)*
+                }
+            }
+        }
+
+        impl Flag for $flag {
+            type Group = $group;
+
+            const ALL: &'static [Self] = &[$($flag::$variant),*];
+
+            fn name(self) -> &'static str {
+                $name(self)
+            }
+
+            fn group(savedata: &Savedata) -> &Self::Group {
+                &savedata.$field
+            }
+
+            fn group_mut(savedata: &mut Savedata) -> &mut Self::Group {
+                &mut savedata.$field
+            }
+        }
+
+        impl GroupFlag for $flag {
+            const GROUP: FlagGroup = FlagGroup::$group;
+        }
+    };
+}
+
 #[repr(u8)]
 #[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd, IntEnum)]
 pub(crate) enum Spell {
@@ -15,47 +146,16 @@ pub(crate) enum Spell {
     Houhi = 7,
 }
 
-impl Spell {
-    pub(crate) fn all() -> [Self; 8] {
-        std::array::from_fn(|i| Self::from_int(i as u8).unwrap())
-    }
-}
-
-impl std::ops::Index<Spell> for Spells {
-    type Output = bool;
-
-    fn index(&self, spell: Spell) -> &Self::Output {
-        use Spell::*;
-
-        match spell {
-            Kintan => &self.kintan,
-            Rokkaku => &self.rokkaku,
-            Inazuma => &self.inazuma,
-            Hien => &self.hien,
-            Mankintan => &self.mankintan,
-            Fuyuu => &self.fuyuu,
-            Dadadidi => &self.dadadidi,
-            Houhi => &self.houhi,
-        }
-    }
-}
-
-impl std::ops::IndexMut<Spell> for Spells {
-    fn index_mut(&mut self, spell: Spell) -> &mut Self::Output {
-        use Spell::*;
-
-        match spell {
-            Kintan => &mut self.kintan,
-            Rokkaku => &mut self.rokkaku,
-            Inazuma => &mut self.inazuma,
-            Hien => &mut self.hien,
-            Mankintan => &mut self.mankintan,
-            Fuyuu => &mut self.fuyuu,
-            Dadadidi => &mut self.dadadidi,
-            Houhi => &mut self.houhi,
-        }
-    }
-}
+impl_flag!(Spell, Spells, spells, spell_name, {
+    Kintan => kintan,
+    Rokkaku => rokkaku,
+    Inazuma => inazuma,
+    Hien => hien,
+    Mankintan => mankintan,
+    Fuyuu => fuyuu,
+    Dadadidi => dadadidi,
+    Houhi => houhi,
+});
 
 #[repr(u8)]
 #[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd, IntEnum)]
@@ -70,47 +170,16 @@ pub(crate) enum Event {
     Hohoemi = 7,
 }
 
-impl Event {
-    pub(crate) fn all() -> [Self; 8] {
-        std::array::from_fn(|i| Self::from_int(i as u8).unwrap())
-    }
-}
-
-impl std::ops::Index<Event> for Events {
-    type Output = bool;
-
-    fn index(&self, event: Event) -> &Self::Output {
-        use Event::*;
-
-        match event {
-            Hanasaka => &self.hanasaka,
-            Kintaro => &self.kintaro,
-            Urashima => &self.urashima,
-            Netaro => &self.netaro,
-            Murata => &self.murata,
-            Sarukani => &self.sarukani,
-            Dragon => &self.dragon,
-            Hohoemi => &self.hohoemi,
-        }
-    }
-}
-
-impl std::ops::IndexMut<Event> for Events {
-    fn index_mut(&mut self, event: Event) -> &mut Self::Output {
-        use Event::*;
-
-        match event {
-            Hanasaka => &mut self.hanasaka,
-            Kintaro => &mut self.kintaro,
-            Urashima => &mut self.urashima,
-            Netaro => &mut self.netaro,
-            Murata => &mut self.murata,
-            Sarukani => &mut self.sarukani,
-            Dragon => &mut self.dragon,
-            Hohoemi => &mut self.hohoemi,
-        }
-    }
-}
+impl_flag!(Event, Events, events, event_name, {
+    Hanasaka => hanasaka,
+    Kintaro => kintaro,
+    Urashima => urashima,
+    Netaro => netaro,
+    Murata => murata,
+    Sarukani => sarukani,
+    Dragon => dragon,
+    Hohoemi => hohoemi,
+});
 
 #[repr(u8)]
 #[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd, IntEnum)]
@@ -122,41 +191,13 @@ pub(crate) enum Treasure {
     Swallow = 4,
 }
 
-impl Treasure {
-    pub(crate) fn all() -> [Self; 5] {
-        std::array::from_fn(|i| Self::from_int(i as u8).unwrap())
-    }
-}
-
-impl std::ops::Index<Treasure> for Treasures {
-    type Output = bool;
-
-    fn index(&self, treasure: Treasure) -> &Self::Output {
-        use Treasure::*;
-
-        match treasure {
-            Dragon => &self.dragon,
-            Fur => &self.fur,
-            Hotoke => &self.hotoke,
-            Hourai => &self.hourai,
-            Swallow => &self.swallow,
-        }
-    }
-}
-
-impl std::ops::IndexMut<Treasure> for Treasures {
-    fn index_mut(&mut self, treasure: Treasure) -> &mut Self::Output {
-        use Treasure::*;
-
-        match treasure {
-            Dragon => &mut self.dragon,
-            Fur => &mut self.fur,
-            Hotoke => &mut self.hotoke,
-            Hourai => &mut self.hourai,
-            Swallow => &mut self.swallow,
-        }
-    }
-}
+impl_flag!(Treasure, Treasures, treasures, treasure_name, {
+    Dragon => dragon,
+    Fur => fur,
+    Hotoke => hotoke,
+    Hourai => hourai,
+    Swallow => swallow,
+});
 
 #[repr(u8)]
 #[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd, IntEnum)]
@@ -166,37 +207,11 @@ pub(crate) enum Minion {
     Monkey = 2,
 }
 
-impl Minion {
-    pub(crate) fn all() -> [Self; 3] {
-        std::array::from_fn(|i| Self::from_int(i as u8).unwrap())
-    }
-}
-
-impl std::ops::Index<Minion> for Minions {
-    type Output = bool;
-
-    fn index(&self, minion: Minion) -> &Self::Output {
-        use Minion::*;
-
-        match minion {
-            Dog => &self.dog,
-            Pheasant => &self.pheasant,
-            Monkey => &self.monkey,
-        }
-    }
-}
-
-impl std::ops::IndexMut<Minion> for Minions {
-    fn index_mut(&mut self, minion: Minion) -> &mut Self::Output {
-        use Minion::*;
-
-        match minion {
-            Dog => &mut self.dog,
-            Pheasant => &mut self.pheasant,
-            Monkey => &mut self.monkey,
-        }
-    }
-}
+impl_flag!(Minion, Minions, minions, minion_name, {
+    Dog => dog,
+    Pheasant => pheasant,
+    Monkey => monkey,
+});
 
 #[repr(u8)]
 #[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd, IntEnum)]
@@ -213,49 +228,93 @@ pub(crate) enum Bookmark {
     Hien = 9,
 }
 
-impl Bookmark {
-    pub(crate) fn all() -> [Self; 10] {
-        std::array::from_fn(|i| Self::from_int(i as u8).unwrap())
-    }
-}
-
-impl std::ops::Index<Bookmark> for Bookmarks {
-    type Output = bool;
-
-    fn index(&self, bookmark: Bookmark) -> &Self::Output {
-        use Bookmark::*;
+impl_flag!(Bookmark, Bookmarks, bookmarks, bookmark_name, {
+    Tabidachi => tabidachi,
+    Hanasaka => hanasaka,
+    Kintaro => kintaro,
+    Urashima => urashima,
+    Netaro => netaro,
+    Kibou => kibou,
+    Sarukani => sarukani,
+    Taketori => taketori,
+    Hohoemi => hohoemi,
+    Hien => hien,
+});
 
-        match bookmark {
-            Tabidachi => &self.tabidachi,
-            Hanasaka => &self.hanasaka,
-            Kintaro => &self.kintaro,
-            Urashima => &self.urashima,
-            Netaro => &self.netaro,
-            Kibou => &self.kibou,
-            Sarukani => &self.sarukani,
-            Taketori => &self.taketori,
-            Hohoemi => &self.hohoemi,
-            Hien => &self.hien,
-        }
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd, IntEnum)]
+pub(crate) enum EquipmentSlot {
+    Helm = 0,
+    Weapon = 1,
+    Armor = 2,
+    Shoes = 3,
+    Accessory0 = 4,
+    Accessory1 = 5,
+    Accessory2 = 6,
+    Accessory3 = 7,
+}
+
+impl EquipmentSlot {
+    pub(crate) fn all() -> [Self; 8] {
+        std::array::from_fn(|i| Self::from_int(i as u8).unwrap())
     }
 }
 
-impl std::ops::IndexMut<Bookmark> for Bookmarks {
-    fn index_mut(&mut self, bookmark: Bookmark) -> &mut Self::Output {
-        use Bookmark::*;
-
-        match bookmark {
-            Tabidachi => &mut self.tabidachi,
-            Hanasaka => &mut self.hanasaka,
-            Kintaro => &mut self.kintaro,
-            Urashima => &mut self.urashima,
-            Netaro => &mut self.netaro,
-            Kibou => &mut self.kibou,
-            Sarukani => &mut self.sarukani,
-            Taketori => &mut self.taketori,
-            Hohoemi => &mut self.hohoemi,
-            Hien => &mut self.hien,
+/// セーブデータ内の装備インデックスがロード時にどう扱われるか。
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub(crate) enum EquipmentFate {
+    /// そのままの枠に装備される。
+    Kept,
+    /// 無視される。
+    Ignored,
+    /// 別の枠に移って装備される。
+    Moved(EquipmentSlot),
+    /// 別の枠に移るが、その枠本来の装備で上書きされる。
+    Overwritten(EquipmentSlot),
+}
+
+/// 装備枠 `slot` に記録されたインデックスがロード時にどう扱われるかを返す。
+///
+/// `Equipment::normalize()` と同じ規則に従う。
+pub(crate) fn equipment_fate(equipment: &Equipment, slot: EquipmentSlot) -> EquipmentFate {
+    // 移動先の枠本来のインデックスがそのまま装備されるなら、移動した装備は上書きされる。
+    let moved = |dst: EquipmentSlot| {
+        if equipment_fate(equipment, dst) == EquipmentFate::Kept {
+            EquipmentFate::Overwritten(dst)
+        } else {
+            EquipmentFate::Moved(dst)
         }
+    };
+
+    match slot {
+        EquipmentSlot::Helm => match equipment.helm.get() {
+            0..=2 => EquipmentFate::Kept,
+            _ => EquipmentFate::Ignored,
+        },
+        EquipmentSlot::Weapon => match equipment.weapon.get() {
+            0..=10 => EquipmentFate::Kept,
+            11..=12 => EquipmentFate::Ignored,
+            _ => moved(EquipmentSlot::Armor),
+        },
+        EquipmentSlot::Armor => match equipment.armor.get() {
+            0..=9 => EquipmentFate::Kept,
+            10..=11 => EquipmentFate::Ignored,
+            _ => moved(EquipmentSlot::Shoes),
+        },
+        EquipmentSlot::Shoes => match equipment.shoes.get() {
+            0..=4 => EquipmentFate::Kept,
+            5..=6 => EquipmentFate::Ignored,
+            _ => moved(EquipmentSlot::Accessory0),
+        },
+        EquipmentSlot::Accessory0 => match equipment.accessory0.get() {
+            0..=2 => EquipmentFate::Kept,
+            _ => EquipmentFate::Ignored,
+        },
+        EquipmentSlot::Accessory1 => match equipment.accessory1.get() {
+            0..=2 => EquipmentFate::Kept,
+            _ => EquipmentFate::Ignored,
+        },
+        EquipmentSlot::Accessory2 | EquipmentSlot::Accessory3 => EquipmentFate::Kept,
     }
 }
 
@@ -328,6 +387,21 @@ pub(crate) fn bookmark_name(bookmark: Bookmark) -> &'static str {
     }
 }
 
+pub(crate) fn equipment_slot_name(slot: EquipmentSlot) -> &'static str {
+    use EquipmentSlot::*;
+
+    match slot {
+        Helm => "兜",
+        Weapon => "武器",
+        Armor => "鎧",
+        Shoes => "靴",
+        Accessory0 => "いでたち0",
+        Accessory1 => "いでたち1",
+        Accessory2 => "いでたち2",
+        Accessory3 => "いでたち3",
+    }
+}
+
 pub(crate) fn respawn_name(respawn: RespawnId) -> &'static str {
     const TABLE: [&str; (RespawnId::MAX_VALUE + 1) as usize] = [
         "(海上)",
@@ -351,6 +425,44 @@ pub(crate) fn respawn_name(respawn: RespawnId) -> &'static str {
     TABLE[usize::from(respawn)]
 }
 
+/// 復活地点に対応する村のブックマークを返す。海上なら `None` を返す。
+pub(crate) fn respawn_bookmark(respawn: RespawnId) -> Option<Bookmark> {
+    use Bookmark::*;
+
+    const TABLE: [Option<Bookmark>; (RespawnId::MAX_VALUE + 1) as usize] = [
+        None,
+        Some(Tabidachi),
+        Some(Hanasaka),
+        Some(Kintaro),
+        Some(Urashima),
+        Some(Netaro),
+        None,
+        Some(Kibou),
+        Some(Sarukani),
+        Some(Taketori),
+        None,
+        None,
+        None,
+        None,
+        None,
+        Some(Hohoemi),
+    ];
+
+    TABLE[usize::from(respawn)]
+}
+
+/// イベントが起こる村のブックマークを返す。村と結びつかないイベントなら `None` を返す。
+pub(crate) fn event_bookmark(event: Event) -> Option<Bookmark> {
+    match event {
+        Event::Hanasaka => Some(Bookmark::Hanasaka),
+        Event::Kintaro => Some(Bookmark::Kintaro),
+        Event::Urashima => Some(Bookmark::Urashima),
+        Event::Netaro | Event::Murata | Event::Dragon => Some(Bookmark::Netaro),
+        Event::Sarukani => Some(Bookmark::Sarukani),
+        Event::Hohoemi => None,
+    }
+}
+
 pub(crate) fn helm_index_name(helm: HelmIndex) -> &'static str {
     const TABLE: [&str; (HelmIndex::MAX_VALUE + 1) as usize] =
         ["(無装備)", "はちまき", "はちがね", "(空欄: 無視される)"];
@@ -449,6 +561,76 @@ pub(crate) fn accessory3_index_name(accessory3: Accessory3Index) -> &'static str
     TABLE[usize::from(accessory3)]
 }
 
+/// 装備枠 `slot` に記録されたインデックスを返す。
+pub(crate) fn equipment_index(equipment: &Equipment, slot: EquipmentSlot) -> u8 {
+    use EquipmentSlot::*;
+
+    match slot {
+        Helm => equipment.helm.get(),
+        Weapon => equipment.weapon.get(),
+        Armor => equipment.armor.get(),
+        Shoes => equipment.shoes.get(),
+        Accessory0 => equipment.accessory0.get(),
+        Accessory1 => equipment.accessory1.get(),
+        Accessory2 => equipment.accessory2.get(),
+        Accessory3 => equipment.accessory3.get(),
+    }
+}
+
+/// 装備枠 `slot` のインデックスを `index` にする。範囲外なら何もせず `None` を返す。
+pub(crate) fn set_equipment_index(
+    equipment: &mut Equipment,
+    slot: EquipmentSlot,
+    index: u8,
+) -> Option<()> {
+    use EquipmentSlot::*;
+
+    match slot {
+        Helm => equipment.helm = HelmIndex::new(index)?,
+        Weapon => equipment.weapon = WeaponIndex::new(index)?,
+        Armor => equipment.armor = ArmorIndex::new(index)?,
+        Shoes => equipment.shoes = ShoesIndex::new(index)?,
+        Accessory0 => equipment.accessory0 = Accessory0Index::new(index)?,
+        Accessory1 => equipment.accessory1 = Accessory1Index::new(index)?,
+        Accessory2 => equipment.accessory2 = Accessory2Index::new(index)?,
+        Accessory3 => equipment.accessory3 = Accessory3Index::new(index)?,
+    }
+
+    Some(())
+}
+
+/// 装備枠 `slot` のインデックスの最大値を返す。
+pub(crate) fn equipment_index_max(slot: EquipmentSlot) -> u8 {
+    use EquipmentSlot::*;
+
+    match slot {
+        Helm => HelmIndex::MAX_VALUE,
+        Weapon => WeaponIndex::MAX_VALUE,
+        Armor => ArmorIndex::MAX_VALUE,
+        Shoes => ShoesIndex::MAX_VALUE,
+        Accessory0 => Accessory0Index::MAX_VALUE,
+        Accessory1 => Accessory1Index::MAX_VALUE,
+        Accessory2 => Accessory2Index::MAX_VALUE,
+        Accessory3 => Accessory3Index::MAX_VALUE,
+    }
+}
+
+/// 装備枠 `slot` に記録されたインデックスの名前を返す。
+pub(crate) fn equipment_index_name(equipment: &Equipment, slot: EquipmentSlot) -> &'static str {
+    use EquipmentSlot::*;
+
+    match slot {
+        Helm => helm_index_name(equipment.helm),
+        Weapon => weapon_index_name(equipment.weapon),
+        Armor => armor_index_name(equipment.armor),
+        Shoes => shoes_index_name(equipment.shoes),
+        Accessory0 => accessory0_index_name(equipment.accessory0),
+        Accessory1 => accessory1_index_name(equipment.accessory1),
+        Accessory2 => accessory2_index_name(equipment.accessory2),
+        Accessory3 => accessory3_index_name(equipment.accessory3),
+    }
+}
+
 pub(crate) fn item_name(id: ItemId) -> &'static str {
     const TABLE: [&str; ItemId::MAX_VALUE as usize] = [
         "おまんじゅう",
@@ -518,3 +700,95 @@ pub(crate) fn item_name(id: ItemId) -> &'static str {
 
     TABLE[usize::from(id) - 1]
 }
+
+/// アイテムの分類。
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub(crate) enum ItemCategory {
+    /// 食べ物。
+    Food,
+    /// 装備品。
+    Equipment,
+    /// その他の道具。
+    Tool,
+    /// 重複して所持することのない大事なもの。
+    Key,
+}
+
+/// アイテム `id` の分類を返す。
+///
+/// 分類の出典は以下の通り:
+///
+/// * 装備品: 上の `helm_index_name()` などの装備枠の表に載っているアイテム(テストで照合している)。
+/// * 大事なもの: しょうかいじょう・つうこうてがたと、`Treasure` の宝のアイテム(リュウのくびかざり 以降)。
+/// * 食べ物: 出典となる資料はなく、`item_name()` の名前から判断した。
+/// * 道具: 以上のいずれでもないもの。
+pub(crate) fn item_category(id: ItemId) -> ItemCategory {
+    match id.get() {
+        0x01..=0x03 | 0x05 | 0x2B | 0x32 | 0x37..=0x39 => ItemCategory::Food,
+        0x08..=0x0B | 0x0D..=0x24 | 0x27 | 0x29 | 0x2F => ItemCategory::Equipment,
+        0x3A.. => ItemCategory::Key,
+        _ => ItemCategory::Tool,
+    }
+}
+
+pub(crate) fn item_category_name(category: ItemCategory) -> &'static str {
+    match category {
+        ItemCategory::Food => "食べ物",
+        ItemCategory::Equipment => "装備品",
+        ItemCategory::Tool => "道具",
+        ItemCategory::Key => "大事なもの",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::query::normalize_query_input;
+
+    #[test]
+    fn test_item_category_equipment() {
+        // 装備枠の表に載っているアイテムと、分類が装備品のアイテムは一致する。
+        let mut names = vec![];
+        for slot in EquipmentSlot::all() {
+            for index in 0..=equipment_index_max(slot) {
+                let mut equipment = Equipment::default();
+                set_equipment_index(&mut equipment, slot, index).unwrap();
+                let name = equipment_index_name(&equipment, slot);
+                if !name.starts_with('(') {
+                    names.push(normalize_query_input(name, false));
+                }
+            }
+        }
+
+        for id in (1..=ItemId::MAX_VALUE).map(|id| ItemId::new(id).unwrap()) {
+            let listed = names.contains(&normalize_query_input(item_name(id), false));
+            assert_eq!(
+                item_category(id) == ItemCategory::Equipment,
+                listed,
+                "{}",
+                item_name(id)
+            );
+        }
+    }
+
+    #[test]
+    fn test_item_category_key() {
+        let keys: Vec<&str> = (1..=ItemId::MAX_VALUE)
+            .map(|id| ItemId::new(id).unwrap())
+            .filter(|&id| item_category(id) == ItemCategory::Key)
+            .map(item_name)
+            .collect();
+        assert_eq!(
+            keys,
+            [
+                "しょうかいじょう",
+                "つうこうてがた",
+                "リュウのくびかざり",
+                "キンいろのけがわ",
+                "ホトケのおはち",
+                "ホウライのタマ",
+            ]
+        );
+    }
+}
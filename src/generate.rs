@@ -23,7 +23,7 @@ impl<'a> Solver<'a> {
         Self {
             pattern,
             count_max,
-            passwords: Vec::with_capacity(count_max),
+            passwords: vec![],
             chars: Vec::with_capacity(Password::MAX_LEN),
         }
     }
@@ -0,0 +1,94 @@
+//! インベントリの編集操作。
+//!
+//! 原作と同様に空欄は作らず、アイテムは常に先頭から詰めて並べる。
+
+use momoden_password::*;
+
+use crate::game::item_name;
+use crate::query::normalize_query_input;
+use crate::romaji::{kana_to_romaji, normalize_romaji};
+
+/// `from` 番目のアイテムを `to` 番目へ移し、間のアイテムをずらす。範囲外の `to` は末尾とみなす。
+pub(crate) fn move_item(inventory: &mut Inventory, from: usize, to: usize) {
+    if from >= inventory.len() {
+        return;
+    }
+
+    let item = inventory.remove(from);
+    let to = to.min(inventory.len());
+    inventory.insert(to, item);
+}
+
+/// `idx` 番目にアイテム `item` を挿入する。範囲外の `idx` は末尾とみなす。満杯なら何もしない。
+pub(crate) fn insert_item(inventory: &mut Inventory, idx: usize, item: ItemId) {
+    if inventory.is_full() {
+        return;
+    }
+
+    let idx = idx.min(inventory.len());
+    inventory.insert(idx, item);
+}
+
+/// `idx` 番目のアイテムを複製して直後に挿入する。満杯または範囲外なら何もしない。
+pub(crate) fn duplicate_item(inventory: &mut Inventory, idx: usize) {
+    if let Some(&item) = inventory.get(idx) {
+        insert_item(inventory, idx + 1, item);
+    }
+}
+
+/// アイテム `id` (`None` は空欄)が検索文字列 `filter` に合うかどうかを返す。
+///
+/// アイテム名(ひらがな・カタカナを区別しない。`romaji` が真ならローマ字でも可)の部分一致、
+/// または 16 進の ID ("2a", "0x2A" など)の前方一致で判定する。空の `filter` は全てに合う。
+pub(crate) fn item_matches(id: Option<ItemId>, filter: &str, romaji: bool) -> bool {
+    let filter = filter.trim();
+    if filter.is_empty() {
+        return true;
+    }
+
+    let hex = format!("{:02x}", id.map_or(0, |id| id.get()));
+    let filter_hex = filter.to_ascii_lowercase();
+    let filter_hex = filter_hex.strip_prefix("0x").unwrap_or(&filter_hex);
+    if !filter_hex.is_empty()
+        && filter_hex.chars().all(|c| c.is_ascii_hexdigit())
+        && hex.starts_with(filter_hex)
+    {
+        return true;
+    }
+
+    let name = normalize_query_input(id.map_or("(空欄)", item_name), false);
+    if name.contains(&normalize_query_input(filter, false)) {
+        return true;
+    }
+    romaji && filter.is_ascii() && kana_to_romaji(&name).contains(&normalize_romaji(filter))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(id: u8) -> Option<ItemId> {
+        Some(ItemId::new(id).unwrap())
+    }
+
+    #[test]
+    fn test_item_matches() {
+        // 0x1F: あすかのけん, 0x22: ひりゅうのけん, 0x3A: しょうかいじょう
+        assert!(item_matches(item(0x1F), "ケン", false));
+        assert!(item_matches(item(0x1F), "ken", true));
+        assert!(!item_matches(item(0x1F), "ken", false));
+        assert!(item_matches(item(0x22), "hiryuu", true));
+        assert!(item_matches(item(0x22), "HIRYU", true));
+        assert!(item_matches(item(0x3A), "shoukai", true));
+        assert!(item_matches(item(0x3A), "syoukaijou", true));
+        assert!(!item_matches(item(0x3A), "ken", true));
+
+        // ID の前方一致。
+        assert!(item_matches(item(0x1F), "0x1f", false));
+        assert!(item_matches(item(0x1F), "1", false));
+        assert!(!item_matches(item(0x1F), "2", false));
+
+        assert!(item_matches(None, "空欄", false));
+        assert!(item_matches(None, "", false));
+    }
+}
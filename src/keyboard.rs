@@ -0,0 +1,244 @@
+use momoden_password::PasswordChar;
+
+/// パスワード入力画面の文字表の幅。
+pub(crate) const KEYBOARD_WIDTH: usize = 10;
+
+/// パスワード入力画面の文字表の高さ。
+pub(crate) const KEYBOARD_HEIGHT: usize = 7;
+
+/// パスワード入力画面の文字表。`None` は空きマス。
+///
+/// 五十音順に、左右 2 ブロックに分けて並べている。この配置は実機の画面と照合したものではなく、仮定である。
+/// 入力手順の生成(`movie`)や最短入力の探索(`shortest`)はこの配置に依存するので、実機と異なれば結果も誤る。
+#[rustfmt::skip]
+pub(crate) const KEYBOARD: [[Option<PasswordChar>; KEYBOARD_WIDTH]; KEYBOARD_HEIGHT] = {
+    use PasswordChar::*;
+
+    const fn k(pc: PasswordChar) -> Option<PasswordChar> {
+        Some(pc)
+    }
+    const N: Option<PasswordChar> = None;
+
+    [
+        [k(A),  k(I),  k(U),  k(E),  k(O),  k(Na), k(Ni), k(Nu), k(Ne), k(No)],
+        [k(Ka), k(Ki), k(Ku), k(Ke), k(Ko), k(Ha), k(Hi), k(Hu), k(He), k(Ho)],
+        [k(Sa), k(Si), k(Su), k(Se), k(So), k(Ma), k(Mi), k(Mu), k(Me), k(Mo)],
+        [k(Ta), k(Ti), k(Tu), k(Te), k(To), k(Ya), N,     k(Yu), N,     k(Yo)],
+        [k(Ra), k(Ri), k(Ru), k(Re), k(Ro), k(Wa), N,     N,     N,     N    ],
+        [k(Ga), k(Gi), k(Gu), k(Ge), k(Go), k(Za), k(Zi), k(Zu), k(Ze), k(Zo)],
+        [k(Ba), k(Bi), k(Bu), k(Be), k(Bo), k(Pa), k(Pi), k(Pu), k(Pe), k(Po)],
+    ]
+};
+
+/// 文字表上のカーソルの移動方向。
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum Direction {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+impl Direction {
+    pub(crate) fn all() -> impl Iterator<Item = Self> {
+        [Self::Up, Self::Down, Self::Left, Self::Right].into_iter()
+    }
+}
+
+/// 文字表上のカーソルの動き方。
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) struct CursorModel {
+    /// 端から反対側の端へ回り込めるか。
+    pub(crate) wrap: bool,
+    /// 空きマスを飛ばして移動するか。偽なら空きマスにも止まる。
+    pub(crate) skip_gaps: bool,
+}
+
+impl Default for CursorModel {
+    fn default() -> Self {
+        Self {
+            wrap: true,
+            skip_gaps: true,
+        }
+    }
+}
+
+/// 文字表上の位置 (行, 列)。
+pub(crate) type KeyboardPos = (usize, usize);
+
+/// 文字 `pc` の文字表上の位置を返す。
+pub(crate) fn keyboard_position(pc: PasswordChar) -> KeyboardPos {
+    KEYBOARD
+        .iter()
+        .enumerate()
+        .find_map(|(r, row)| {
+            row.iter()
+                .position(|&cell| cell == Some(pc))
+                .map(|c| (r, c))
+        })
+        .expect("every PasswordChar must be on the keyboard")
+}
+
+impl CursorModel {
+    /// `pos` から `dir` 方向に 1 回移動した位置を返す。移動できなければ `None` を返す。
+    pub(crate) fn step(self, pos: KeyboardPos, dir: Direction) -> Option<KeyboardPos> {
+        let (mut r, mut c) = pos;
+        loop {
+            (r, c) = match dir {
+                Direction::Up => (self.step_index(r, KEYBOARD_HEIGHT, false)?, c),
+                Direction::Down => (self.step_index(r, KEYBOARD_HEIGHT, true)?, c),
+                Direction::Left => (r, self.step_index(c, KEYBOARD_WIDTH, false)?),
+                Direction::Right => (r, self.step_index(c, KEYBOARD_WIDTH, true)?),
+            };
+            if (r, c) == pos {
+                return None;
+            }
+            if !self.skip_gaps || KEYBOARD[r][c].is_some() {
+                return Some((r, c));
+            }
+        }
+    }
+
+    fn step_index(self, i: usize, len: usize, forward: bool) -> Option<usize> {
+        match (forward, self.wrap) {
+            (true, _) if i + 1 < len => Some(i + 1),
+            (true, true) => Some(0),
+            (false, _) if i > 0 => Some(i - 1),
+            (false, true) => Some(len - 1),
+            _ => None,
+        }
+    }
+
+    /// `from` から `to` への最短の移動手順を返す。
+    pub(crate) fn path(self, from: KeyboardPos, to: KeyboardPos) -> Vec<Direction> {
+        // 各マスへの最短手順の直前の (位置, 方向)。
+        let mut prev = [[None; KEYBOARD_WIDTH]; KEYBOARD_HEIGHT];
+        let mut visited = [[false; KEYBOARD_WIDTH]; KEYBOARD_HEIGHT];
+        let mut queue = std::collections::VecDeque::from([from]);
+        visited[from.0][from.1] = true;
+
+        while let Some(pos) = queue.pop_front() {
+            if pos == to {
+                break;
+            }
+            for dir in Direction::all() {
+                let Some(next) = self.step(pos, dir) else {
+                    continue;
+                };
+                if !visited[next.0][next.1] {
+                    visited[next.0][next.1] = true;
+                    prev[next.0][next.1] = Some((pos, dir));
+                    queue.push_back(next);
+                }
+            }
+        }
+
+        let mut path = vec![];
+        let mut pos = to;
+        while let Some((p, dir)) = prev[pos.0][pos.1] {
+            path.push(dir);
+            pos = p;
+        }
+        path.reverse();
+        path
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NO_WRAP: CursorModel = CursorModel {
+        wrap: false,
+        skip_gaps: true,
+    };
+    const NO_SKIP: CursorModel = CursorModel {
+        wrap: true,
+        skip_gaps: false,
+    };
+
+    #[test]
+    fn test_keyboard_position() {
+        assert_eq!(keyboard_position(PasswordChar::A), (0, 0));
+        assert_eq!(keyboard_position(PasswordChar::Yu), (3, 7));
+        assert_eq!(keyboard_position(PasswordChar::Po), (6, 9));
+    }
+
+    #[test]
+    fn test_step_wrap() {
+        let model = CursorModel::default();
+        assert_eq!(model.step((0, 0), Direction::Left), Some((0, 9)));
+        assert_eq!(model.step((0, 0), Direction::Up), Some((6, 0)));
+        assert_eq!(model.step((6, 9), Direction::Right), Some((6, 0)));
+        assert_eq!(model.step((6, 9), Direction::Down), Some((0, 9)));
+        assert_eq!(model.step((1, 1), Direction::Right), Some((1, 2)));
+
+        assert_eq!(NO_WRAP.step((0, 0), Direction::Left), None);
+        assert_eq!(NO_WRAP.step((0, 0), Direction::Up), None);
+        assert_eq!(NO_WRAP.step((6, 9), Direction::Right), None);
+        assert_eq!(NO_WRAP.step((0, 0), Direction::Right), Some((0, 1)));
+    }
+
+    #[test]
+    fn test_step_gaps() {
+        let model = CursorModel::default();
+
+        // や行の空きマスを飛ばす。
+        assert_eq!(model.step((3, 5), Direction::Right), Some((3, 7)));
+        assert_eq!(model.step((3, 9), Direction::Left), Some((3, 7)));
+        assert_eq!(NO_SKIP.step((3, 5), Direction::Right), Some((3, 6)));
+
+        // わ行の右側は全て空きマスなので、回り込んで行頭に出る。
+        assert_eq!(model.step((4, 5), Direction::Right), Some((4, 0)));
+        assert_eq!(NO_WRAP.step((4, 5), Direction::Right), None);
+        assert_eq!(NO_SKIP.step((4, 5), Direction::Right), Some((4, 6)));
+
+        // 縦方向にも空きマスを飛ばす。
+        assert_eq!(model.step((2, 6), Direction::Down), Some((5, 6)));
+        assert_eq!(model.step((5, 8), Direction::Up), Some((2, 8)));
+        assert_eq!(NO_SKIP.step((2, 6), Direction::Down), Some((3, 6)));
+    }
+
+    #[test]
+    fn test_path() {
+        fn walk(model: CursorModel, from: KeyboardPos, path: &[Direction]) -> KeyboardPos {
+            path.iter()
+                .fold(from, |pos, &dir| model.step(pos, dir).unwrap())
+        }
+
+        let model = CursorModel::default();
+        let a = keyboard_position(PasswordChar::A);
+        let pos = |pc| keyboard_position(pc);
+
+        assert_eq!(model.path(a, a), []);
+        assert_eq!(model.path(a, pos(PasswordChar::Ko)).len(), 5);
+        assert_eq!(model.path(a, pos(PasswordChar::No)), [Direction::Left]);
+        assert_eq!(model.path(a, pos(PasswordChar::Po)).len(), 2);
+        assert_eq!(
+            model
+                .path(pos(PasswordChar::Ya), pos(PasswordChar::Yu))
+                .len(),
+            1
+        );
+
+        assert_eq!(NO_WRAP.path(a, pos(PasswordChar::No)).len(), 9);
+        // 空きマスを飛ばす分、縦横の距離の和 (15) より短い。
+        assert_eq!(NO_WRAP.path(a, pos(PasswordChar::Po)).len(), 12);
+        assert_eq!(
+            NO_SKIP
+                .path(pos(PasswordChar::Ya), pos(PasswordChar::Yu))
+                .len(),
+            2
+        );
+
+        // 全ての文字の組で、手順をたどると目的の文字に着く。
+        for model in [model, NO_WRAP, NO_SKIP] {
+            for from in PasswordChar::all() {
+                for to in PasswordChar::all() {
+                    let (from, to) = (pos(from), pos(to));
+                    assert_eq!(walk(model, from, &model.path(from, to)), to);
+                }
+            }
+        }
+    }
+}
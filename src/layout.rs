@@ -0,0 +1,192 @@
+//! `SerializedBytes` における各フィールドのビット配置。
+//!
+//! ビット位置はチェックサム(先頭 2 バイト)を除いたデータ部の先頭からの通し番号で、各バイトの下位 6bit を
+//! 上位側から順に数える。データ部の i バイト目はパスワードの i + 2 文字目(0 始まり)に対応する。
+//!
+//! ただしパスワードの各文字は直前の文字にも依存するので、あるフィールドの値を変えるとそれを格納する文字より
+//! 後ろの文字も変わる。また値が変わればチェックサムも(したがって全ての文字も)変わりうる。
+
+use std::ops::Range;
+
+use crate::game::*;
+
+/// ゲーム側がデシリアライズ時に読むデータ部のバイト数。
+pub(crate) const DATA_LEN_READ: usize = 27;
+
+/// セーブデータのフィールド。データ部での配置順に並べている。
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum SavedataField {
+    AgeTimerHi,
+    Purse,
+    Age,
+    Xp,
+    Deposit,
+    Spells,
+    Treasures,
+    Respawn,
+    Bookmarks,
+    Minions,
+    Events,
+    Equipment(EquipmentSlot),
+    Inventory,
+}
+
+impl SavedataField {
+    pub(crate) fn all() -> impl Iterator<Item = Self> {
+        [
+            Self::AgeTimerHi,
+            Self::Purse,
+            Self::Age,
+            Self::Xp,
+            Self::Deposit,
+            Self::Spells,
+            Self::Treasures,
+            Self::Respawn,
+            Self::Bookmarks,
+            Self::Minions,
+            Self::Events,
+        ]
+        .into_iter()
+        .chain(EquipmentSlot::all().into_iter().map(Self::Equipment))
+        .chain(std::iter::once(Self::Inventory))
+    }
+}
+
+impl From<FlagGroup> for SavedataField {
+    fn from(group: FlagGroup) -> Self {
+        match group {
+            FlagGroup::Spells => Self::Spells,
+            FlagGroup::Events => Self::Events,
+            FlagGroup::Treasures => Self::Treasures,
+            FlagGroup::Minions => Self::Minions,
+            FlagGroup::Bookmarks => Self::Bookmarks,
+        }
+    }
+}
+
+pub(crate) fn savedata_field_name(field: SavedataField) -> &'static str {
+    match field {
+        SavedataField::Xp => "経験値",
+        SavedataField::Purse => "所持金",
+        SavedataField::Deposit => "預金",
+        SavedataField::Age => "年齢",
+        SavedataField::AgeTimerHi => "タイマー",
+        SavedataField::Spells => "術",
+        SavedataField::Treasures => "宝物",
+        SavedataField::Respawn => "復活地点",
+        SavedataField::Minions => "お供",
+        SavedataField::Bookmarks => "ブックマーク",
+        SavedataField::Events => "イベント",
+        SavedataField::Equipment(slot) => equipment_slot_name(slot),
+        SavedataField::Inventory => "インベントリ",
+    }
+}
+
+/// フィールド `field` が占めるデータ部のビット範囲を返す。
+///
+/// 複数バイトにまたがる値は上位側の範囲から順に並べる。インベントリは 8 個全て埋まっている場合の範囲。
+// 要素が 1 個の範囲の列も意図したもの。
+#[allow(clippy::single_range_in_vec_init)]
+pub(crate) fn field_bit_ranges(field: SavedataField) -> &'static [Range<usize>] {
+    use EquipmentSlot::*;
+
+    match field {
+        SavedataField::AgeTimerHi => &[0..8],
+        SavedataField::Purse => &[8..16, 24..32],
+        SavedataField::Age => &[16..24],
+        SavedataField::Xp => &[46..54, 32..40],
+        SavedataField::Deposit => &[40..46],
+        SavedataField::Spells => &[54..62],
+        SavedataField::Treasures => &[62..67],
+        SavedataField::Respawn => &[67..71],
+        SavedataField::Bookmarks => &[71..73, 76..84],
+        SavedataField::Minions => &[73..76],
+        SavedataField::Events => &[84..92],
+        SavedataField::Equipment(Helm) => &[92..94],
+        SavedataField::Equipment(Weapon) => &[94..98],
+        SavedataField::Equipment(Armor) => &[98..102],
+        SavedataField::Equipment(Shoes) => &[102..105],
+        SavedataField::Equipment(Accessory0) => &[105..107],
+        SavedataField::Equipment(Accessory1) => &[107..109],
+        SavedataField::Equipment(Accessory2) => &[109..110],
+        SavedataField::Equipment(Accessory3) => &[110..111],
+        SavedataField::Inventory => &[111..159],
+    }
+}
+
+/// フィールド `field` を格納するパスワードの文字位置(0 始まり、昇順)を返す。
+pub(crate) fn field_char_indices(field: SavedataField) -> Vec<usize> {
+    let mut indices: Vec<usize> = field_bit_ranges(field)
+        .iter()
+        .flat_map(|range| range.start / 6..=(range.end - 1) / 6)
+        .map(|i| i + 2)
+        .collect();
+    indices.sort_unstable();
+    indices.dedup();
+    indices
+}
+
+/// パスワードの `idx` 文字目(0 始まり)に格納されるフィールドを返す。
+pub(crate) fn char_fields(idx: usize) -> Vec<SavedataField> {
+    SavedataField::all()
+        .filter(|&field| field_char_indices(field).contains(&idx))
+        .collect()
+}
+
+/// フィールド `field` の配置の説明文を返す。
+pub(crate) fn field_layout_desc(field: SavedataField) -> String {
+    let bits = field_bit_ranges(field)
+        .iter()
+        .map(|range| format!("{}〜{}", range.start, range.end - 1))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let chars = format_char_indices(&field_char_indices(field));
+
+    format!(
+        "パスワードの {chars} 文字目に格納 (データ部のビット {bits})\n\
+         値を変えると、これより後ろの文字とチェックサム (1〜2 文字目) も変わりうる。"
+    )
+}
+
+/// パスワードの各文字位置に格納されるフィールドの一覧の説明文を返す。
+pub(crate) fn password_layout_desc() -> String {
+    let data = (2..DATA_LEN_READ + 2).map(|idx| {
+        let names = char_fields(idx)
+            .into_iter()
+            .map(savedata_field_name)
+            .collect::<Vec<_>>()
+            .join(", ");
+        format!("{}: {names}", idx + 1)
+    });
+
+    std::iter::once("1〜2: チェックサム".to_owned())
+        .chain(data)
+        .chain(std::iter::once(format!(
+            "{}〜: (読まれない)",
+            DATA_LEN_READ + 3
+        )))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// 文字位置(0 始まり、昇順)の列を連続部分をまとめた 1 始まりの表記にする。
+fn format_char_indices(indices: &[usize]) -> String {
+    let mut runs: Vec<(usize, usize)> = vec![];
+    for &idx in indices {
+        match runs.last_mut() {
+            Some((_, last)) if *last + 1 == idx => *last = idx,
+            _ => runs.push((idx, idx)),
+        }
+    }
+
+    runs.into_iter()
+        .map(|(first, last)| {
+            if first == last {
+                format!("{}", first + 1)
+            } else {
+                format!("{}〜{}", first + 1, last + 1)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
@@ -1,7 +1,29 @@
+mod bulk;
+mod csv;
+mod equivalent;
+mod flags;
 mod game;
 mod generate;
+mod inventory;
+mod keyboard;
+mod layout;
+mod memory;
+mod movie;
+mod ocr;
+mod plausibility;
+mod preset;
 mod query;
+mod ram_map;
+mod repair;
+mod romaji;
+mod screen;
+mod shortcut;
+mod shortest;
 mod util;
+mod vanity;
 mod web;
 
+pub use self::query::{
+    normalize_query_input, Pattern, PatternChar, Query, QueryDiagnostic, QueryParseError,
+};
 pub use self::web::start;
@@ -0,0 +1,403 @@
+//! エミュレータのメモリとのやりとり。
+//!
+//! ゲームはパスワード入力画面で、入力中のパスワードを内部値(`PasswordChar::to_inner()`)の列として
+//! RAM 上に持つ。ここではその領域を読み書きすることでゲーム状態を受け渡す。
+//! パスワードが読めればセーブデータの各項目はそれをデコードして得られる。
+//!
+//! 扱える形式は以下の通り:
+//!
+//! * RAM の生ダンプ、およびメモリエディタからコピーした 16 進ダンプ
+//! * FCEUX のセーブステート (.fcs)。RAM (0x800 バイト) を取り出し、書き込み後は無圧縮のセーブステートとして保存する。
+//!
+//! Mesen などそれ以外のセーブステートは形式を解釈しないので、RAM をダンプしてから読み込むこと。
+//!
+//! パスワード領域のアドレスと文字数は利用者が指定する。領域の終端は RAM 上の値からは判別できない
+//! (内部値 0x00 は 'あ' なので、0 埋めされた残りの領域も有効な文字として読めてしまう)。
+//! 現在のパスワードがメモリ上のどこにあるか分かっていれば `find_password()` で探せる。
+//!
+//! パスワード入力画面以外の RAM では、`crate::ram_map::RamMap` で学習した位置からセーブデータの各項目を直接読み書きする。
+
+use momoden_password::*;
+
+/// FCEUX のセーブステートのヘッダ。
+const FCEUX_MAGIC: &[u8; 4] = b"FCSX";
+
+/// FCEUX のセーブステートのヘッダのサイズ。
+const FCEUX_HEADER_LEN: usize = 16;
+
+/// FCEUX のセーブステートで、データが無圧縮であることを示す圧縮後サイズ。
+const FCEUX_UNCOMPRESSED: u32 = u32::MAX;
+
+/// FCEUX のセーブステート内の RAM のチャンク名。
+const FCEUX_RAM_DESC: &[u8; 4] = b"RAM\0";
+
+/// NES の RAM のサイズ。
+const RAM_LEN: usize = 0x800;
+
+/// 読み込んだメモリダンプ。
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub(crate) struct MemoryDump {
+    format: DumpFormat,
+    /// 生ダンプならその内容、セーブステートなら展開後の内容。
+    data: Vec<u8>,
+    /// `data` のうち RAM の範囲。
+    ram: std::ops::Range<usize>,
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub(crate) enum DumpFormat {
+    #[default]
+    Raw,
+    /// FCEUX のセーブステート。ヘッダのバージョン番号を保持する。
+    Fceux { version: u32 },
+}
+
+impl MemoryDump {
+    /// 生ダンプとして読み込む。
+    pub(crate) fn raw(data: Vec<u8>) -> Self {
+        let ram = 0..data.len();
+        Self {
+            format: DumpFormat::Raw,
+            data,
+            ram,
+        }
+    }
+
+    /// ファイルの内容から読み込む。FCEUX のセーブステートでなければ生ダンプとみなす。
+    pub(crate) fn from_file(file: Vec<u8>) -> Result<Self, MemoryError> {
+        if file.starts_with(FCEUX_MAGIC) {
+            Self::from_fceux_state(&file)
+        } else {
+            Ok(Self::raw(file))
+        }
+    }
+
+    fn from_fceux_state(file: &[u8]) -> Result<Self, MemoryError> {
+        let header = file
+            .get(..FCEUX_HEADER_LEN)
+            .ok_or(MemoryError::InvalidState)?;
+        let read_u32 = |i: usize| u32::from_le_bytes(header[i..i + 4].try_into().unwrap());
+        let (len, version, compressed_len) = (read_u32(4), read_u32(8), read_u32(12));
+
+        let body = &file[FCEUX_HEADER_LEN..];
+        let data = if compressed_len == FCEUX_UNCOMPRESSED {
+            body.to_vec()
+        } else {
+            let compressed = body
+                .get(..compressed_len as usize)
+                .ok_or(MemoryError::InvalidState)?;
+            miniz_oxide::inflate::decompress_to_vec_zlib(compressed)
+                .map_err(|_| MemoryError::InvalidState)?
+        };
+        if data.len() != len as usize {
+            return Err(MemoryError::InvalidState);
+        }
+
+        let ram = fceux_find_chunk(&data, FCEUX_RAM_DESC)
+            .filter(|ram| ram.len() == RAM_LEN)
+            .ok_or(MemoryError::InvalidState)?;
+
+        Ok(Self {
+            format: DumpFormat::Fceux { version },
+            data,
+            ram,
+        })
+    }
+
+    pub(crate) fn format(&self) -> DumpFormat {
+        self.format
+    }
+
+    pub(crate) fn ram(&self) -> &[u8] {
+        &self.data[self.ram.clone()]
+    }
+
+    pub(crate) fn ram_mut(&mut self) -> &mut [u8] {
+        &mut self.data[self.ram.clone()]
+    }
+
+    /// ファイルとして保存する内容を返す。FCEUX のセーブステートは無圧縮で書き出す。
+    pub(crate) fn to_file(&self) -> Vec<u8> {
+        match self.format {
+            DumpFormat::Raw => self.data.clone(),
+            DumpFormat::Fceux { version } => {
+                let mut file = Vec::with_capacity(FCEUX_HEADER_LEN + self.data.len());
+                file.extend_from_slice(FCEUX_MAGIC);
+                file.extend_from_slice(&(self.data.len() as u32).to_le_bytes());
+                file.extend_from_slice(&version.to_le_bytes());
+                file.extend_from_slice(&FCEUX_UNCOMPRESSED.to_le_bytes());
+                file.extend_from_slice(&self.data);
+                file
+            }
+        }
+    }
+
+    /// 保存時のファイル名。
+    pub(crate) fn file_name(&self) -> &'static str {
+        match self.format {
+            DumpFormat::Raw => "ram.bin",
+            DumpFormat::Fceux { .. } => "state.fcs",
+        }
+    }
+}
+
+/// FCEUX のセーブステートの展開後のデータから、名前が `desc` のチャンクのデータの範囲を探す。
+///
+/// データは「種別 (1 バイト)・サイズ (4 バイト)・チャンク列」のセクションの列で、
+/// 各チャンクは「名前 (4 バイト)・サイズ (4 バイト)・データ」からなる。
+fn fceux_find_chunk(data: &[u8], desc: &[u8; 4]) -> Option<std::ops::Range<usize>> {
+    let read_u32 = |i: usize| -> Option<usize> {
+        let bytes = data.get(i..i + 4)?;
+        Some(u32::from_le_bytes(bytes.try_into().unwrap()) as usize)
+    };
+
+    let mut section = 0;
+    while section < data.len() {
+        let section_len = read_u32(section + 1)?;
+        let section_end = (section + 5).checked_add(section_len)?;
+
+        let mut chunk = section + 5;
+        while chunk < section_end {
+            let chunk_len = read_u32(chunk + 4)?;
+            let start = chunk + 8;
+            let end = start.checked_add(chunk_len)?;
+            if end > section_end {
+                return None;
+            }
+            if data[chunk..chunk + 4] == desc[..] {
+                return Some(start..end);
+            }
+            chunk = end;
+        }
+
+        section = section_end;
+    }
+
+    None
+}
+
+/// "00 36 3F" のような空白区切りの 16 進ダンプをバイト列に変換する。
+pub(crate) fn parse_hex_dump(s: &str) -> Result<Vec<u8>, MemoryError> {
+    s.split_whitespace()
+        .map(|token| {
+            u8::from_str_radix(token, 16).map_err(|_| MemoryError::InvalidHex(token.to_owned()))
+        })
+        .collect()
+}
+
+/// メモリの `offset` から始まる `len` 文字のパスワード領域を読み、パスワードを得る。
+pub(crate) fn read_password(
+    memory: &[u8],
+    offset: usize,
+    len: usize,
+) -> Result<Password, MemoryError> {
+    let src = memory
+        .get(offset..)
+        .and_then(|tail| tail.get(..len))
+        .ok_or(MemoryError::OutOfRange)?;
+
+    let chars: Vec<PasswordChar> = src
+        .iter()
+        .map(|&b| PasswordChar::from_inner(b).ok_or(MemoryError::InvalidChar(b)))
+        .collect::<Result<_, _>>()?;
+
+    let password = Password::new(&chars).ok_or(MemoryError::InvalidLen)?;
+    if !password.is_valid() {
+        return Err(MemoryError::InvalidChecksum);
+    }
+
+    Ok(password)
+}
+
+/// メモリの `offset` 以降にパスワードを書き込む。
+pub(crate) fn write_password(
+    memory: &mut [u8],
+    offset: usize,
+    password: &Password,
+) -> Result<(), MemoryError> {
+    let dst = memory
+        .get_mut(offset..)
+        .and_then(|tail| tail.get_mut(..password.len()))
+        .ok_or(MemoryError::OutOfRange)?;
+
+    for (d, &pc) in dst.iter_mut().zip(password.iter()) {
+        *d = pc.to_inner();
+    }
+
+    Ok(())
+}
+
+/// メモリ上でパスワードが書かれている位置を探す。
+pub(crate) fn find_password(memory: &[u8], password: &Password) -> Option<usize> {
+    let inner: Vec<u8> = password.iter().map(|pc| pc.to_inner()).collect();
+    memory
+        .windows(inner.len())
+        .position(|window| window == inner)
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) enum MemoryError {
+    InvalidHex(String),
+    InvalidState,
+    OutOfRange,
+    InvalidChar(u8),
+    InvalidLen,
+    InvalidChecksum,
+}
+
+impl std::fmt::Display for MemoryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::InvalidHex(token) => write!(f, "16 進数として不正: '{token}'"),
+            Self::InvalidState => f.write_str("FCEUX のセーブステートとして読めない"),
+            Self::OutOfRange => f.write_str("パスワード領域がメモリの範囲外"),
+            Self::InvalidChar(b) => write!(f, "パスワードの文字の内部値として不正: {b:02X}"),
+            Self::InvalidLen => write!(
+                f,
+                "パスワードの文字数は {}..={} でなければならない",
+                Password::MIN_LEN,
+                Password::MAX_LEN
+            ),
+            Self::InvalidChecksum => {
+                f.write_str("指定した領域のパスワードのチェックサムが合わない")
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::game::save_to_password;
+
+    fn test_password() -> Password {
+        save_to_password(&Savedata::default())
+    }
+
+    fn fceux_chunk(desc: &[u8; 4], data: &[u8]) -> Vec<u8> {
+        let mut chunk = desc.to_vec();
+        chunk.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        chunk.extend_from_slice(data);
+        chunk
+    }
+
+    fn fceux_section(ty: u8, chunks: &[Vec<u8>]) -> Vec<u8> {
+        let body = chunks.concat();
+        let mut section = vec![ty];
+        section.extend_from_slice(&(body.len() as u32).to_le_bytes());
+        section.extend_from_slice(&body);
+        section
+    }
+
+    /// RAM の i バイト目が i % 64 である FCEUX のセーブステートの、展開後のデータ。
+    fn fceux_body() -> Vec<u8> {
+        let ram: Vec<u8> = (0..RAM_LEN).map(|i| (i % 64) as u8).collect();
+        [
+            fceux_section(
+                1,
+                &[
+                    fceux_chunk(b"PC\0\0", &[0x00, 0x80]),
+                    fceux_chunk(b"RAM\0", &ram),
+                ],
+            ),
+            fceux_section(2, &[fceux_chunk(b"PPUR", &[0; 4])]),
+        ]
+        .concat()
+    }
+
+    fn fceux_state(body: &[u8], compressed: Option<&[u8]>) -> Vec<u8> {
+        let mut file = FCEUX_MAGIC.to_vec();
+        file.extend_from_slice(&(body.len() as u32).to_le_bytes());
+        file.extend_from_slice(&22020_u32.to_le_bytes());
+        match compressed {
+            Some(compressed) => {
+                file.extend_from_slice(&(compressed.len() as u32).to_le_bytes());
+                file.extend_from_slice(compressed);
+            }
+            None => {
+                file.extend_from_slice(&FCEUX_UNCOMPRESSED.to_le_bytes());
+                file.extend_from_slice(body);
+            }
+        }
+        file
+    }
+
+    #[test]
+    fn test_read_write_password() {
+        let password = test_password();
+        let mut memory = vec![0; 0x100];
+        write_password(&mut memory, 0x10, &password).unwrap();
+
+        assert_eq!(find_password(&memory, &password), Some(0x10));
+        assert_eq!(
+            read_password(&memory, 0x10, password.len()),
+            Ok(password.clone())
+        );
+
+        // 後続の 0x00 は 'あ' として読めるが、文字数を変えればチェックサムが合わない。
+        assert_eq!(
+            read_password(&memory, 0x10, password.len() + 1),
+            Err(MemoryError::InvalidChecksum)
+        );
+        assert_eq!(
+            read_password(&memory, 0xF0, 0x20),
+            Err(MemoryError::OutOfRange)
+        );
+        memory[0x10] = 0x40;
+        assert_eq!(
+            read_password(&memory, 0x10, password.len()),
+            Err(MemoryError::InvalidChar(0x40))
+        );
+    }
+
+    #[test]
+    fn test_fceux_state() {
+        let body = fceux_body();
+        let expected_ram: Vec<u8> = (0..RAM_LEN).map(|i| (i % 64) as u8).collect();
+
+        // zlib で圧縮したもの。
+        let compressed: Vec<u8> = [
+            "78da6314e2606008706660606200828620475f0606a008032313330b2b1b3b072717370f2f1fbf80a090b0",
+            "88a898b884a494b48cac9cbc82a292b28aaa9aba86a696b68eae9ebe81a191b189a999b985a595b58dad9d",
+            "fda8fe51fda3fa47f58fea1fd53faa7fe8e967e201b601020242835818200000ddc6ff72",
+        ]
+        .concat()
+        .as_bytes()
+        .chunks(2)
+        .map(|hex| u8::from_str_radix(std::str::from_utf8(hex).unwrap(), 16).unwrap())
+        .collect();
+        let dump = MemoryDump::from_file(fceux_state(&body, Some(&compressed))).unwrap();
+        assert_eq!(dump.format(), DumpFormat::Fceux { version: 22020 });
+        assert_eq!(dump.ram(), expected_ram);
+
+        // 書き込んだものは無圧縮のセーブステートとして保存され、読み直せる。
+        let password = test_password();
+        let mut dump = MemoryDump::from_file(fceux_state(&body, None)).unwrap();
+        assert_eq!(dump.ram(), expected_ram);
+        write_password(dump.ram_mut(), 0x123, &password).unwrap();
+
+        let file = dump.to_file();
+        assert_eq!(file.len(), FCEUX_HEADER_LEN + body.len());
+        let dump = MemoryDump::from_file(file).unwrap();
+        assert_eq!(
+            read_password(dump.ram(), 0x123, password.len()),
+            Ok(password)
+        );
+        assert_eq!(dump.ram()[..0x123], expected_ram[..0x123]);
+
+        // 壊れたセーブステート。
+        let mut broken = fceux_state(&body, Some(&compressed));
+        broken.truncate(broken.len() - 1);
+        assert_eq!(
+            MemoryDump::from_file(broken),
+            Err(MemoryError::InvalidState)
+        );
+
+        // 先頭が "FCSX" でなければ生ダンプとして扱う。
+        let dump = MemoryDump::from_file(vec![1, 2, 3]).unwrap();
+        assert_eq!(dump.format(), DumpFormat::Raw);
+        assert_eq!(dump.ram(), [1, 2, 3]);
+        assert_eq!(dump.to_file(), [1, 2, 3]);
+    }
+}
@@ -0,0 +1,712 @@
+//! パスワードを自動入力するエミュレータ用入力ムービーの生成。
+//!
+//! パスワード入力画面の文字表の上でカーソルを最短手順で動かし、各文字で A ボタンを押し、
+//! 最後に START ボタンで確定する入力列を作る。
+//!
+//! ムービーは電源投入からではなく、利用者が用意したセーブステート(パスワード入力画面でカーソルが「あ」に
+//! ある状態で作ったもの)から再生する。FM2 には FCEUX のセーブステート (.fcs) をそのまま、
+//! BK2 には BizHawk のセーブステートからコアの状態 (Core.bin) を取り出して埋め込む。
+//! ROM のチェックサムも再生時に照合されるので、ROM ファイルから計算して埋める。
+
+use base64::Engine as _;
+use momoden_password::*;
+
+use crate::keyboard::*;
+
+/// iNES ヘッダ。
+const INES_MAGIC: &[u8; 4] = b"NES\x1A";
+
+/// iNES ヘッダのサイズ。
+const INES_HEADER_LEN: usize = 16;
+
+/// iNES のトレーナーのサイズ。
+const INES_TRAINER_LEN: usize = 512;
+
+/// FCEUX のセーブステートのヘッダ。
+const FCEUX_STATE_MAGIC: &[u8; 4] = b"FCSX";
+
+/// zip のローカルファイルヘッダのシグネチャ。BizHawk のセーブステートは zip アーカイブ。
+const ZIP_LOCAL_MAGIC: u32 = 0x0403_4B50;
+
+/// zip のセントラルディレクトリのヘッダのシグネチャ。
+const ZIP_CENTRAL_MAGIC: u32 = 0x0201_4B50;
+
+/// zip の終端レコードのシグネチャ。
+const ZIP_END_MAGIC: u32 = 0x0605_4B50;
+
+/// BizHawk のセーブステート内のコアの状態のファイル名。
+const BIZHAWK_CORE_STATE: &str = "Core.bin";
+
+/// コントローラのボタン。
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum Button {
+    Up,
+    Down,
+    Left,
+    Right,
+    Start,
+    A,
+}
+
+impl From<Direction> for Button {
+    fn from(dir: Direction) -> Self {
+        match dir {
+            Direction::Up => Self::Up,
+            Direction::Down => Self::Down,
+            Direction::Left => Self::Left,
+            Direction::Right => Self::Right,
+        }
+    }
+}
+
+/// 入力ムービー生成の設定。
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) struct MovieConfig {
+    pub(crate) cursor: CursorModel,
+    /// 入力 1 回あたりのフレーム数。最初の 1 フレームだけボタンを押し、残りは離す。
+    pub(crate) frames_per_input: usize,
+}
+
+impl MovieConfig {
+    pub(crate) const FRAMES_PER_INPUT_MIN: usize = 2;
+    pub(crate) const FRAMES_PER_INPUT_MAX: usize = 600;
+}
+
+impl Default for MovieConfig {
+    fn default() -> Self {
+        Self {
+            cursor: Default::default(),
+            frames_per_input: Self::FRAMES_PER_INPUT_MIN,
+        }
+    }
+}
+
+/// ムービーの生成に使う、利用者が読み込んだファイルの内容。
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub(crate) struct MovieSource {
+    rom: Option<RomHashes>,
+    /// FCEUX のセーブステートの内容。
+    fceux_state: Option<Vec<u8>>,
+    /// BizHawk のセーブステート内のコアの状態。
+    bizhawk_state: Option<Vec<u8>>,
+}
+
+/// ROM のチェックサム。
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+struct RomHashes {
+    /// FCEUX が照合する、PRG と CHR の MD5。
+    md5: [u8; 16],
+    /// BizHawk が照合する、PRG と CHR の SHA-1。
+    sha1: [u8; 20],
+}
+
+/// `MovieSource::load()` で読み込んだファイルの種類。
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum MovieFileKind {
+    Rom,
+    FceuxState,
+    BizHawkState,
+}
+
+impl MovieSource {
+    /// ROM (iNES 形式)、FCEUX のセーブステート、BizHawk のセーブステートのいずれかを読み込む。種類は内容から判別する。
+    pub(crate) fn load(&mut self, file: &[u8]) -> Result<MovieFileKind, MovieError> {
+        if file.starts_with(INES_MAGIC) {
+            self.rom = Some(rom_hashes(file).ok_or(MovieError::InvalidRom)?);
+            Ok(MovieFileKind::Rom)
+        } else if file.starts_with(FCEUX_STATE_MAGIC) {
+            self.fceux_state = Some(file.to_vec());
+            Ok(MovieFileKind::FceuxState)
+        } else if file.starts_with(&ZIP_LOCAL_MAGIC.to_le_bytes()) {
+            let state = zip_entry(file, BIZHAWK_CORE_STATE).ok_or(MovieError::InvalidState)?;
+            self.bizhawk_state = Some(state);
+            Ok(MovieFileKind::BizHawkState)
+        } else {
+            Err(MovieError::UnknownFile)
+        }
+    }
+
+    pub(crate) fn has_rom(&self) -> bool {
+        self.rom.is_some()
+    }
+
+    pub(crate) fn has_fceux_state(&self) -> bool {
+        self.fceux_state.is_some()
+    }
+
+    pub(crate) fn has_bizhawk_state(&self) -> bool {
+        self.bizhawk_state.is_some()
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) enum MovieError {
+    UnknownFile,
+    InvalidRom,
+    InvalidState,
+    NoRom,
+    NoState,
+}
+
+impl std::fmt::Display for MovieError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnknownFile => f.write_str("ROM・セーブステートのいずれの形式でもない"),
+            Self::InvalidRom => f.write_str("ROM として読めない"),
+            Self::InvalidState => f.write_str("BizHawk のセーブステートとして読めない"),
+            Self::NoRom => f.write_str("ROM を読み込んでいない"),
+            Self::NoState => f.write_str("このエミュレータのセーブステートを読み込んでいない"),
+        }
+    }
+}
+
+/// iNES 形式の ROM の、ヘッダとトレーナーを除いた PRG と CHR のチェックサムを計算する。
+fn rom_hashes(file: &[u8]) -> Option<RomHashes> {
+    let header = file.get(..INES_HEADER_LEN)?;
+    let prg_len = 0x4000 * usize::from(header[4]);
+    let chr_len = 0x2000 * usize::from(header[5]);
+    let start = if header[6] & 0x04 != 0 {
+        INES_HEADER_LEN + INES_TRAINER_LEN
+    } else {
+        INES_HEADER_LEN
+    };
+    let data = file.get(start..start + prg_len + chr_len)?;
+
+    Some(RomHashes {
+        md5: md5::compute(data).0,
+        sha1: sha1_smol::Sha1::from(data).digest().bytes(),
+    })
+}
+
+/// パスワードを入力するボタン操作の列を返す。カーソルは「あ」の位置から始まるものとする。
+pub(crate) fn password_inputs(password: &Password, cursor: CursorModel) -> Vec<Button> {
+    let mut inputs = vec![];
+    let mut pos = (0, 0);
+    for &pc in password.iter() {
+        let to = keyboard_position(pc);
+        inputs.extend(cursor.path(pos, to).into_iter().map(Button::from));
+        inputs.push(Button::A);
+        pos = to;
+    }
+    inputs.push(Button::Start);
+    inputs
+}
+
+/// ボタン操作の列をフレームごとの入力(押されたボタン)に展開する。
+fn frames(password: &Password, config: MovieConfig) -> Vec<Option<Button>> {
+    let frames_per_input = config.frames_per_input.clamp(
+        MovieConfig::FRAMES_PER_INPUT_MIN,
+        MovieConfig::FRAMES_PER_INPUT_MAX,
+    );
+    password_inputs(password, config.cursor)
+        .into_iter()
+        .flat_map(|button| {
+            std::iter::once(Some(button)).chain(std::iter::repeat_n(None, frames_per_input - 1))
+        })
+        .collect()
+}
+
+/// 1 フレームの入力を、パッド表記 `layout` の形式の文字列にする。
+///
+/// `keys[i]` が押されていれば `layout` の i 文字目、そうでなければ '.' を置く。
+fn format_pad(button: Option<Button>, layout: &str, keys: &[Option<Button>]) -> String {
+    layout
+        .chars()
+        .zip(keys)
+        .map(|(c, &key)| {
+            if key.is_some() && key == button {
+                c
+            } else {
+                '.'
+            }
+        })
+        .collect()
+}
+
+/// FCEUX の入力ムービー (FM2) を生成する。ROM と FCEUX のセーブステートが必要。
+pub(crate) fn to_fm2(
+    password: &Password,
+    config: MovieConfig,
+    source: &MovieSource,
+) -> Result<String, MovieError> {
+    const LAYOUT: &str = "RLDUTSBA";
+    const KEYS: &[Option<Button>] = &[
+        Some(Button::Right),
+        Some(Button::Left),
+        Some(Button::Down),
+        Some(Button::Up),
+        Some(Button::Start),
+        None,
+        None,
+        Some(Button::A),
+    ];
+
+    let rom = source.rom.ok_or(MovieError::NoRom)?;
+    let state = source.fceux_state.as_deref().ok_or(MovieError::NoState)?;
+    let base64 = base64::engine::general_purpose::STANDARD;
+
+    let mut fm2 = [
+        "version 3",
+        "emuVersion 22020",
+        "rerecordCount 0",
+        "palFlag 0",
+        "romFilename Momotarou Densetsu (J)",
+        &format!("romChecksum base64:{}", base64.encode(rom.md5)),
+        &format!("guid {}", movie_guid(password, &rom, state)),
+        "fourscore 0",
+        "microphone 0",
+        "port0 1",
+        "port1 1",
+        "port2 0",
+        "FDS 0",
+        "NewPPU 0",
+        "comment author momoden-password-calc",
+        &format!("comment password {}", password.display()),
+        &format!("savestate base64:{}", base64.encode(state)),
+    ]
+    .join("\n");
+    fm2.push('\n');
+
+    for button in frames(password, config) {
+        let pad = format_pad(button, LAYOUT, KEYS);
+        fm2.push_str(&format!("|0|{pad}|........||\n"));
+    }
+
+    Ok(fm2)
+}
+
+/// BizHawk の入力ムービー (BK2) を生成する。ROM と BizHawk のセーブステートが必要。
+///
+/// BK2 は zip アーカイブなので、そのバイト列を返す。
+pub(crate) fn to_bk2(
+    password: &Password,
+    config: MovieConfig,
+    source: &MovieSource,
+) -> Result<Vec<u8>, MovieError> {
+    const LAYOUT: &str = "UDLRSsBA";
+    const KEYS: &[Option<Button>] = &[
+        Some(Button::Up),
+        Some(Button::Down),
+        Some(Button::Left),
+        Some(Button::Right),
+        Some(Button::Start),
+        None,
+        None,
+        Some(Button::A),
+    ];
+
+    let rom = source.rom.ok_or(MovieError::NoRom)?;
+    let state = source.bizhawk_state.as_deref().ok_or(MovieError::NoState)?;
+
+    let header = [
+        "MovieVersion BizHawk v2.0.0",
+        "Author momoden-password-calc",
+        "emuVersion Version 2.9",
+        "Platform NES",
+        "GameName Momotarou Densetsu (J)",
+        &format!("SHA1 {}", hex_upper(&rom.sha1)),
+        "Core NesHawk",
+        "rerecordCount 0",
+        "StartsFromSavestate True",
+        "",
+    ]
+    .join("\n");
+
+    let mut input = "[Input]\n\
+        LogKey:#Reset|Power|#P1 Up|P1 Down|P1 Left|P1 Right|P1 Start|P1 Select|P1 B|P1 A|\
+        #P2 Up|P2 Down|P2 Left|P2 Right|P2 Start|P2 Select|P2 B|P2 A|\n"
+        .to_owned();
+    for button in frames(password, config) {
+        let pad = format_pad(button, LAYOUT, KEYS);
+        input.push_str(&format!("|..|{pad}|........|\n"));
+    }
+    input.push_str("[/Input]\n");
+
+    let comments = format!("password {}\n", password.display());
+
+    Ok(zip_store(&[
+        ("Header.txt", header.as_bytes()),
+        ("Input Log.txt", input.as_bytes()),
+        ("Comments.txt", comments.as_bytes()),
+        (BIZHAWK_CORE_STATE, state),
+    ]))
+}
+
+/// FM2 の GUID。乱数の代わりに、パスワード・ROM・セーブステートの MD5 から作る。
+fn movie_guid(password: &Password, rom: &RomHashes, state: &[u8]) -> String {
+    let mut context = md5::Context::new();
+    context.consume(password.display().to_string());
+    context.consume(rom.md5);
+    context.consume(state);
+    let hex = hex_upper(&context.compute().0);
+
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    )
+}
+
+fn hex_upper(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02X}")).collect()
+}
+
+/// 無圧縮の zip アーカイブを作る。
+fn zip_store(files: &[(&str, &[u8])]) -> Vec<u8> {
+    let mut out = vec![];
+    let mut central = vec![];
+
+    for &(name, data) in files {
+        let offset = out.len() as u32;
+        let crc = crc32(data);
+        let size = data.len() as u32;
+        let name_len = name.len() as u16;
+
+        // local file header
+        out.extend(ZIP_LOCAL_MAGIC.to_le_bytes());
+        out.extend(20_u16.to_le_bytes()); // version needed
+        out.extend(0_u16.to_le_bytes()); // flags
+        out.extend(0_u16.to_le_bytes()); // method: stored
+        out.extend(0_u32.to_le_bytes()); // time, date
+        out.extend(crc.to_le_bytes());
+        out.extend(size.to_le_bytes());
+        out.extend(size.to_le_bytes());
+        out.extend(name_len.to_le_bytes());
+        out.extend(0_u16.to_le_bytes()); // extra length
+        out.extend(name.as_bytes());
+        out.extend(data);
+
+        // central directory header
+        central.extend(ZIP_CENTRAL_MAGIC.to_le_bytes());
+        central.extend(20_u16.to_le_bytes()); // version made by
+        central.extend(20_u16.to_le_bytes()); // version needed
+        central.extend(0_u16.to_le_bytes()); // flags
+        central.extend(0_u16.to_le_bytes()); // method: stored
+        central.extend(0_u32.to_le_bytes()); // time, date
+        central.extend(crc.to_le_bytes());
+        central.extend(size.to_le_bytes());
+        central.extend(size.to_le_bytes());
+        central.extend(name_len.to_le_bytes());
+        central.extend([0_u8; 12]); // extra, comment, disk, attributes
+        central.extend(offset.to_le_bytes());
+        central.extend(name.as_bytes());
+    }
+
+    let central_offset = out.len() as u32;
+    let central_size = central.len() as u32;
+    let count = files.len() as u16;
+    out.extend(central);
+
+    // end of central directory
+    out.extend(ZIP_END_MAGIC.to_le_bytes());
+    out.extend([0_u8; 4]); // disk numbers
+    out.extend(count.to_le_bytes());
+    out.extend(count.to_le_bytes());
+    out.extend(central_size.to_le_bytes());
+    out.extend(central_offset.to_le_bytes());
+    out.extend(0_u16.to_le_bytes()); // comment length
+
+    out
+}
+
+/// zip アーカイブ `zip` から `name` のファイルの内容を取り出す。無圧縮と deflate に対応する。
+fn zip_entry(zip: &[u8], name: &str) -> Option<Vec<u8>> {
+    let read_u16 = |i: usize| Some(u16::from_le_bytes(zip.get(i..i + 2)?.try_into().unwrap()));
+    let read_u32 = |i: usize| Some(u32::from_le_bytes(zip.get(i..i + 4)?.try_into().unwrap()));
+
+    // 終端レコードは末尾のコメントの手前にある。
+    let end = (0..zip.len().saturating_sub(21))
+        .rev()
+        .find(|&i| read_u32(i) == Some(ZIP_END_MAGIC))?;
+    let count = read_u16(end + 10)?;
+    let mut pos = read_u32(end + 16)? as usize;
+
+    for _ in 0..count {
+        if read_u32(pos)? != ZIP_CENTRAL_MAGIC {
+            return None;
+        }
+        let method = read_u16(pos + 10)?;
+        let crc = read_u32(pos + 16)?;
+        let compressed_len = read_u32(pos + 20)? as usize;
+        let len = read_u32(pos + 24)? as usize;
+        let name_len = usize::from(read_u16(pos + 28)?);
+        let extra_len = usize::from(read_u16(pos + 30)?);
+        let comment_len = usize::from(read_u16(pos + 32)?);
+        let local = read_u32(pos + 42)? as usize;
+        let entry_name = zip.get(pos + 46..pos + 46 + name_len)?;
+        pos += 46 + name_len + extra_len + comment_len;
+
+        if entry_name != name.as_bytes() {
+            continue;
+        }
+
+        if read_u32(local)? != ZIP_LOCAL_MAGIC {
+            return None;
+        }
+        let start =
+            local + 30 + usize::from(read_u16(local + 26)?) + usize::from(read_u16(local + 28)?);
+        let compressed = zip.get(start..start + compressed_len)?;
+        let data = match method {
+            0 => compressed.to_vec(),
+            8 => miniz_oxide::inflate::decompress_to_vec(compressed).ok()?,
+            _ => return None,
+        };
+        return (data.len() == len && crc32(&data) == crc).then_some(data);
+    }
+
+    None
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0_u32;
+    for &b in data {
+        crc ^= u32::from(b);
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// PRG 16 KiB (全て 0)、CHR なしの ROM。
+    fn rom(trainer: bool) -> Vec<u8> {
+        let mut rom = INES_MAGIC.to_vec();
+        rom.extend([1, 0, if trainer { 0x04 } else { 0 }]);
+        rom.resize(INES_HEADER_LEN, 0);
+        if trainer {
+            rom.extend([0xFF; INES_TRAINER_LEN]);
+        }
+        rom.extend([0; 0x4000]);
+        rom
+    }
+
+    fn source() -> MovieSource {
+        let mut source = MovieSource::default();
+        source.load(&rom(false)).unwrap();
+        source.load(b"FCSX state").unwrap();
+        source
+            .load(&zip_store(&[(BIZHAWK_CORE_STATE, b"core")]))
+            .unwrap();
+        source
+    }
+
+    fn config() -> MovieConfig {
+        MovieConfig {
+            cursor: CursorModel {
+                wrap: true,
+                skip_gaps: true,
+            },
+            frames_per_input: 2,
+        }
+    }
+
+    #[test]
+    fn test_crc32() {
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+    }
+
+    #[test]
+    fn test_zip_store() {
+        let zip = zip_store(&[("a.txt", b"abc"), ("b", b"")]);
+        let u16_at = |i: usize| u16::from_le_bytes([zip[i], zip[i + 1]]);
+        let u32_at = |i: usize| u32::from_le_bytes(zip[i..i + 4].try_into().unwrap());
+        let crc = crc32(b"abc");
+
+        // 1 つ目のローカルファイルヘッダ
+        assert_eq!(u32_at(0), ZIP_LOCAL_MAGIC);
+        assert_eq!(u16_at(4), 20);
+        assert_eq!(u16_at(8), 0);
+        assert_eq!(u32_at(14), crc);
+        assert_eq!(u32_at(18), 3);
+        assert_eq!(u32_at(22), 3);
+        assert_eq!(u16_at(26), 5);
+        assert_eq!(u16_at(28), 0);
+        assert_eq!(&zip[30..38], b"a.txtabc");
+
+        // 2 つ目のローカルファイルヘッダ
+        assert_eq!(u32_at(38), ZIP_LOCAL_MAGIC);
+        assert_eq!(u32_at(38 + 14), 0);
+        assert_eq!(&zip[38 + 30..38 + 31], b"b");
+
+        // セントラルディレクトリ
+        let central = 38 + 31;
+        assert_eq!(u32_at(central), ZIP_CENTRAL_MAGIC);
+        assert_eq!(u16_at(central + 10), 0);
+        assert_eq!(u32_at(central + 16), crc);
+        assert_eq!(u32_at(central + 20), 3);
+        assert_eq!(u32_at(central + 24), 3);
+        assert_eq!(u16_at(central + 28), 5);
+        assert_eq!(u32_at(central + 42), 0);
+        assert_eq!(&zip[central + 46..central + 51], b"a.txt");
+        assert_eq!(u32_at(central + 51), ZIP_CENTRAL_MAGIC);
+        assert_eq!(u32_at(central + 51 + 42), 38);
+
+        // 終端レコード
+        let end = zip.len() - 22;
+        assert_eq!(u32_at(end), ZIP_END_MAGIC);
+        assert_eq!(u16_at(end + 8), 2);
+        assert_eq!(u16_at(end + 10), 2);
+        assert_eq!(u32_at(end + 12) as usize, end - central);
+        assert_eq!(u32_at(end + 16) as usize, central);
+        assert_eq!(u16_at(end + 20), 0);
+    }
+
+    #[test]
+    fn test_zip_entry() {
+        let zip = zip_store(&[("a.txt", b"abc"), ("b", b"")]);
+        assert_eq!(zip_entry(&zip, "a.txt").as_deref(), Some(&b"abc"[..]));
+        assert_eq!(zip_entry(&zip, "b").as_deref(), Some(&b""[..]));
+        assert_eq!(zip_entry(&zip, "c"), None);
+
+        // CRC が合わなければ読まない。
+        let mut broken = zip.clone();
+        broken[35] = b'x';
+        assert_eq!(zip_entry(&broken, "a.txt"), None);
+        assert_eq!(zip_entry(&zip[..zip.len() - 1], "a.txt"), None);
+    }
+
+    #[test]
+    fn test_movie_source_load() {
+        let mut source = MovieSource::default();
+        assert!(!source.has_rom());
+
+        assert_eq!(source.load(&rom(false)), Ok(MovieFileKind::Rom));
+        let hashes = source.rom.unwrap();
+        assert_eq!(hex_upper(&hashes.md5), "CE338FE6899778AACFC28414F2D9498B");
+        assert_eq!(
+            hex_upper(&hashes.sha1),
+            "897256B6709E1A4DA9DABA92B6BDE39CCFCCD8C1"
+        );
+        // トレーナーはチェックサムに含めない。
+        assert_eq!(rom_hashes(&rom(true)), Some(hashes));
+        assert_eq!(
+            source.load(&rom(false)[..0x1000]),
+            Err(MovieError::InvalidRom)
+        );
+
+        assert_eq!(source.load(b"FCSX state"), Ok(MovieFileKind::FceuxState));
+        assert!(source.has_fceux_state());
+
+        let state = zip_store(&[("Framebuffer.bin", b"fb"), (BIZHAWK_CORE_STATE, b"core")]);
+        assert_eq!(source.load(&state), Ok(MovieFileKind::BizHawkState));
+        assert_eq!(source.bizhawk_state.as_deref(), Some(&b"core"[..]));
+        assert_eq!(
+            source.load(&zip_store(&[("Framebuffer.bin", b"fb")])),
+            Err(MovieError::InvalidState)
+        );
+
+        assert_eq!(source.load(b"abc"), Err(MovieError::UnknownFile));
+    }
+
+    #[test]
+    fn test_to_fm2() {
+        let password = Password::parse("あい").unwrap();
+
+        assert_eq!(
+            to_fm2(&password, config(), &MovieSource::default()),
+            Err(MovieError::NoRom)
+        );
+        let mut no_state = MovieSource::default();
+        no_state.load(&rom(false)).unwrap();
+        assert_eq!(
+            to_fm2(&password, config(), &no_state),
+            Err(MovieError::NoState)
+        );
+
+        let fm2 = to_fm2(&password, config(), &source()).unwrap();
+        let lines: Vec<&str> = fm2.lines().collect();
+        assert_eq!(
+            lines,
+            [
+                "version 3",
+                "emuVersion 22020",
+                "rerecordCount 0",
+                "palFlag 0",
+                "romFilename Momotarou Densetsu (J)",
+                "romChecksum base64:zjOP5omXeKrPwoQU8tlJiw==",
+                "guid 6E0A3EC4-33D1-4460-E6D9-89F26C69F8C4",
+                "fourscore 0",
+                "microphone 0",
+                "port0 1",
+                "port1 1",
+                "port2 0",
+                "FDS 0",
+                "NewPPU 0",
+                "comment author momoden-password-calc",
+                "comment password あい",
+                "savestate base64:RkNTWCBzdGF0ZQ==",
+                // 'あ' で A、右へ 1 マス移動して 'い' で A、START。各入力の後に 1 フレーム離す。
+                "|0|.......A|........||",
+                "|0|........|........||",
+                "|0|R.......|........||",
+                "|0|........|........||",
+                "|0|.......A|........||",
+                "|0|........|........||",
+                "|0|....T...|........||",
+                "|0|........|........||",
+            ]
+        );
+        assert!(fm2.ends_with('\n'));
+    }
+
+    #[test]
+    fn test_to_bk2() {
+        let password = Password::parse("あい").unwrap();
+
+        let mut no_state = MovieSource::default();
+        no_state.load(&rom(false)).unwrap();
+        no_state.load(b"FCSX state").unwrap();
+        assert_eq!(
+            to_bk2(&password, config(), &no_state),
+            Err(MovieError::NoState)
+        );
+
+        let bk2 = to_bk2(&password, config(), &source()).unwrap();
+        let entry = |name| String::from_utf8(zip_entry(&bk2, name).unwrap()).unwrap();
+
+        assert_eq!(
+            entry("Header.txt"),
+            "MovieVersion BizHawk v2.0.0\n\
+             Author momoden-password-calc\n\
+             emuVersion Version 2.9\n\
+             Platform NES\n\
+             GameName Momotarou Densetsu (J)\n\
+             SHA1 897256B6709E1A4DA9DABA92B6BDE39CCFCCD8C1\n\
+             Core NesHawk\n\
+             rerecordCount 0\n\
+             StartsFromSavestate True\n"
+        );
+        assert_eq!(
+            entry("Input Log.txt"),
+            "[Input]\n\
+             LogKey:#Reset|Power|#P1 Up|P1 Down|P1 Left|P1 Right|P1 Start|P1 Select|P1 B|P1 A|\
+             #P2 Up|P2 Down|P2 Left|P2 Right|P2 Start|P2 Select|P2 B|P2 A|\n\
+             |..|.......A|........|\n\
+             |..|........|........|\n\
+             |..|...R....|........|\n\
+             |..|........|........|\n\
+             |..|.......A|........|\n\
+             |..|........|........|\n\
+             |..|....S...|........|\n\
+             |..|........|........|\n\
+             [/Input]\n"
+        );
+        assert_eq!(entry("Comments.txt"), "password あい\n");
+        assert_eq!(
+            zip_entry(&bk2, BIZHAWK_CORE_STATE).as_deref(),
+            Some(&b"core"[..])
+        );
+    }
+}
@@ -0,0 +1,589 @@
+//! パスワード画面のスクリーンショットからのパスワード読み取り。
+//!
+//! ファミコンの画面は 8x8 ドットのタイル単位で描かれる。そこでスクリーンショットを実機の解像度(横 256 ドット)に
+//! 戻してタイルに分け、文字のタイルが並ぶ行のうち、行間が一定で文字数が最も多いまとまりをパスワード欄とみなす。
+//! 窓の中にあるまとまりを優先する。窓枠のタイル(端から端まで線が通っているもの、およびそれに接するもの)は
+//! 文字から除く。文字の行の直上にある、小さな模様だけが並ぶ行は濁点・半濁点の行として直下の文字に含める。
+//! 画面はスクロールしていない(タイル境界が画面の左上に揃っている)ものとする。
+//!
+//! ゲームのフォント(CHR ROM)は同梱していないので、文字の認識には利用者が登録したテンプレートを使う。
+//! テンプレートは、パスワードが既知のスクリーンショットから `GlyphTemplates::learn()` で作る。このとき各文字の
+//! 画面上の位置も記録しておき、ゲーム画面の体裁での描画(`screen` モジュール)に使う。
+
+use momoden_password::*;
+
+use crate::query::PatternChar;
+
+/// 実機の画面の横幅(ドット)。
+const SCREEN_WIDTH: usize = 256;
+
+/// 実機の画面の縦幅(ドット)の候補。上下 8 ドットずつを削ったスクリーンショットもよくある。
+const SCREEN_HEIGHTS: [usize; 2] = [240, 224];
+
+/// 1 タイルの大きさ(ドット)。
+pub(crate) const TILE: usize = 8;
+
+/// 濁点・半濁点のタイルとみなすドット数の上限。
+const MARK_INK_MAX: u32 = 8;
+
+/// 同じパスワード欄とみなす行間(タイル)の上限。
+const LINE_PITCH_MAX: usize = 3;
+
+/// これを超えるハミング距離のテンプレートとは一致しないとみなす。
+const MATCH_DISTANCE_MAX: u32 = 8;
+
+/// 最良候補と次点の距離の差がこれ未満なら、判別できないとみなす。
+const MATCH_MARGIN_MIN: u32 = 3;
+
+/// 画面上のタイルの位置 (列, 行)。行は上下を削っていない 240 ドットの画面でのもの。
+pub(crate) type TilePos = (usize, usize);
+
+/// 文字 1 個の字形。各タイルは `TILE * y + x` ビット目が (x, y) のドットを表す。
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) struct Glyph {
+    /// 文字の上のタイル(濁点・半濁点)。なければ 0。
+    pub(crate) mark: u64,
+    /// 文字本体のタイル。
+    pub(crate) body: u64,
+}
+
+impl Glyph {
+    fn distance(self, other: Self) -> u32 {
+        (self.mark ^ other.mark).count_ones() + (self.body ^ other.body).count_ones()
+    }
+}
+
+/// グレースケール画像。
+#[derive(Clone, Debug)]
+pub(crate) struct GrayImage {
+    width: usize,
+    height: usize,
+    pixels: Vec<u8>,
+}
+
+impl GrayImage {
+    /// RGBA 形式のピクセル列から画像を作る。
+    pub(crate) fn from_rgba(width: usize, height: usize, rgba: &[u8]) -> Self {
+        assert_eq!(rgba.len(), 4 * width * height);
+
+        let pixels = rgba
+            .chunks_exact(4)
+            .map(|px| {
+                let [r, g, b] = [px[0], px[1], px[2]].map(u32::from);
+                ((299 * r + 587 * g + 114 * b) / 1000) as u8
+            })
+            .collect();
+
+        Self {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    /// 実機の解像度に戻して 2 値化し(明るいドットが文字)、タイルに分ける。
+    fn to_tiles(&self) -> Result<TileMap, OcrError> {
+        let sx = self.width as f64 / SCREEN_WIDTH as f64;
+        // 縦の倍率は、割り切れるものを優先し、次に横の倍率に近いものを選ぶ。
+        let key = |h: usize| {
+            (
+                !self.height.is_multiple_of(h),
+                (self.height as f64 / h as f64 - sx).abs(),
+            )
+        };
+        let height = SCREEN_HEIGHTS
+            .into_iter()
+            .min_by(|&a, &b| key(a).partial_cmp(&key(b)).unwrap())
+            .unwrap();
+        if self.width < SCREEN_WIDTH || self.height < height {
+            return Err(OcrError::ImageTooSmall);
+        }
+        let sy = self.height as f64 / height as f64;
+
+        let min = self.pixels.iter().copied().min().unwrap_or(0);
+        let max = self.pixels.iter().copied().max().unwrap_or(0);
+        let threshold = (u16::from(min) + u16::from(max)) / 2;
+
+        // 実機の 1 ドットに当たる領域の中心のピクセルを見る。
+        let ink = |x: usize, y: usize| {
+            let px = (((x as f64 + 0.5) * sx) as usize).min(self.width - 1);
+            let py = (((y as f64 + 0.5) * sy) as usize).min(self.height - 1);
+            max > min && u16::from(self.pixels[self.width * py + px]) > threshold
+        };
+
+        let cols = SCREEN_WIDTH / TILE;
+        let rows = height / TILE;
+        let mut tiles = vec![0; cols * rows];
+        for row in 0..rows {
+            for col in 0..cols {
+                for y in 0..TILE {
+                    for x in 0..TILE {
+                        if ink(TILE * col + x, TILE * row + y) {
+                            tiles[cols * row + col] |= 1 << (TILE * y + x);
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(TileMap::new(
+            cols,
+            rows,
+            (SCREEN_HEIGHTS[0] - height) / 2 / TILE,
+            tiles,
+        ))
+    }
+}
+
+/// 画面をタイルに分けたもの。
+#[derive(Debug)]
+struct TileMap {
+    cols: usize,
+    rows: usize,
+    /// 画像の先頭のタイル行に当たる、240 ドットの画面でのタイル行。
+    row_offset: usize,
+    tiles: Vec<u64>,
+    /// 各タイルが窓枠かどうか。
+    frame: Vec<bool>,
+}
+
+impl TileMap {
+    fn new(cols: usize, rows: usize, row_offset: usize, tiles: Vec<u64>) -> Self {
+        let straight: Vec<bool> = tiles.iter().map(|&bits| is_straight_line(bits)).collect();
+
+        // 直線のタイルに接するタイルも窓枠(の角)とみなす。
+        let frame = (0..rows)
+            .flat_map(|row| (0..cols).map(move |col| (col, row)))
+            .map(|(col, row)| {
+                let neighbors = [
+                    (col, row),
+                    (col.wrapping_sub(1), row),
+                    (col + 1, row),
+                    (col, row.wrapping_sub(1)),
+                    (col, row + 1),
+                ];
+                tiles[cols * row + col] != 0
+                    && neighbors
+                        .into_iter()
+                        .any(|(c, r)| c < cols && r < rows && straight[cols * r + c])
+            })
+            .collect();
+
+        Self {
+            cols,
+            rows,
+            row_offset,
+            tiles,
+            frame,
+        }
+    }
+
+    fn get(&self, col: usize, row: usize) -> u64 {
+        self.tiles[self.cols * row + col]
+    }
+
+    /// 窓枠以外の、ドットのあるタイルかどうかを返す。
+    fn is_ink(&self, col: usize, row: usize) -> bool {
+        self.get(col, row) != 0 && !self.frame[self.cols * row + col]
+    }
+
+    /// 行 `row` の文字が左右とも窓枠に挟まれているかどうかを返す。
+    fn is_in_window(&self, row: usize) -> bool {
+        let is_frame = |col: usize| self.frame[self.cols * row + col];
+        let Some(first) = (0..self.cols).find(|&col| self.is_ink(col, row)) else {
+            return false;
+        };
+        let last = (0..self.cols).rfind(|&col| self.is_ink(col, row)).unwrap();
+
+        (0..first).any(is_frame) && (last + 1..self.cols).any(is_frame)
+    }
+
+    fn ink_count(&self, row: usize) -> usize {
+        (0..self.cols).filter(|&col| self.is_ink(col, row)).count()
+    }
+
+    /// 濁点・半濁点の行かどうかを返す。直下の行の文字の上にだけ、小さな模様が並ぶ行をそうみなす。
+    fn is_mark_row(&self, row: usize) -> bool {
+        if row + 1 >= self.rows || self.ink_count(row) == 0 {
+            return false;
+        }
+
+        (0..self.cols)
+            .filter(|&col| self.is_ink(col, row))
+            .all(|col| self.get(col, row).count_ones() <= MARK_INK_MAX && self.is_ink(col, row + 1))
+    }
+
+    /// パスワード欄の文字を、画面上の位置とともに読み順に列挙する。
+    fn glyphs(&self) -> Vec<(TilePos, Glyph)> {
+        let text_rows: Vec<usize> = (0..self.rows)
+            .filter(|&row| self.ink_count(row) > 0 && !self.is_mark_row(row))
+            .collect();
+
+        let mut glyphs = vec![];
+        for row in self.password_rows(&text_rows) {
+            let has_marks = row > 0 && self.is_mark_row(row - 1);
+            for col in (0..self.cols).filter(|&col| self.is_ink(col, row)) {
+                let mark = if has_marks && self.is_ink(col, row - 1) {
+                    self.get(col, row - 1)
+                } else {
+                    0
+                };
+                let glyph = Glyph {
+                    mark,
+                    body: self.get(col, row),
+                };
+                glyphs.push(((col, row + self.row_offset), glyph));
+            }
+        }
+
+        glyphs
+    }
+
+    /// 文字のある行 `rows`(昇順)のうち、行間が一定で文字数が最も多いまとまりを返す。
+    ///
+    /// 窓の中にあるまとまりを優先する。
+    fn password_rows(&self, rows: &[usize]) -> Vec<usize> {
+        let mut best: Vec<usize> = vec![];
+        let mut best_score = (false, 0);
+
+        for start in 0..rows.len() {
+            // start 番目の行から、行間を変えずに伸ばせるだけ伸ばす。
+            let mut block = vec![rows[start]];
+            let mut pitch = None;
+            for &row in &rows[start + 1..] {
+                let gap = row - block.last().unwrap();
+                if gap > LINE_PITCH_MAX || pitch.is_some_and(|pitch| pitch != gap) {
+                    break;
+                }
+                pitch = Some(gap);
+                block.push(row);
+            }
+
+            let in_window = block.iter().all(|&row| self.is_in_window(row));
+            let count: usize = block.iter().map(|&row| self.ink_count(row)).sum();
+            if (in_window, count) > best_score {
+                best = block;
+                best_score = (in_window, count);
+            }
+        }
+
+        best
+    }
+}
+
+/// タイルの端から端まで、縦または横に直線が通っているかどうかを返す。
+///
+/// ゲームの文字は字間・行間のために端の 1 列・1 行を空けてあるので、これに当たるのは窓枠だけ。
+fn is_straight_line(bits: u64) -> bool {
+    let dot = |x: usize, y: usize| bits >> (TILE * y + x) & 1 != 0;
+
+    (0..TILE).any(|i| (0..TILE).all(|x| dot(x, i)) || (0..TILE).all(|y| dot(i, y)))
+}
+
+/// 文字認識用のテンプレート集合。
+#[derive(Clone, Debug, Default)]
+pub(crate) struct GlyphTemplates {
+    templates: Vec<(PasswordChar, Glyph)>,
+    /// 最後に登録したスクリーンショットでの、パスワードの各文字の位置。
+    layout: Vec<TilePos>,
+}
+
+impl GlyphTemplates {
+    /// 登録済みの文字の種類数を返す。
+    pub(crate) fn char_count(&self) -> usize {
+        PasswordChar::all()
+            .into_iter()
+            .filter(|&pc| self.glyph(pc).is_some())
+            .count()
+    }
+
+    /// 文字 `pc` の字形を返す。未登録なら `None` を返す。
+    pub(crate) fn glyph(&self, pc: PasswordChar) -> Option<Glyph> {
+        self.templates
+            .iter()
+            .find(|&&(t, _)| t == pc)
+            .map(|&(_, glyph)| glyph)
+    }
+
+    /// 最後に登録したスクリーンショットでの、パスワードの各文字の位置を返す。未登録なら空。
+    pub(crate) fn layout(&self) -> &[TilePos] {
+        &self.layout
+    }
+
+    /// パスワード `password` を写したスクリーンショットからテンプレートを登録する。
+    pub(crate) fn learn(&mut self, image: &GrayImage, password: &Password) -> Result<(), OcrError> {
+        let glyphs = image.to_tiles()?.glyphs();
+        if glyphs.len() != password.len() {
+            return Err(OcrError::GlyphCountMismatch {
+                expected: password.len(),
+                actual: glyphs.len(),
+            });
+        }
+
+        self.layout = glyphs.iter().map(|&(pos, _)| pos).collect();
+        for (&pc, (_, glyph)) in password.iter().zip(glyphs) {
+            if !self.templates.contains(&(pc, glyph)) {
+                self.templates.push((pc, glyph));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// スクリーンショットから文字を読み取る。判別できない文字は `PatternChar::Any` になる。
+    pub(crate) fn recognize(&self, image: &GrayImage) -> Result<Vec<PatternChar>, OcrError> {
+        if self.templates.is_empty() {
+            return Err(OcrError::NoTemplate);
+        }
+
+        let glyphs = image.to_tiles()?.glyphs();
+        if glyphs.is_empty() {
+            return Err(OcrError::NoGlyph);
+        }
+
+        Ok(glyphs
+            .into_iter()
+            .map(|(_, glyph)| self.match_glyph(glyph))
+            .collect())
+    }
+
+    fn match_glyph(&self, glyph: Glyph) -> PatternChar {
+        // 文字ごとの最小距離。
+        let mut dists: Vec<(u32, PasswordChar)> = PasswordChar::all()
+            .into_iter()
+            .filter_map(|pc| {
+                self.templates
+                    .iter()
+                    .filter(|&&(t, _)| t == pc)
+                    .map(|&(_, t)| t.distance(glyph))
+                    .min()
+                    .map(|d| (d, pc))
+            })
+            .collect();
+        dists.sort_unstable_by_key(|&(d, _)| d);
+
+        match dists[..] {
+            [(best, pc), (second, _), ..]
+                if best <= MATCH_DISTANCE_MAX && second - best >= MATCH_MARGIN_MIN =>
+            {
+                PatternChar::Password(pc)
+            }
+            [(best, pc)] if best <= MATCH_DISTANCE_MAX => PatternChar::Password(pc),
+            _ => PatternChar::Any,
+        }
+    }
+}
+
+/// 保存用の文字列形式。
+///
+/// 1 行に 1 テンプレートを「文字 濁点タイル 本体タイル」(タイルは 16 進数)の形で書き、最後に文字の位置を
+/// 「layout 列,行 列,行 ...」の形で書く。
+impl std::fmt::Display for GlyphTemplates {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for &(pc, glyph) in &self.templates {
+            writeln!(
+                f,
+                "{} {:016X} {:016X}",
+                pc.to_char(),
+                glyph.mark,
+                glyph.body
+            )?;
+        }
+
+        if !self.layout.is_empty() {
+            f.write_str("layout")?;
+            for &(col, row) in &self.layout {
+                write!(f, " {col},{row}")?;
+            }
+            writeln!(f)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl std::str::FromStr for GlyphTemplates {
+    type Err = std::convert::Infallible;
+
+    /// 保存用の文字列形式からテンプレートを読み込む。不正な行は無視する。
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut this = Self::default();
+
+        for line in s.lines() {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            match fields[..] {
+                ["layout", ref positions @ ..] => {
+                    this.layout = positions
+                        .iter()
+                        .filter_map(|pos| {
+                            let (col, row) = pos.split_once(',')?;
+                            Some((col.parse().ok()?, row.parse().ok()?))
+                        })
+                        .collect();
+                }
+                [c, mark, body] => {
+                    let mut cs = c.chars();
+                    let Some(pc) = cs.next().and_then(PasswordChar::from_char) else {
+                        continue;
+                    };
+                    if cs.next().is_some() {
+                        continue;
+                    }
+                    let (Ok(mark), Ok(body)) =
+                        (u64::from_str_radix(mark, 16), u64::from_str_radix(body, 16))
+                    else {
+                        continue;
+                    };
+                    this.templates.push((pc, Glyph { mark, body }));
+                }
+                _ => {}
+            }
+        }
+
+        Ok(this)
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) enum OcrError {
+    NoTemplate,
+    NoGlyph,
+    ImageTooSmall,
+    GlyphCountMismatch { expected: usize, actual: usize },
+}
+
+impl std::fmt::Display for OcrError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NoTemplate => f.write_str("文字テンプレートが登録されていない"),
+            Self::NoGlyph => f.write_str("画像から文字が見つからない"),
+            Self::ImageTooSmall => {
+                write!(f, "画像が小さすぎる (横 {SCREEN_WIDTH} ドット以上が必要)")
+            }
+            Self::GlyphCountMismatch { expected, actual } => write!(
+                f,
+                "画像から見つかった文字数 ({actual}) がパスワードの文字数 ({expected}) と一致しない"
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::query::Pattern;
+
+    /// 文字ごとに異なる、右端の列と下端の行を空けた字形。
+    fn test_glyph(pc: PasswordChar) -> Glyph {
+        const MASK: u64 = 0x007F_7F7F_7F7F_7F7F;
+
+        let v = u64::from(pc.to_inner());
+        let mut h = (v + 1).wrapping_mul(0x9E37_79B9_7F4A_7C15);
+        h ^= h >> 31;
+        h = h.wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        h ^= h >> 27;
+
+        // 濁音・半濁音には右下に点を付ける。
+        let mark = if v >= 0x2C { 0x0606_0000_0000 } else { 0 };
+        Glyph {
+            mark,
+            body: h & MASK,
+        }
+    }
+
+    /// 2 倍に拡大したパスワード画面の画像を作る。`title` は窓の上に置く文字。
+    fn test_screen(password: &Password, title: &[PasswordChar]) -> GrayImage {
+        const SCALE: usize = 2;
+        let (w, h) = (SCREEN_WIDTH, SCREEN_HEIGHTS[0]);
+        let mut dots = vec![false; w * h];
+        let mut put_tile = |col: usize, row: usize, bits: u64| {
+            for y in 0..TILE {
+                for x in 0..TILE {
+                    if bits >> (TILE * y + x) & 1 != 0 {
+                        dots[w * (TILE * row + y) + TILE * col + x] = true;
+                    }
+                }
+            }
+        };
+
+        for (i, &pc) in title.iter().enumerate() {
+            put_tile(4 + 2 * i, 3, test_glyph(pc).body);
+        }
+
+        // 窓枠: 列 2..=29, 行 8..=24。
+        for col in 2..=29 {
+            put_tile(col, 8, 0xFF << (TILE * 4));
+            put_tile(col, 24, 0xFF << (TILE * 4));
+        }
+        for row in 8..=24 {
+            put_tile(2, row, 0x1010_1010_1010_1010);
+            put_tile(29, row, 0x1010_1010_1010_1010);
+        }
+
+        // 1 行 8 文字、1 文字おき・2 行おきに並べる。
+        for (i, &pc) in password.iter().enumerate() {
+            let (col, row) = (5 + 2 * (i % 8), 11 + 2 * (i / 8));
+            let glyph = test_glyph(pc);
+            put_tile(col, row - 1, glyph.mark);
+            put_tile(col, row, glyph.body);
+        }
+
+        let mut rgba = vec![0; 4 * SCALE * w * SCALE * h];
+        for y in 0..SCALE * h {
+            for x in 0..SCALE * w {
+                if dots[w * (y / SCALE) + x / SCALE] {
+                    rgba[4 * (SCALE * w * y + x)..][..4].copy_from_slice(&[0xFF; 4]);
+                }
+            }
+        }
+
+        GrayImage::from_rgba(SCALE * w, SCALE * h, &rgba)
+    }
+
+    #[test]
+    fn test_learn_recognize() {
+        let title = [PasswordChar::Hu, PasswordChar::Ka, PasswordChar::Tu];
+        let password = Password::parse(
+            "あかさたなはまやらわがざばぱいきしちにひみゆりぎじびぴうくすつぬふむよる",
+        )
+        .unwrap();
+
+        let mut templates = GlyphTemplates::default();
+        templates
+            .learn(&test_screen(&password, &title), &password)
+            .unwrap();
+        assert_eq!(templates.layout().len(), password.len());
+        assert_eq!(templates.layout()[0], (5, 11));
+        assert_eq!(templates.layout()[8], (5, 13));
+
+        let target = Password::parse("がかいぱぴぎ").unwrap();
+        let recognized = templates.recognize(&test_screen(&target, &title)).unwrap();
+        assert_eq!(recognized, Pattern::from(&target).as_slice());
+
+        // 未登録の文字は判別できない。
+        let target = Password::parse("かけか").unwrap();
+        let recognized = templates.recognize(&test_screen(&target, &title)).unwrap();
+        assert_eq!(
+            recognized,
+            [
+                PatternChar::Password(PasswordChar::Ka),
+                PatternChar::Any,
+                PatternChar::Password(PasswordChar::Ka),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_templates_roundtrip() {
+        let password = Password::parse("あがぱ").unwrap();
+        let mut templates = GlyphTemplates::default();
+        templates
+            .learn(&test_screen(&password, &[]), &password)
+            .unwrap();
+
+        let restored: GlyphTemplates = templates.to_string().parse().unwrap();
+        assert_eq!(restored.templates, templates.templates);
+        assert_eq!(restored.layout, templates.layout);
+    }
+}
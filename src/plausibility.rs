@@ -0,0 +1,222 @@
+use momoden_password::*;
+
+use crate::flags::Flag;
+use crate::game::*;
+
+/// これを超える年齢は不自然とみなす。
+const AGE_PLAUSIBLE_MAX: u8 = 99;
+
+/// セーブデータの不自然な点。
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub(crate) enum Implausibility {
+    /// ロード時に装備が変化する。
+    EquipmentChanged,
+    /// 復活地点が海上。
+    RespawnAtSea,
+    /// 復活地点の村がブックマークされていない。
+    RespawnNotBookmarked,
+    /// イベントを終えているのに、その村がブックマークされていない。
+    EventNotBookmarked(Event),
+    /// 大事なものを重複して所持している。
+    DuplicateKeyItem(ItemId),
+    /// 年齢が高すぎる。
+    AgeTooHigh,
+}
+
+impl Implausibility {
+    /// ペナルティの重み。
+    pub(crate) fn weight(self) -> u32 {
+        match self {
+            Self::EquipmentChanged => 4,
+            Self::RespawnAtSea => 3,
+            Self::DuplicateKeyItem(_) => 2,
+            Self::RespawnNotBookmarked | Self::EventNotBookmarked(_) | Self::AgeTooHigh => 1,
+        }
+    }
+}
+
+impl std::fmt::Display for Implausibility {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::EquipmentChanged => f.write_str("ロード時に装備が変化する"),
+            Self::RespawnAtSea => f.write_str("復活地点が海上"),
+            Self::RespawnNotBookmarked => f.write_str("復活地点の村がブックマークされていない"),
+            Self::EventNotBookmarked(event) => {
+                write!(
+                    f,
+                    "「{}」の村がブックマークされていない",
+                    event_name(*event)
+                )
+            }
+            Self::DuplicateKeyItem(id) => write!(f, "「{}」を重複して所持", item_name(*id)),
+            Self::AgeTooHigh => write!(f, "年齢が {AGE_PLAUSIBLE_MAX} 歳を超えている"),
+        }
+    }
+}
+
+/// セーブデータ(正規化前)の不自然な点を列挙する。
+pub(crate) fn implausibilities(savedata: &Savedata) -> Vec<Implausibility> {
+    let mut res = vec![];
+
+    if savedata.equipment.normalize() != savedata.equipment {
+        res.push(Implausibility::EquipmentChanged);
+    }
+
+    match respawn_bookmark(savedata.respawn) {
+        None => res.push(Implausibility::RespawnAtSea),
+        Some(bookmark) if !savedata.bookmarks[bookmark] => {
+            res.push(Implausibility::RespawnNotBookmarked);
+        }
+        Some(_) => {}
+    }
+
+    for &event in Event::ALL {
+        let Some(bookmark) = event_bookmark(event) else {
+            continue;
+        };
+        if savedata.events[event] && !savedata.bookmarks[bookmark] {
+            res.push(Implausibility::EventNotBookmarked(event));
+        }
+    }
+
+    let inventory = &savedata.inventory;
+    for (i, &id) in inventory.iter().enumerate() {
+        if item_category(id) == ItemCategory::Key && inventory[..i].contains(&id) {
+            res.push(Implausibility::DuplicateKeyItem(id));
+        }
+    }
+
+    if savedata.age > AGE_PLAUSIBLE_MAX {
+        res.push(Implausibility::AgeTooHigh);
+    }
+
+    res
+}
+
+/// セーブデータ(正規化前)の不自然さを返す。値が小さいほどもっともらしい。
+pub(crate) fn implausibility_score(savedata: &Savedata) -> u32 {
+    implausibilities(savedata)
+        .into_iter()
+        .map(Implausibility::weight)
+        .sum()
+}
+
+/// パスワードたちを、デコード結果がもっともらしい順に安定ソートする。無効なパスワードは末尾に回す。
+pub(crate) fn sort_by_plausibility(passwords: &mut [Password]) {
+    passwords.sort_by_cached_key(|password| {
+        load_from_password(password, false)
+            .map_or(u32::MAX, |savedata| implausibility_score(&savedata))
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::preset::{preset_savedata, Preset};
+
+    fn start_with(f: impl FnOnce(&mut Savedata)) -> Savedata {
+        let mut savedata = preset_savedata(Preset::Start);
+        f(&mut savedata);
+        savedata
+    }
+
+    fn item(id: u8) -> ItemId {
+        ItemId::new(id).unwrap()
+    }
+
+    #[test]
+    fn test_implausibilities_preset() {
+        for preset in Preset::all() {
+            let savedata = preset_savedata(preset);
+            assert_eq!(implausibilities(&savedata), [], "{preset:?}");
+            assert_eq!(implausibility_score(&savedata), 0);
+        }
+    }
+
+    #[test]
+    fn test_implausibilities() {
+        let cases = [
+            (
+                start_with(|s| s.equipment.helm = HelmIndex::new(3).unwrap()),
+                Implausibility::EquipmentChanged,
+            ),
+            (
+                start_with(|s| s.respawn = RespawnId::new(0).unwrap()),
+                Implausibility::RespawnAtSea,
+            ),
+            (
+                start_with(|s| s.respawn = RespawnId::new(2).unwrap()),
+                Implausibility::RespawnNotBookmarked,
+            ),
+            (
+                start_with(|s| s.events.hanasaka = true),
+                Implausibility::EventNotBookmarked(Event::Hanasaka),
+            ),
+            (
+                // 0x3A: しょうかいじょう
+                start_with(|s| {
+                    s.inventory.push(item(0x3A));
+                    s.inventory.push(item(0x3A));
+                }),
+                Implausibility::DuplicateKeyItem(item(0x3A)),
+            ),
+            (
+                start_with(|s| s.age = AGE_PLAUSIBLE_MAX + 1),
+                Implausibility::AgeTooHigh,
+            ),
+        ];
+
+        for (savedata, expected) in cases {
+            assert_eq!(implausibilities(&savedata), [expected]);
+            assert_eq!(implausibility_score(&savedata), expected.weight());
+        }
+
+        // 大事なもの以外の重複や、上限ちょうどの年齢は不自然ではない。
+        let savedata = start_with(|s| {
+            s.inventory.push(item(0x01));
+            // 0x39: フグりょうり
+            s.inventory.push(item(0x39));
+            s.inventory.push(item(0x39));
+            s.age = AGE_PLAUSIBLE_MAX;
+        });
+        assert_eq!(implausibilities(&savedata), []);
+
+        // 複数の不自然な点の重みは合算する。
+        let savedata = start_with(|s| {
+            s.respawn = RespawnId::new(0).unwrap();
+            s.age = AGE_PLAUSIBLE_MAX + 1;
+        });
+        assert_eq!(
+            implausibilities(&savedata),
+            [Implausibility::RespawnAtSea, Implausibility::AgeTooHigh]
+        );
+        assert_eq!(implausibility_score(&savedata), 4);
+    }
+
+    #[test]
+    fn test_sort_by_plausibility() {
+        let equipment = save_to_password(&start_with(|s| {
+            s.equipment.helm = HelmIndex::new(3).unwrap();
+        }));
+        let sea = save_to_password(&start_with(|s| s.respawn = RespawnId::new(0).unwrap()));
+        let age = save_to_password(&start_with(|s| s.age = AGE_PLAUSIBLE_MAX + 1));
+        let event = save_to_password(&start_with(|s| s.events.hanasaka = true));
+        let start = save_to_password(&preset_savedata(Preset::Start));
+        let invalid = Password::new(&[PasswordChar::A; 5]).unwrap();
+        assert!(!invalid.is_valid());
+
+        let mut passwords = [
+            equipment.clone(),
+            invalid.clone(),
+            age.clone(),
+            sea.clone(),
+            start.clone(),
+            event.clone(),
+        ];
+        sort_by_plausibility(&mut passwords);
+
+        // 不自然さの昇順。同点(age と event)は元の順序を保つ。無効なパスワードは末尾。
+        assert_eq!(passwords, [start, age, event, sea, equipment, invalid]);
+    }
+}
@@ -0,0 +1,162 @@
+use int_enum::IntEnum;
+
+use momoden_password::*;
+
+/// ストーリー進行度ごとのセーブデータのひな形。
+///
+/// 各値は典型的なプレイを想定した目安であり、実際のプレイデータとは異なりうる。
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd, IntEnum)]
+pub(crate) enum Preset {
+    Start = 0,
+    Hanasaka = 1,
+    Kintaro = 2,
+    Urashima = 3,
+    BeforeHien = 4,
+    Endgame = 5,
+}
+
+impl Preset {
+    pub(crate) fn all() -> [Self; 6] {
+        std::array::from_fn(|i| Self::from_int(i as u8).unwrap())
+    }
+}
+
+pub(crate) fn preset_name(preset: Preset) -> &'static str {
+    use Preset::*;
+
+    match preset {
+        Start => "ゲーム開始直後",
+        Hanasaka => "銀の鬼を倒した後",
+        Kintaro => "金の鬼を倒した後",
+        Urashima => "パールの鬼を倒した後",
+        BeforeHien => "飛燕の城の手前",
+        Endgame => "最終盤 (全て入手済み)",
+    }
+}
+
+pub(crate) fn preset_savedata(preset: Preset) -> Savedata {
+    use Preset::*;
+
+    // 各プリセットは 1 つ前のプリセットに進行分を加えたものとする。
+    match preset {
+        Start => Savedata {
+            respawn: respawn(1),
+            bookmarks: Bookmarks {
+                tabidachi: true,
+                ..Bookmarks::NONE
+            },
+            minions: Minions {
+                dog: true,
+                ..Minions::NONE
+            },
+            equipment: equipment([0, 1, 0, 0, 0, 0, 0, 0]),
+            inventory: inventory(&[0x01, 0x03]),
+            ..Default::default()
+        },
+        Hanasaka => {
+            let mut savedata = preset_savedata(Start);
+            savedata.xp = 300;
+            savedata.purse = 500;
+            savedata.spells.kintan = true;
+            savedata.events.hanasaka = true;
+            savedata.bookmarks.hanasaka = true;
+            savedata.respawn = respawn(2);
+            savedata.equipment = equipment([1, 2, 1, 1, 0, 0, 0, 0]);
+            savedata.inventory = inventory(&[0x01, 0x01, 0x03, 0x07]);
+            savedata
+        }
+        Kintaro => {
+            let mut savedata = preset_savedata(Hanasaka);
+            savedata.xp = 1500;
+            savedata.purse = 1500;
+            savedata.spells.rokkaku = true;
+            savedata.events.kintaro = true;
+            savedata.minions.monkey = true;
+            savedata.bookmarks.kintaro = true;
+            savedata.respawn = respawn(3);
+            savedata.equipment = equipment([1, 3, 2, 2, 1, 0, 0, 0]);
+            savedata
+        }
+        Urashima => {
+            let mut savedata = preset_savedata(Kintaro);
+            savedata.xp = 5000;
+            savedata.purse = 3000;
+            savedata.deposit = Deposit::new(5).unwrap();
+            savedata.spells.inazuma = true;
+            savedata.spells.hien = true;
+            savedata.events.urashima = true;
+            savedata.minions.pheasant = true;
+            savedata.bookmarks.urashima = true;
+            savedata.respawn = respawn(4);
+            savedata.equipment = equipment([2, 5, 4, 2, 1, 1, 0, 0]);
+            savedata
+        }
+        BeforeHien => {
+            let mut savedata = preset_savedata(Urashima);
+            savedata.xp = 30000;
+            savedata.purse = 10000;
+            savedata.deposit = Deposit::new(30).unwrap();
+            savedata.spells = Spells::ALL;
+            savedata.events = Events::ALL;
+            savedata.treasures = Treasures::ALL;
+            savedata.minions = Minions::ALL;
+            savedata.bookmarks = Bookmarks {
+                hien: false,
+                ..Bookmarks::ALL
+            };
+            savedata.respawn = respawn(15);
+            savedata.equipment = equipment([2, 8, 8, 4, 2, 2, 1, 1]);
+            savedata.inventory = inventory(&[0x01, 0x01, 0x03, 0x03, 0x05, 0x06]);
+            savedata
+        }
+        Endgame => {
+            let mut savedata = preset_savedata(BeforeHien);
+            savedata.xp = u16::MAX;
+            savedata.purse = u16::MAX;
+            savedata.deposit = Deposit::MAX;
+            savedata.bookmarks = Bookmarks::ALL;
+            savedata.equipment = equipment([2, 10, 9, 4, 2, 2, 1, 1]);
+            savedata
+        }
+    }
+}
+
+/// 経験値・所持金・預金を最大にし、全てのフラグを立て、最強の装備にする。年齢、復活地点、インベントリはそのまま。
+pub(crate) fn max_out(savedata: &mut Savedata) {
+    let endgame = preset_savedata(Preset::Endgame);
+
+    savedata.xp = u16::MAX;
+    savedata.purse = u16::MAX;
+    savedata.deposit = Deposit::MAX;
+    savedata.spells = Spells::ALL;
+    savedata.events = Events::ALL;
+    savedata.treasures = Treasures::ALL;
+    savedata.minions = Minions::ALL;
+    savedata.bookmarks = Bookmarks::ALL;
+    savedata.equipment = endgame.equipment;
+}
+
+fn respawn(inner: u8) -> RespawnId {
+    RespawnId::new(inner).unwrap()
+}
+
+/// 兜, 武器, 鎧, 靴, いでたち0, いでたち1, いでたち2, いでたち3 の順にインデックスを指定して装備を作る。
+fn equipment(indices: [u8; 8]) -> Equipment {
+    let [helm, weapon, armor, shoes, accessory0, accessory1, accessory2, accessory3] = indices;
+
+    Equipment {
+        helm: HelmIndex::new(helm).unwrap(),
+        weapon: WeaponIndex::new(weapon).unwrap(),
+        armor: ArmorIndex::new(armor).unwrap(),
+        shoes: ShoesIndex::new(shoes).unwrap(),
+        accessory0: Accessory0Index::new(accessory0).unwrap(),
+        accessory1: Accessory1Index::new(accessory1).unwrap(),
+        accessory2: Accessory2Index::new(accessory2).unwrap(),
+        accessory3: Accessory3Index::new(accessory3).unwrap(),
+    }
+}
+
+fn inventory(ids: &[u8]) -> Inventory {
+    ids.iter().map(|&id| ItemId::new(id).unwrap()).collect()
+}
@@ -1,3 +1,5 @@
+use std::fmt::Write as _;
+
 use arrayvec::ArrayVec;
 
 use momoden_password::{Password, PasswordChar};
@@ -5,14 +7,30 @@ use momoden_password::{Password, PasswordChar};
 /// '?' の最大個数。
 const COUNT_ANY_MAX: usize = 3;
 
-#[derive(Debug)]
-pub(crate) enum Query {
+/// パスワード入力欄のクエリ。'?' を含まなければパスワード、含めばパターンとなる。
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub enum Query {
+    /// '?' を含まないクエリ。
     Password(Password),
+    /// '?' を含むクエリ。
     Pattern(Pattern),
 }
 
 impl Query {
-    pub(crate) fn parse(s: &str) -> Result<Self, QueryParseError> {
+    /// 文字列をパースしてクエリを得る。空白は無視される。
+    ///
+    /// '?' は 3 個まで含められる。
+    ///
+    /// ```
+    /// use momoden_password_calc::{PatternChar, Query, QueryParseError};
+    ///
+    /// let Ok(Query::Pattern(pattern)) = Query::parse("ふ?") else { panic!() };
+    /// assert_eq!(pattern.count_any(), 1);
+    /// assert_eq!(pattern[1], PatternChar::Any);
+    ///
+    /// assert_eq!(Query::parse("????"), Err(QueryParseError::TooManyAny));
+    /// ```
+    pub fn parse(s: &str) -> Result<Self, QueryParseError> {
         let mut cs = s.chars().filter(|&c| !c.is_whitespace()).peekable();
         let v: ArrayVec<char, { Password::MAX_LEN }> =
             cs.by_ref().take(Password::MAX_LEN).collect();
@@ -38,7 +56,7 @@ impl Query {
         }
 
         let this = if count_any > 0 {
-            Self::Pattern(Pattern::new(v))
+            Self::Pattern(Pattern(v))
         } else {
             let v: PasswordVec = v
                 .into_iter()
@@ -54,20 +72,231 @@ impl Query {
     }
 }
 
+impl Query {
+    /// 文字列をクエリとしてパースする際の問題点を全て列挙する。問題がなければ空の `Vec` を返す。
+    ///
+    /// `Query::parse()` が最初の 1 個しか返さないエラーを、元の文字列内での位置とともに全て返す。
+    pub fn diagnose(s: &str) -> Vec<QueryDiagnostic> {
+        let mut diags = vec![];
+
+        // (元の文字列内の位置, 文字)。空白は除く。
+        let cs: Vec<(usize, char)> = s
+            .chars()
+            .enumerate()
+            .filter(|&(_, c)| !c.is_whitespace())
+            .collect();
+
+        if cs.is_empty() {
+            diags.push(QueryDiagnostic {
+                error: QueryParseError::Empty,
+                span: 0..0,
+            });
+            return diags;
+        }
+
+        if let Some(&(start, _)) = cs.get(Password::MAX_LEN) {
+            diags.push(QueryDiagnostic {
+                error: QueryParseError::TooLong,
+                span: start..s.chars().count(),
+            });
+        }
+
+        let mut count_any = 0;
+        for (i, &(idx, c)) in cs.iter().enumerate() {
+            match PatternChar::from_char(c) {
+                None => diags.push(QueryDiagnostic {
+                    error: QueryParseError::InvalidChar { pos: i, ch: c },
+                    span: idx..idx + 1,
+                }),
+                Some(PatternChar::Any) => {
+                    count_any += 1;
+                    if count_any > COUNT_ANY_MAX {
+                        diags.push(QueryDiagnostic {
+                            error: QueryParseError::TooManyAny,
+                            span: idx..idx + 1,
+                        });
+                    }
+                }
+                Some(PatternChar::Password(_)) => {}
+            }
+        }
+
+        diags.sort_by_key(|diag| diag.span.start);
+
+        diags
+    }
+}
+
+impl std::str::FromStr for Query {
+    type Err = QueryParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+/// クエリをひらがな文字列(空白区切りなし)としてフォーマットする。
+///
+/// `Query::parse()` が返したクエリなら、結果を `Query::parse()` で元に戻せる。`Pattern::new()` で作った
+/// パターンの '?' が 0 個または 4 個以上の場合は戻せない(パスワードになるか、エラーになる)。
+impl std::fmt::Display for Query {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Password(password) => password.display().fmt(f),
+            Self::Pattern(pattern) => pattern.fmt(f),
+        }
+    }
+}
+
+/// ユーザーの入力をひらがな・'?'・空白からなる文字列に正規化する。
+///
+/// * カタカナ(全角・半角)はひらがなに変換する。濁点・半濁点は直前の文字と結合する。
+/// * 全角の '？' は '?' に変換する。
+/// * `romaji` が真ならば、ローマ字(大文字小文字を問わない)をひらがなに変換する。
+///
+/// 変換できない文字はそのまま残すので、後で `Query::parse()` がエラーとして検出する。
+pub fn normalize_query_input(s: &str, romaji: bool) -> String {
+    let s = if romaji {
+        romaji_to_hiragana(s)
+    } else {
+        s.to_owned()
+    };
+
+    let mut res = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            // 濁点・半濁点(結合文字、単独、半角)
+            '\u{3099}' | '\u{309B}' | '\u{FF9E}' | '\u{309A}' | '\u{309C}' | '\u{FF9F}' => {
+                let handakuten = matches!(c, '\u{309A}' | '\u{309C}' | '\u{FF9F}');
+                match res
+                    .chars()
+                    .last()
+                    .and_then(|prev| add_dakuten(prev, handakuten))
+                {
+                    Some(voiced) => {
+                        res.pop();
+                        res.push(voiced);
+                    }
+                    None => res.push(c),
+                }
+            }
+            _ => res.push(normalize_char(c)),
+        }
+    }
+
+    res
+}
+
+/// 濁点・半濁点を付けない文字単位の正規化。
+fn normalize_char(c: char) -> char {
+    // 半角カタカナ U+FF66..=U+FF9D に対応するひらがな。
+    const HALF_WIDTH: &str = "をぁぃぅぇぉゃゅょっーあいうえおかきくけこさしすせそたちつてとなにぬねのはひふへほまみむめもやゆよらりるれろわん";
+
+    match c {
+        'ァ'..='ヶ' => char::from_u32(u32::from(c) - 0x60).unwrap(),
+        '\u{FF66}'..='\u{FF9D}' => HALF_WIDTH
+            .chars()
+            .nth((u32::from(c) - 0xFF66) as usize)
+            .unwrap(),
+        '？' => '?',
+        _ => c,
+    }
+}
+
+/// ひらがな `c` に濁点(`handakuten` が真なら半濁点)を付けた文字を返す。付けられなければ `None` を返す。
+fn add_dakuten(c: char, handakuten: bool) -> Option<char> {
+    // Unicode では濁音・半濁音は清音の直後に並んでいる。
+    const DAKUTEN: &str = "かきくけこさしすせそたちつてとはひふへほ";
+    const HANDAKUTEN: &str = "はひふへほ";
+
+    let (table, offset) = if handakuten {
+        (HANDAKUTEN, 2)
+    } else {
+        (DAKUTEN, 1)
+    };
+
+    table
+        .contains(c)
+        .then(|| char::from_u32(u32::from(c) + offset).unwrap())
+}
+
+/// ローマ字をひらがなに変換する。変換できない部分はそのまま残す。
+///
+/// パスワードに使える文字の綴りのみを扱う(「ん」「を」「だ」行などは変換しない)。
+fn romaji_to_hiragana(s: &str) -> String {
+    #[rustfmt::skip]
+    const TABLE: &[(&str, char)] = &[
+        ("a", 'あ'), ("i", 'い'), ("u", 'う'), ("e", 'え'), ("o", 'お'),
+        ("ka", 'か'), ("ki", 'き'), ("ku", 'く'), ("ke", 'け'), ("ko", 'こ'),
+        ("sa", 'さ'), ("si", 'し'), ("shi", 'し'), ("su", 'す'), ("se", 'せ'), ("so", 'そ'),
+        ("ta", 'た'), ("ti", 'ち'), ("chi", 'ち'), ("tu", 'つ'), ("tsu", 'つ'), ("te", 'て'), ("to", 'と'),
+        ("na", 'な'), ("ni", 'に'), ("nu", 'ぬ'), ("ne", 'ね'), ("no", 'の'),
+        ("ha", 'は'), ("hi", 'ひ'), ("hu", 'ふ'), ("fu", 'ふ'), ("he", 'へ'), ("ho", 'ほ'),
+        ("ma", 'ま'), ("mi", 'み'), ("mu", 'む'), ("me", 'め'), ("mo", 'も'),
+        ("ya", 'や'), ("yu", 'ゆ'), ("yo", 'よ'),
+        ("ra", 'ら'), ("ri", 'り'), ("ru", 'る'), ("re", 'れ'), ("ro", 'ろ'),
+        ("la", 'ら'), ("li", 'り'), ("lu", 'る'), ("le", 'れ'), ("lo", 'ろ'),
+        ("wa", 'わ'),
+        ("ga", 'が'), ("gi", 'ぎ'), ("gu", 'ぐ'), ("ge", 'げ'), ("go", 'ご'),
+        ("za", 'ざ'), ("zi", 'じ'), ("ji", 'じ'), ("zu", 'ず'), ("ze", 'ぜ'), ("zo", 'ぞ'),
+        ("ba", 'ば'), ("bi", 'び'), ("bu", 'ぶ'), ("be", 'べ'), ("bo", 'ぼ'),
+        ("pa", 'ぱ'), ("pi", 'ぴ'), ("pu", 'ぷ'), ("pe", 'ぺ'), ("po", 'ぽ'),
+    ];
+
+    let cs: Vec<char> = s.chars().collect();
+    let mut res = String::with_capacity(s.len());
+
+    let mut i = 0;
+    while i < cs.len() {
+        // 長い綴りを優先して照合する。
+        let hit = (1..=3).rev().find_map(|len| {
+            let syllable: String = cs.get(i..i + len)?.iter().collect();
+            let syllable = syllable.to_ascii_lowercase();
+            TABLE
+                .iter()
+                .find(|&&(romaji, _)| romaji == syllable)
+                .map(|&(_, kana)| (len, kana))
+        });
+        match hit {
+            Some((len, kana)) => {
+                res.push(kana);
+                i += len;
+            }
+            None => {
+                res.push(cs[i]);
+                i += 1;
+            }
+        }
+    }
+
+    res
+}
+
 type PasswordVec = ArrayVec<PasswordChar, { Password::MAX_LEN }>;
 type PatternVec = ArrayVec<PatternChar, { Password::MAX_LEN }>;
 
-#[derive(Debug)]
-pub(crate) struct Pattern(PatternVec);
+/// '?' を含みうるパスワードのパターン。
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct Pattern(PatternVec);
 
 impl Pattern {
-    fn new(inner: PatternVec) -> Self {
-        Self(inner)
+    /// `PatternChar` のスライスから `Pattern` を作る。文字数が範囲外なら `None` を返す。
+    ///
+    /// '?' の個数は制限しないので、`Query::parse()` では受け付けないパターンも作れる。
+    pub fn new(chars: &[PatternChar]) -> Option<Self> {
+        matches!(chars.len(), Password::MIN_LEN..=Password::MAX_LEN)
+            .then(|| Self(chars.iter().copied().collect()))
     }
 
-    fn as_slice(&self) -> &[PatternChar] {
+    /// パターン全体を含むスライスを返す。
+    pub fn as_slice(&self) -> &[PatternChar] {
         self.0.as_slice()
     }
+
+    /// パターンに含まれる '?' の個数を返す。
+    pub fn count_any(&self) -> usize {
+        self.iter().filter(|&&e| e == PatternChar::Any).count()
+    }
 }
 
 impl std::ops::Deref for Pattern {
@@ -78,26 +307,72 @@ impl std::ops::Deref for Pattern {
     }
 }
 
+impl From<&Password> for Pattern {
+    fn from(password: &Password) -> Self {
+        Self(
+            password
+                .iter()
+                .copied()
+                .map(PatternChar::Password)
+                .collect(),
+        )
+    }
+}
+
+/// パターンをひらがな文字列(空白区切りなし)としてフォーマットする。
+impl std::fmt::Display for Pattern {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for e in self.iter() {
+            e.fmt(f)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// パターン内の文字。
 #[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
-pub(crate) enum PatternChar {
+pub enum PatternChar {
+    /// 確定した文字。
     Password(PasswordChar),
+    /// 任意の文字 ('?')。
     Any,
 }
 
 impl PatternChar {
-    pub(crate) fn from_char(c: char) -> Option<Self> {
+    /// ひらがな文字または '?' を `PatternChar` に変換する。無効な文字に対しては `None` を返す。
+    pub fn from_char(c: char) -> Option<Self> {
         match c {
             '?' => Some(Self::Any),
             _ => PasswordChar::from_char(c).map(Self::Password),
         }
     }
+
+    /// 対応する文字を返す。
+    pub fn to_char(self) -> char {
+        match self {
+            Self::Password(pc) => pc.to_char(),
+            Self::Any => '?',
+        }
+    }
+}
+
+impl std::fmt::Display for PatternChar {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_char(self.to_char())
+    }
 }
 
-#[derive(Debug)]
-pub(crate) enum QueryParseError {
+/// クエリのパース時に発生しうるエラー。
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum QueryParseError {
+    /// 入力が空(空白のみを含む)。
     Empty,
+    /// 入力が長すぎる。
     TooLong,
+    /// '?' が多すぎる。
     TooManyAny,
+    /// 無効な文字が含まれている。`pos` は空白を除いた位置 (0-based)。
     InvalidChar { pos: usize, ch: char },
 }
 
@@ -111,3 +386,110 @@ impl std::fmt::Display for QueryParseError {
         }
     }
 }
+
+impl std::error::Error for QueryParseError {}
+
+/// クエリの問題点 1 個。`Query::diagnose()` が返す。
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct QueryDiagnostic {
+    pub error: QueryParseError,
+    /// 問題のある箇所の、元の文字列内での位置(文字単位)。
+    pub span: std::ops::Range<usize>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pattern(s: &str) -> Pattern {
+        let v: Vec<PatternChar> = s
+            .chars()
+            .map(|c| PatternChar::from_char(c).unwrap())
+            .collect();
+        Pattern::new(&v).unwrap()
+    }
+
+    #[test]
+    fn test_query_parse() {
+        assert_eq!(
+            Query::parse("ふ"),
+            Ok(Query::Password(Password::parse("ふ").unwrap()))
+        );
+        assert_eq!(
+            Query::parse("あ ば ぽ"),
+            Ok(Query::Password(Password::parse("あばぽ").unwrap()))
+        );
+        assert_eq!(
+            Query::parse("ふ?あ?"),
+            Ok(Query::Pattern(pattern("ふ?あ?")))
+        );
+
+        assert_eq!(Query::parse(" "), Err(QueryParseError::Empty));
+        assert_eq!(
+            Query::parse(&"あ".repeat(Password::MAX_LEN + 1)),
+            Err(QueryParseError::TooLong)
+        );
+        assert_eq!(Query::parse("????"), Err(QueryParseError::TooManyAny));
+        assert_eq!(
+            Query::parse("あ いx"),
+            Err(QueryParseError::InvalidChar { pos: 2, ch: 'x' })
+        );
+    }
+
+    #[test]
+    fn test_query_display() {
+        for s in ["ふ", "あばぽ", "ふ?あ?", "???"] {
+            let query = Query::parse(s).unwrap();
+            assert_eq!(query.to_string(), s);
+            assert_eq!(Query::parse(&query.to_string()), Ok(query));
+        }
+    }
+
+    #[test]
+    fn test_query_diagnose() {
+        assert!(Query::diagnose("ふ?").is_empty());
+        assert_eq!(
+            Query::diagnose(""),
+            [QueryDiagnostic {
+                error: QueryParseError::Empty,
+                span: 0..0,
+            }]
+        );
+        assert_eq!(
+            Query::diagnose("x ????y"),
+            [
+                QueryDiagnostic {
+                    error: QueryParseError::InvalidChar { pos: 0, ch: 'x' },
+                    span: 0..1,
+                },
+                QueryDiagnostic {
+                    error: QueryParseError::TooManyAny,
+                    span: 5..6,
+                },
+                QueryDiagnostic {
+                    error: QueryParseError::InvalidChar { pos: 5, ch: 'y' },
+                    span: 6..7,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_pattern_new() {
+        assert_eq!(Pattern::new(&[]), None);
+        assert_eq!(pattern("????").count_any(), 4);
+        assert_eq!(
+            Pattern::from(&Password::parse("あば").unwrap()),
+            pattern("あば")
+        );
+    }
+
+    #[test]
+    fn test_normalize_query_input() {
+        assert_eq!(normalize_query_input("フッカツ", false), "ふっかつ");
+        assert_eq!(normalize_query_input("ﾊﾟｽﾜｰﾄﾞ", false), "ぱすわーど");
+        assert_eq!(normalize_query_input("カ\u{3099}？", false), "が?");
+        assert_eq!(normalize_query_input("momo ?", true), "もも ?");
+        assert_eq!(normalize_query_input("momo", false), "momo");
+    }
+}
@@ -0,0 +1,549 @@
+//! RAM 上のセーブデータの各項目の位置。
+//!
+//! このゲームの RAM の配置は公開された資料が見当たらないので、内容の分かっている RAM から学習する。
+//! `RamMap::learn()` に RAM とそのときのセーブデータ(その場で取得したパスワードをデコードしたものなど)を
+//! 与えるたびに、各項目の値と矛盾しない位置だけを候補に残す。候補が 1 つに定まった項目だけを読み書きする。
+//! 学習する RAM ごとに値が異なる項目ほど早く定まる。
+//!
+//! 各項目の格納形式は以下を仮定している。仮定に合わない項目は候補がなくなる:
+//!
+//! * 経験値・所持金: 2 バイトのリトルエンディアン
+//! * 預金・年齢・タイマー・復活地点・各装備枠のインデックス: 1 バイト
+//! * フラグ群: 値の昇順に並んだ連続するビット列(各バイトの下位ビットから)、または 1 フラグ 1 バイト(0 以外で立っている)
+//! * インベントリ: 8 バイトのアイテム ID の列。空欄は 0 で、空欄の後にアイテムはない
+
+use momoden_password::*;
+
+use crate::flags::{Flag, FlagSet};
+use crate::game::*;
+use crate::layout::SavedataField;
+
+/// インベントリの枠数。
+const INVENTORY_LEN: usize = 8;
+
+/// RAM 上の項目の位置。アドレスは RAM の先頭からのオフセット。
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub(crate) enum RamLocation {
+    /// このアドレスから始まる 2 バイトのリトルエンディアンの値。
+    Word(usize),
+    /// このアドレスから始まる、1 要素 1 バイトの列。
+    Bytes(usize),
+    /// RAM 全体を通したこのビット位置(バイト `bit / 8` のビット `bit % 8`)から始まる、1 要素 1 ビットの列。
+    Bits(usize),
+}
+
+/// アドレスを 16 進数で表す。ビット列は「アドレス.ビット」。
+impl std::fmt::Display for RamLocation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            Self::Word(addr) | Self::Bytes(addr) => write!(f, "{addr:04X}"),
+            Self::Bits(bit) => write!(f, "{:04X}.{}", bit / 8, bit % 8),
+        }
+    }
+}
+
+/// 学習による項目の位置の推定状態。
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum RamFieldStatus {
+    /// まだ学習していない。
+    Unlearned,
+    /// 位置が 1 つに定まった。
+    Located(RamLocation),
+    /// 候補が複数残っている。
+    Ambiguous(usize),
+    /// 矛盾しない位置がない(格納形式の仮定が合わない、または学習に与えたセーブデータが誤っている)。
+    NotFound,
+}
+
+/// 学習した RAM 上の項目の位置。
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub(crate) struct RamMap {
+    /// 学習した RAM の個数。
+    samples: usize,
+    /// 各項目の位置の候補。学習前は空。
+    candidates: Vec<(SavedataField, Vec<RamLocation>)>,
+}
+
+impl RamMap {
+    /// 学習した RAM の個数を返す。
+    pub(crate) fn sample_count(&self) -> usize {
+        self.samples
+    }
+
+    /// RAM `ram` の内容がセーブデータ `savedata` であるとして、各項目の位置の候補を絞り込む。
+    pub(crate) fn learn(&mut self, ram: &[u8], savedata: &Savedata) {
+        if self.samples == 0 {
+            self.candidates = SavedataField::all()
+                .map(|field| (field, all_locations(field, ram.len())))
+                .collect();
+        }
+
+        for (field, locations) in &mut self.candidates {
+            let values = field_values(savedata, *field);
+            locations.retain(|&loc| {
+                values
+                    .iter()
+                    .enumerate()
+                    .all(|(i, &value)| read_element(ram, *field, loc, i) == Some(value))
+            });
+        }
+
+        self.samples += 1;
+    }
+
+    /// 項目 `field` の位置の推定状態を返す。
+    pub(crate) fn status(&self, field: SavedataField) -> RamFieldStatus {
+        let Some((_, locations)) = self.candidates.iter().find(|&&(f, _)| f == field) else {
+            return RamFieldStatus::Unlearned;
+        };
+
+        match locations[..] {
+            [] => RamFieldStatus::NotFound,
+            [loc] => RamFieldStatus::Located(loc),
+            _ => RamFieldStatus::Ambiguous(locations.len()),
+        }
+    }
+
+    fn location(&self, field: SavedataField) -> Option<RamLocation> {
+        match self.status(field) {
+            RamFieldStatus::Located(loc) => Some(loc),
+            _ => None,
+        }
+    }
+
+    /// RAM `ram` からセーブデータを読む。
+    ///
+    /// 位置が定まっていない項目と、RAM 上の値がセーブデータの値として不正な項目は `base` の値のままにし、
+    /// それらの項目を併せて返す。
+    pub(crate) fn read_savedata(
+        &self,
+        ram: &[u8],
+        base: &Savedata,
+    ) -> (Savedata, Vec<SavedataField>) {
+        let mut savedata = base.clone();
+        let mut unread = vec![];
+
+        for field in SavedataField::all() {
+            let read = self.location(field).and_then(|loc| {
+                let values: Vec<u16> = (0..field_len(field))
+                    .map(|i| read_element(ram, field, loc, i))
+                    .collect::<Option<_>>()?;
+                set_field_values(&mut savedata, field, &values)
+            });
+            if read.is_none() {
+                unread.push(field);
+            }
+        }
+
+        (savedata, unread)
+    }
+
+    /// RAM `ram` にセーブデータ `savedata` を書き込む。位置が定まっていない項目は書き込まず、それらの項目を返す。
+    pub(crate) fn write_savedata(&self, ram: &mut [u8], savedata: &Savedata) -> Vec<SavedataField> {
+        let mut unwritten = vec![];
+
+        for field in SavedataField::all() {
+            let written = self.location(field).and_then(|loc| {
+                let values = field_values(savedata, field);
+                // 途中まで書き込むことのないよう、先に範囲を確かめる。
+                (0..values.len()).try_for_each(|i| read_element(ram, field, loc, i).map(drop))?;
+                for (i, &value) in values.iter().enumerate() {
+                    write_element(ram, loc, i, value);
+                }
+                Some(())
+            });
+            if written.is_none() {
+                unwritten.push(field);
+            }
+        }
+
+        unwritten
+    }
+}
+
+/// 保存用の文字列形式。
+///
+/// 1 行目に「samples 学習した RAM の個数」を書き、続けて 1 行に 1 項目を「項目 候補 候補 ...」の形で書く。
+/// 候補は種別を表す文字('w': 2 バイト、'b': バイト列、't': ビット列)と 16 進数のアドレス(ビット列はビット位置)。
+impl std::fmt::Display for RamMap {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.samples == 0 {
+            return Ok(());
+        }
+
+        writeln!(f, "samples {}", self.samples)?;
+        for (field, locations) in &self.candidates {
+            write!(f, "{field:?}")?;
+            for &loc in locations {
+                match loc {
+                    RamLocation::Word(addr) => write!(f, " w{addr:X}")?,
+                    RamLocation::Bytes(addr) => write!(f, " b{addr:X}")?,
+                    RamLocation::Bits(bit) => write!(f, " t{bit:X}")?,
+                }
+            }
+            writeln!(f)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl std::str::FromStr for RamMap {
+    type Err = std::convert::Infallible;
+
+    /// 保存用の文字列形式から読み込む。不正な行や候補は無視する。
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut this = Self::default();
+
+        for line in s.lines() {
+            let mut tokens = line.split_whitespace();
+            let Some(head) = tokens.next() else {
+                continue;
+            };
+
+            if head == "samples" {
+                this.samples = tokens.next().and_then(|n| n.parse().ok()).unwrap_or(0);
+                continue;
+            }
+
+            let Some(field) = SavedataField::all().find(|field| format!("{field:?}") == head)
+            else {
+                continue;
+            };
+            let locations = tokens
+                .filter_map(|token| {
+                    let (kind, addr) = token.split_at_checked(1)?;
+                    let addr = usize::from_str_radix(addr, 16).ok()?;
+                    match kind {
+                        "w" => Some(RamLocation::Word(addr)),
+                        "b" => Some(RamLocation::Bytes(addr)),
+                        "t" => Some(RamLocation::Bits(addr)),
+                        _ => None,
+                    }
+                })
+                .collect();
+            this.candidates.push((field, locations));
+        }
+
+        if this.samples == 0 || this.candidates.len() != SavedataField::all().count() {
+            return Ok(Self::default());
+        }
+
+        Ok(this)
+    }
+}
+
+/// 項目 `field` の値の要素数。
+fn field_len(field: SavedataField) -> usize {
+    match field {
+        SavedataField::Spells => Spell::ALL.len(),
+        SavedataField::Events => Event::ALL.len(),
+        SavedataField::Treasures => Treasure::ALL.len(),
+        SavedataField::Minions => Minion::ALL.len(),
+        SavedataField::Bookmarks => Bookmark::ALL.len(),
+        SavedataField::Inventory => INVENTORY_LEN,
+        _ => 1,
+    }
+}
+
+fn is_flags(field: SavedataField) -> bool {
+    matches!(
+        field,
+        SavedataField::Spells
+            | SavedataField::Events
+            | SavedataField::Treasures
+            | SavedataField::Minions
+            | SavedataField::Bookmarks
+    )
+}
+
+/// RAM の長さが `ram_len` のとき、項目 `field` が置かれうる全ての位置を返す。
+fn all_locations(field: SavedataField, ram_len: usize) -> Vec<RamLocation> {
+    let len = field_len(field);
+    let bytes = (0..(ram_len + 1).saturating_sub(len)).map(RamLocation::Bytes);
+
+    match field {
+        SavedataField::Xp | SavedataField::Purse => (0..ram_len.saturating_sub(1))
+            .map(RamLocation::Word)
+            .collect(),
+        _ if is_flags(field) => (0..(8 * ram_len + 1).saturating_sub(len))
+            .map(RamLocation::Bits)
+            .chain(bytes)
+            .collect(),
+        _ => bytes.collect(),
+    }
+}
+
+/// 位置 `loc` にある項目 `field` の `i` 番目の要素を読む。RAM の範囲外なら `None` を返す。
+fn read_element(ram: &[u8], field: SavedataField, loc: RamLocation, i: usize) -> Option<u16> {
+    match loc {
+        RamLocation::Word(addr) => {
+            let bytes = ram.get(addr + 2 * i..)?.get(..2)?;
+            Some(u16::from_le_bytes([bytes[0], bytes[1]]))
+        }
+        RamLocation::Bytes(addr) => {
+            let b = *ram.get(addr + i)?;
+            Some(if is_flags(field) {
+                u16::from(b != 0)
+            } else {
+                u16::from(b)
+            })
+        }
+        RamLocation::Bits(bit) => {
+            let bit = bit + i;
+            ram.get(bit / 8).map(|&b| u16::from(b >> (bit % 8) & 1))
+        }
+    }
+}
+
+/// 位置 `loc` の `i` 番目の要素に `value` を書く。範囲は呼び出し側で確かめておくこと。
+fn write_element(ram: &mut [u8], loc: RamLocation, i: usize, value: u16) {
+    match loc {
+        RamLocation::Word(addr) => {
+            ram[addr + 2 * i..addr + 2 * i + 2].copy_from_slice(&value.to_le_bytes());
+        }
+        RamLocation::Bytes(addr) => ram[addr + i] = value as u8,
+        RamLocation::Bits(bit) => {
+            let bit = bit + i;
+            let mask = 1 << (bit % 8);
+            if value != 0 {
+                ram[bit / 8] |= mask;
+            } else {
+                ram[bit / 8] &= !mask;
+            }
+        }
+    }
+}
+
+/// セーブデータの項目 `field` の値を要素の列として返す。フラグは 0 か 1、インベントリの空欄は 0。
+fn field_values(savedata: &Savedata, field: SavedataField) -> Vec<u16> {
+    fn flag_values<F: Flag>(savedata: &Savedata) -> Vec<u16> {
+        let flags = FlagSet::<F>::from_savedata(savedata);
+        F::ALL
+            .iter()
+            .map(|&flag| u16::from(flags.contains(flag)))
+            .collect()
+    }
+
+    match field {
+        SavedataField::Xp => vec![savedata.xp],
+        SavedataField::Purse => vec![savedata.purse],
+        SavedataField::Deposit => vec![u16::from(savedata.deposit.get())],
+        SavedataField::Age => vec![u16::from(savedata.age)],
+        SavedataField::AgeTimerHi => vec![u16::from(savedata.age_timer_hi)],
+        SavedataField::Respawn => vec![u16::from(savedata.respawn.get())],
+        SavedataField::Spells => flag_values::<Spell>(savedata),
+        SavedataField::Events => flag_values::<Event>(savedata),
+        SavedataField::Treasures => flag_values::<Treasure>(savedata),
+        SavedataField::Minions => flag_values::<Minion>(savedata),
+        SavedataField::Bookmarks => flag_values::<Bookmark>(savedata),
+        SavedataField::Equipment(slot) => {
+            vec![u16::from(equipment_index(&savedata.equipment, slot))]
+        }
+        SavedataField::Inventory => (0..INVENTORY_LEN)
+            .map(|i| {
+                savedata
+                    .inventory
+                    .get(i)
+                    .map_or(0, |id| u16::from(id.get()))
+            })
+            .collect(),
+    }
+}
+
+/// セーブデータの項目 `field` を要素の列 `values` にする。値が不正なら何もせず `None` を返す。
+fn set_field_values(savedata: &mut Savedata, field: SavedataField, values: &[u16]) -> Option<()> {
+    fn set_flags<F: Flag>(savedata: &mut Savedata, values: &[u16]) {
+        let group = F::group_mut(savedata);
+        for (&flag, &value) in F::ALL.iter().zip(values) {
+            group[flag] = value != 0;
+        }
+    }
+
+    let byte = u8::try_from(values[0]).ok();
+
+    match field {
+        SavedataField::Xp => savedata.xp = values[0],
+        SavedataField::Purse => savedata.purse = values[0],
+        SavedataField::Deposit => savedata.deposit = Deposit::new(byte?)?,
+        SavedataField::Age => savedata.age = byte?,
+        SavedataField::AgeTimerHi => savedata.age_timer_hi = byte?,
+        SavedataField::Respawn => savedata.respawn = RespawnId::new(byte?)?,
+        SavedataField::Spells => set_flags::<Spell>(savedata, values),
+        SavedataField::Events => set_flags::<Event>(savedata, values),
+        SavedataField::Treasures => set_flags::<Treasure>(savedata, values),
+        SavedataField::Minions => set_flags::<Minion>(savedata, values),
+        SavedataField::Bookmarks => set_flags::<Bookmark>(savedata, values),
+        SavedataField::Equipment(slot) => {
+            set_equipment_index(&mut savedata.equipment, slot, byte?)?
+        }
+        SavedataField::Inventory => {
+            let len = values
+                .iter()
+                .position(|&id| id == 0)
+                .unwrap_or(values.len());
+            if values[len..].iter().any(|&id| id != 0) {
+                return None;
+            }
+            savedata.inventory = values[..len]
+                .iter()
+                .map(|&id| ItemId::new(u8::try_from(id).ok()?))
+                .collect::<Option<_>>()?;
+        }
+    }
+
+    Some(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::preset::{preset_savedata, Preset};
+
+    /// テスト用の RAM の配置。
+    fn truth() -> RamMap {
+        let location = |field| match field {
+            SavedataField::Xp => RamLocation::Word(0x100),
+            SavedataField::Purse => RamLocation::Word(0x102),
+            SavedataField::Deposit => RamLocation::Bytes(0x104),
+            SavedataField::Age => RamLocation::Bytes(0x105),
+            SavedataField::AgeTimerHi => RamLocation::Bytes(0x106),
+            SavedataField::Respawn => RamLocation::Bytes(0x107),
+            SavedataField::Spells => RamLocation::Bits(8 * 0x110),
+            SavedataField::Events => RamLocation::Bytes(0x120),
+            SavedataField::Treasures => RamLocation::Bits(8 * 0x130 + 3),
+            SavedataField::Minions => RamLocation::Bytes(0x140),
+            SavedataField::Bookmarks => RamLocation::Bits(8 * 0x150 + 1),
+            SavedataField::Equipment(slot) => RamLocation::Bytes(0x160 + slot as usize),
+            SavedataField::Inventory => RamLocation::Bytes(0x170),
+        };
+
+        RamMap {
+            samples: 1,
+            candidates: SavedataField::all()
+                .map(|field| (field, vec![location(field)]))
+                .collect(),
+        }
+    }
+
+    /// 乱数で埋めた RAM に `savedata` を書き込んだものを返す。
+    fn ram_with(savedata: &Savedata, seed: u32) -> Vec<u8> {
+        let mut state = seed.wrapping_mul(2_654_435_761).wrapping_add(1);
+        let mut ram: Vec<u8> = (0..0x800)
+            .map(|_| {
+                state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+                (state >> 16) as u8
+            })
+            .collect();
+        assert_eq!(truth().write_savedata(&mut ram, savedata), []);
+        ram
+    }
+
+    fn samples() -> Vec<Savedata> {
+        let mut samples: Vec<Savedata> = Preset::all().into_iter().map(preset_savedata).collect();
+        for (i, savedata) in samples.iter_mut().enumerate() {
+            savedata.age = 10 * i as u8;
+            savedata.age_timer_hi = 3 * i as u8;
+            // プリセットのままでは連動して変わる項目があり、位置を見分けられないので値を散らす。
+            savedata.treasures.fur = i % 2 == 0;
+            savedata.treasures.hourai = i % 3 == 0;
+            savedata.bookmarks.hanasaka = i % 3 == 1;
+            let equipment = &mut savedata.equipment;
+            set_equipment_index(equipment, EquipmentSlot::Accessory2, (i % 2) as u8).unwrap();
+            set_equipment_index(equipment, EquipmentSlot::Accessory3, (i / 2 % 2) as u8).unwrap();
+        }
+        samples
+    }
+
+    #[test]
+    fn test_learn() {
+        let mut map = RamMap::default();
+        assert_eq!(map.status(SavedataField::Xp), RamFieldStatus::Unlearned);
+
+        for (i, savedata) in samples().iter().enumerate() {
+            map.learn(&ram_with(savedata, i as u32), savedata);
+        }
+        assert_eq!(map.sample_count(), samples().len());
+
+        let truth = truth();
+        for field in SavedataField::all() {
+            assert_eq!(map.status(field), truth.status(field), "{field:?}");
+        }
+    }
+
+    #[test]
+    fn test_learn_ambiguous() {
+        let savedata = preset_savedata(Preset::Start);
+        let mut map = RamMap::default();
+        map.learn(&vec![0; 0x800], &savedata);
+
+        // 値が 0 の項目は全ての位置が候補に残る。
+        assert_eq!(
+            map.status(SavedataField::Age),
+            RamFieldStatus::Ambiguous(0x800)
+        );
+        assert_eq!(
+            map.status(SavedataField::Xp),
+            RamFieldStatus::Ambiguous(0x7FF)
+        );
+        // 0 でない値は見つからない。
+        assert_eq!(
+            map.status(SavedataField::Inventory),
+            RamFieldStatus::NotFound
+        );
+
+        let (read, unread) = map.read_savedata(&vec![0xFF; 0x800], &savedata);
+        assert_eq!(read, savedata);
+        assert_eq!(unread.len(), SavedataField::all().count());
+    }
+
+    #[test]
+    fn test_read_write_savedata() {
+        let truth = truth();
+
+        let mut expected = preset_savedata(Preset::Urashima);
+        expected.age = 42;
+        let ram = ram_with(&expected, 100);
+        let (savedata, unread) = truth.read_savedata(&ram, &Savedata::default());
+        assert_eq!(unread, []);
+        assert_eq!(savedata, expected);
+
+        // 書き込んだ項目以外の RAM は変えない。
+        let other = preset_savedata(Preset::Endgame);
+        let mut written = ram.clone();
+        assert_eq!(truth.write_savedata(&mut written, &other), []);
+        assert_eq!(truth.read_savedata(&written, &expected).0, other);
+        let changed: Vec<usize> = (0..ram.len()).filter(|&i| ram[i] != written[i]).collect();
+        assert!(changed.iter().all(|&i| (0x100..0x178).contains(&i)));
+        assert_eq!(written[0x150] & 1, ram[0x150] & 1);
+
+        // 不正な値は読まない。
+        let mut broken = ram.clone();
+        broken[0x104] = 0x40;
+        broken[0x172] = 0;
+        let (savedata, unread) = truth.read_savedata(&broken, &expected);
+        assert_eq!(unread, [SavedataField::Deposit, SavedataField::Inventory]);
+        assert_eq!(savedata, expected);
+    }
+
+    #[test]
+    fn test_to_string() {
+        let mut map = RamMap::default();
+        assert_eq!(map.to_string(), "");
+        assert_eq!("".parse::<RamMap>().unwrap(), map);
+
+        for (i, savedata) in samples().iter().enumerate().take(2) {
+            map.learn(&ram_with(savedata, i as u32), savedata);
+        }
+        let s = map.to_string();
+        assert!(s.starts_with("samples 2\nAgeTimerHi "));
+        assert_eq!(s.parse::<RamMap>().unwrap(), map);
+
+        assert_eq!(truth().to_string().parse::<RamMap>().unwrap(), truth());
+
+        // 項目が欠けていれば読み込まない。
+        let truncated: String = s.lines().take(3).map(|line| format!("{line}\n")).collect();
+        assert_eq!(truncated.parse::<RamMap>().unwrap(), RamMap::default());
+    }
+}
@@ -0,0 +1,219 @@
+//! 手で編集したパスワードのチェックサムの修復。
+//!
+//! 変更してよい箇所(文字位置またはフィールド)を利用者が指定し、その範囲内で変更が最小になるように
+//! パスワードを有効にする。
+
+use momoden_password::*;
+
+use crate::equivalent::*;
+use crate::vanity::{free_field_masks, FreeField};
+
+const COST_INF: u8 = u8::MAX;
+
+/// 文字位置 `positions`(0 始まり)の文字のみを変えてパスワードを有効にする。変える文字数が最小のものを返す。
+///
+/// 既に有効ならそのまま返す。修復できなければ `None` を返す。
+pub(crate) fn repair_by_positions(password: &Password, positions: &[usize]) -> Option<Password> {
+    let chars: Vec<u8> = password.iter().map(|pc| pc.to_inner()).collect();
+    let candidates = |i: usize| -> Vec<u8> {
+        if positions.contains(&i) {
+            (0..=0x3F).collect()
+        } else {
+            vec![chars[i]]
+        }
+    };
+    let char_cost = |i: usize, pc: u8| u8::from(pc != chars[i]);
+
+    if chars.len() < 3 {
+        return repair_short_by_positions(&chars, positions);
+    }
+
+    let mut cost = vec![COST_INF; STATE_COUNT];
+    for c0 in candidates(0) {
+        for c1 in candidates(1) {
+            let sum_add = decode_char(0, c0, FIRST_PREV);
+            let sum_xor = decode_char(1, c1, c0);
+            cost[state(c1, sum_add, sum_xor)] = char_cost(0, c0) + char_cost(1, c1);
+        }
+    }
+
+    let mut choices = vec![];
+    for i in 2..chars.len() {
+        let mut next = vec![COST_INF; STATE_COUNT];
+        let mut choice = vec![0; STATE_COUNT];
+        let pcs = candidates(i);
+        for (s, &c) in cost.iter().enumerate() {
+            if c == COST_INF {
+                continue;
+            }
+            let (prev, sum_add, sum_xor) = unpack_state(s);
+            for &pc in &pcs {
+                let b = decode_char(i, pc, prev);
+                let ns = state(pc, sum_add.wrapping_sub(b) & 0x3F, sum_xor ^ b);
+                let nc = c + char_cost(i, pc);
+                if nc < next[ns] {
+                    next[ns] = nc;
+                    choice[ns] = b;
+                }
+            }
+        }
+        cost = next;
+        choices.push(choice);
+    }
+
+    restore(&cost, COST_INF, &choices)
+}
+
+/// 長さ 2 以下のパスワードを、文字位置 `positions` の文字のみを変えて有効にする。
+///
+/// この長さではデータ部がなく、チェックサムとして (0x3F, 0x3F) が計算されるので、
+/// 有効なパスワードは各長さに 1 つしかない。それと異なる文字が全て変えてよい位置にあれば修復できる。
+fn repair_short_by_positions(chars: &[u8], positions: &[usize]) -> Option<Password> {
+    let max = SerializedByte::MAX;
+    let valid = SerializedBytes::new(&[max, max][..chars.len()])
+        .unwrap()
+        .to_password();
+
+    valid
+        .iter()
+        .zip(chars)
+        .enumerate()
+        .all(|(i, (pc, &c))| pc.to_inner() == c || positions.contains(&i))
+        .then_some(valid)
+}
+
+/// フィールド `fields` のビットのみを変えてパスワードを有効にする。変えるビット数が最小のものを返す。
+///
+/// チェックサム(先頭 2 文字)は変えず、それに合うようにフィールドを調整する。
+/// 既に有効ならそのまま返す。修復できなければ `None` を返す(長さ 2 以下ではフィールドがないので修復できない)。
+pub(crate) fn repair_by_fields(password: &Password, fields: &[FreeField]) -> Option<Password> {
+    let bytes = SerializedBytes::from_password(password);
+    if bytes.len() < 3 {
+        return password.is_valid().then(|| password.clone());
+    }
+
+    let data: Vec<u8> = bytes[2..].iter().map(|b| b.get()).collect();
+    let soft_masks = free_field_masks(fields, data.len());
+    let c0 = password[0].to_inner();
+    let c1 = password[1].to_inner();
+
+    let mut cost = vec![COST_INF; STATE_COUNT];
+    cost[state(c1, bytes[0].get(), bytes[1].get())] = 0;
+
+    let mut choices = vec![];
+    for (i, (&orig, &soft)) in data.iter().zip(&soft_masks).enumerate() {
+        let mut next = vec![COST_INF; STATE_COUNT];
+        let mut choice = vec![0; STATE_COUNT];
+        let candidates = ByteConstraint {
+            mask: !soft & 0x3F,
+            value: orig & !soft,
+        }
+        .candidates()
+        .collect::<Vec<_>>();
+        for (s, &c) in cost.iter().enumerate() {
+            if c == COST_INF {
+                continue;
+            }
+            let (prev, sum_add, sum_xor) = unpack_state(s);
+            for &b in &candidates {
+                let pc = encode_char(i + 2, b, prev);
+                let ns = state(pc, sum_add.wrapping_sub(b) & 0x3F, sum_xor ^ b);
+                let nc = c + (b ^ orig).count_ones() as u8;
+                if nc < next[ns] {
+                    next[ns] = nc;
+                    choice[ns] = b;
+                }
+            }
+        }
+        cost = next;
+        choices.push(choice);
+    }
+
+    let repaired = restore(&cost, COST_INF, &choices)?;
+    debug_assert_eq!((repaired[0].to_inner(), repaired[1].to_inner()), (c0, c1));
+    Some(repaired)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::preset::{preset_savedata, Preset};
+
+    fn decode(password: &Password) -> Option<Savedata> {
+        SerializedBytes::from_password(password).to_savedata()
+    }
+
+    /// 有効なパスワードの `pos` 文字目を 1 つずらして無効にしたもの。
+    fn broken_password(pos: usize) -> (Password, Password) {
+        let savedata = preset_savedata(Preset::Hanasaka);
+        let valid = SerializedBytes::from_savedata(&savedata).to_password();
+        let mut chars: Vec<PasswordChar> = valid.iter().copied().collect();
+        let inner = (chars[pos].to_inner() + 1) & 0x3F;
+        chars[pos] = PasswordChar::from_inner(inner).unwrap();
+        let broken = Password::new(&chars).unwrap();
+        assert!(!broken.is_valid());
+        (valid, broken)
+    }
+
+    fn changed_positions(a: &Password, b: &Password) -> Vec<usize> {
+        (0..a.len()).filter(|&i| a[i] != b[i]).collect()
+    }
+
+    #[test]
+    fn test_repair_by_positions() {
+        let (valid, broken) = broken_password(5);
+        assert_eq!(repair_by_positions(&valid, &[]), Some(valid.clone()));
+        assert_eq!(repair_by_positions(&broken, &[]), None);
+
+        for positions in [&[5, 10][..], &[0, 1], &[0, 1, 5]] {
+            let repaired = repair_by_positions(&broken, positions).unwrap();
+            assert!(repaired.is_valid());
+            assert_eq!(repaired.len(), broken.len());
+            let changed = changed_positions(&broken, &repaired);
+            assert!(changed.iter().all(|i| positions.contains(i)));
+        }
+
+        // 壊した位置を直すのが最小の変更。
+        let repaired = repair_by_positions(&broken, &[5, 10]).unwrap();
+        assert_eq!(changed_positions(&broken, &repaired), [5]);
+    }
+
+    #[test]
+    fn test_repair_by_positions_short() {
+        for len in 1..=2 {
+            let broken = Password::new(&[PasswordChar::A; 2][..len]).unwrap();
+            assert!(!broken.is_valid());
+
+            let positions: Vec<usize> = (0..len).collect();
+            let repaired = repair_by_positions(&broken, &positions).unwrap();
+            assert!(repaired.is_valid());
+            assert_eq!(repaired.len(), len);
+
+            assert_eq!(repair_by_positions(&broken, &[]), None);
+        }
+    }
+
+    #[test]
+    fn test_repair_by_fields() {
+        let savedata = preset_savedata(Preset::Hanasaka);
+        let mut bytes = SerializedBytes::from_savedata(&savedata);
+        // 年齢のビット (データ部の 16..24 ビット目) を含むバイトを変える。
+        bytes[4] = SerializedByte::new(bytes[4].get() ^ 0x01).unwrap();
+        let broken = bytes.to_password();
+        assert!(!broken.is_valid());
+
+        assert_eq!(repair_by_fields(&broken, &[]), None);
+
+        let fields = [FreeField::Age, FreeField::Deposit];
+        let repaired = repair_by_fields(&broken, &fields).unwrap();
+        assert!(repaired.is_valid());
+        assert_eq!(repaired[..2], broken[..2]);
+
+        // 変わるのは指定したフィールドのみ。
+        let mut decoded = decode(&repaired).unwrap();
+        decoded.age = savedata.age;
+        decoded.deposit = savedata.deposit;
+        assert_eq!(decoded, savedata);
+    }
+}
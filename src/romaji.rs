@@ -0,0 +1,196 @@
+//! アイテム名などをローマ字で検索するための変換。
+//!
+//! パスワード入力用の `query::normalize_query_input()` はパスワードに使える文字の綴りしか扱わないので、
+//! 「ん」や拗音・促音を含む名前の検索には使えない。ここでは名前と入力の両方を訓令式に近い綴りにそろえてから
+//! 比較する。「ん」は常に "n" とするので、"ken" は「けん」にも「けな」の先頭にも合う。
+
+/// ひらがな 1 文字の綴り。
+#[rustfmt::skip]
+const TABLE: &[(char, &str)] = &[
+    ('あ', "a"), ('い', "i"), ('う', "u"), ('え', "e"), ('お', "o"),
+    ('か', "ka"), ('き', "ki"), ('く', "ku"), ('け', "ke"), ('こ', "ko"),
+    ('さ', "sa"), ('し', "si"), ('す', "su"), ('せ', "se"), ('そ', "so"),
+    ('た', "ta"), ('ち', "ti"), ('つ', "tu"), ('て', "te"), ('と', "to"),
+    ('な', "na"), ('に', "ni"), ('ぬ', "nu"), ('ね', "ne"), ('の', "no"),
+    ('は', "ha"), ('ひ', "hi"), ('ふ', "hu"), ('へ', "he"), ('ほ', "ho"),
+    ('ま', "ma"), ('み', "mi"), ('む', "mu"), ('め', "me"), ('も', "mo"),
+    ('や', "ya"), ('ゆ', "yu"), ('よ', "yo"),
+    ('ら', "ra"), ('り', "ri"), ('る', "ru"), ('れ', "re"), ('ろ', "ro"),
+    ('わ', "wa"), ('ゐ', "wi"), ('ゑ', "we"), ('を', "wo"), ('ん', "n"),
+    ('が', "ga"), ('ぎ', "gi"), ('ぐ', "gu"), ('げ', "ge"), ('ご', "go"),
+    ('ざ', "za"), ('じ', "zi"), ('ず', "zu"), ('ぜ', "ze"), ('ぞ', "zo"),
+    ('だ', "da"), ('ぢ', "zi"), ('づ', "zu"), ('で', "de"), ('ど', "do"),
+    ('ば', "ba"), ('び', "bi"), ('ぶ', "bu"), ('べ', "be"), ('ぼ', "bo"),
+    ('ぱ', "pa"), ('ぴ', "pi"), ('ぷ', "pu"), ('ぺ', "pe"), ('ぽ', "po"),
+    ('ゔ', "vu"),
+    ('ぁ', "xa"), ('ぃ', "xi"), ('ぅ', "xu"), ('ぇ', "xe"), ('ぉ', "xo"),
+    ('ゃ', "xya"), ('ゅ', "xyu"), ('ょ', "xyo"), ('ゎ', "xwa"), ('っ', "xtu"),
+];
+
+/// 入力でよく使われる綴りの揺れ。上から順に置き換える。
+const SPELLINGS: &[(&str, &str)] = &[
+    ("shi", "si"),
+    ("sh", "sy"),
+    ("chi", "ti"),
+    ("ch", "ty"),
+    ("tsu", "tu"),
+    ("fu", "hu"),
+    ("ji", "zi"),
+    ("j", "zy"),
+    ("di", "zi"),
+    ("du", "zu"),
+];
+
+/// ひらがなをローマ字に変換する。ひらがな以外の文字はそのまま残す。
+///
+/// 「きゃ」などの拗音は "kya"、「っ」は次の子音を重ねる。
+pub(crate) fn kana_to_romaji(s: &str) -> String {
+    let cs: Vec<char> = s.chars().collect();
+    let mut res = String::with_capacity(2 * s.len());
+
+    let mut sokuon = false;
+    let mut i = 0;
+    while i < cs.len() {
+        let c = cs[i];
+        i += 1;
+
+        let Some(mut syllable) = kana_spelling(c).map(str::to_owned) else {
+            if std::mem::take(&mut sokuon) {
+                res.push_str("xtu");
+            }
+            res.push(c);
+            continue;
+        };
+
+        if c == 'っ' {
+            if std::mem::take(&mut sokuon) {
+                res.push_str("xtu");
+            }
+            sokuon = true;
+            continue;
+        }
+
+        // い段の文字 + 小さい「ゃゅょ」
+        if let Some(vowel) = cs.get(i).and_then(|&small| youon_vowel(small)) {
+            if syllable.len() >= 2 && syllable.ends_with('i') {
+                syllable.pop();
+                syllable.push('y');
+                syllable.push(vowel);
+                i += 1;
+            }
+        }
+
+        if std::mem::take(&mut sokuon) {
+            match syllable.chars().next() {
+                Some(consonant) if !"aiueon".contains(consonant) => res.push(consonant),
+                _ => res.push_str("xtu"),
+            }
+        }
+        res.push_str(&syllable);
+    }
+    if sokuon {
+        res.push_str("xtu");
+    }
+
+    res
+}
+
+/// ローマ字の入力を `kana_to_romaji()` の綴りにそろえる。
+///
+/// 大文字小文字を区別せず、ヘボン式の綴り("shi", "cha", "tsu", "fu", "ji" など)と
+/// ローマ字入力での「ん」の綴り("nn", "n'")を受け付ける。入力途中の末尾の "sh", "ch", "ts", "j" も考慮する。
+pub(crate) fn normalize_romaji(s: &str) -> String {
+    let cs: Vec<char> = s.to_ascii_lowercase().chars().collect();
+
+    // "nn" と "n'" は「ん」。"konna" のように直後に母音が続く場合は「ん」+「な」なので残す。
+    let mut res = String::with_capacity(s.len());
+    let mut i = 0;
+    while i < cs.len() {
+        res.push(cs[i]);
+        if cs[i] == 'n' {
+            match cs.get(i + 1) {
+                Some('\'') => i += 1,
+                Some('n') if !matches!(cs.get(i + 2), Some('a' | 'i' | 'u' | 'e' | 'o' | 'y')) => {
+                    i += 1
+                }
+                _ => {}
+            }
+        }
+        i += 1;
+    }
+
+    // 入力途中の綴りは、その先に続きうるどの綴りにも合うよう短くする。
+    for (partial, prefix) in [("sh", "s"), ("ch", "t"), ("ts", "t"), ("j", "z")] {
+        if let Some(rest) = res.strip_suffix(partial) {
+            res = format!("{rest}{prefix}");
+            break;
+        }
+    }
+
+    SPELLINGS
+        .iter()
+        .fold(res, |res, &(from, to)| res.replace(from, to))
+}
+
+fn kana_spelling(c: char) -> Option<&'static str> {
+    TABLE
+        .iter()
+        .find(|&&(kana, _)| kana == c)
+        .map(|&(_, spelling)| spelling)
+}
+
+fn youon_vowel(c: char) -> Option<char> {
+    match c {
+        'ゃ' => Some('a'),
+        'ゅ' => Some('u'),
+        'ょ' => Some('o'),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_kana_to_romaji() {
+        assert_eq!(kana_to_romaji("ももたろう"), "momotarou");
+        assert_eq!(kana_to_romaji("あすかのけん"), "asukanoken");
+        assert_eq!(kana_to_romaji("しょうかいじょう"), "syoukaizyou");
+        assert_eq!(kana_to_romaji("びゃっこのけん"), "byakkonoken");
+        assert_eq!(kana_to_romaji("ぶんぶくちゃがま"), "bunbukutyagama");
+        assert_eq!(kana_to_romaji("うづきのどう"), "uzukinodou");
+        assert_eq!(kana_to_romaji("せんにん"), "sennin");
+
+        // 単独の小さい文字や、子音の続かない「っ」。
+        assert_eq!(kana_to_romaji("ゃ"), "xya");
+        assert_eq!(kana_to_romaji("あっ"), "axtu");
+        assert_eq!(kana_to_romaji("っあ"), "xtua");
+        assert_eq!(kana_to_romaji("(空欄)"), "(空欄)");
+    }
+
+    #[test]
+    fn test_normalize_romaji() {
+        assert_eq!(normalize_romaji("Shoukai"), "syoukai");
+        assert_eq!(normalize_romaji("chagama"), "tyagama");
+        assert_eq!(normalize_romaji("matcha"), "mattya");
+        assert_eq!(normalize_romaji("tsuru"), "turu");
+        assert_eq!(normalize_romaji("fugu"), "hugu");
+        assert_eq!(normalize_romaji("jinbaori"), "zinbaori");
+        assert_eq!(normalize_romaji("jou"), "zyou");
+        assert_eq!(normalize_romaji("uduki"), "uzuki");
+
+        // 「ん」の綴り。
+        assert_eq!(normalize_romaji("kenn"), "ken");
+        assert_eq!(normalize_romaji("kannji"), "kanzi");
+        assert_eq!(normalize_romaji("sennnin"), "sennin");
+        assert_eq!(normalize_romaji("konna"), "konna");
+        assert_eq!(normalize_romaji("kin'iro"), "kiniro");
+
+        // 入力途中。
+        assert_eq!(normalize_romaji("sh"), "s");
+        assert_eq!(normalize_romaji("kinch"), "kint");
+        assert_eq!(normalize_romaji("ts"), "t");
+        assert_eq!(normalize_romaji("oj"), "oz");
+    }
+}
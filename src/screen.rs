@@ -0,0 +1,254 @@
+//! パスワードをゲーム画面と同じ体裁で描画する。
+//!
+//! ゲームのフォント(CHR ROM)は同梱していないので、スクリーンショットから登録した文字テンプレート
+//! (`ocr::GlyphTemplates`)の字形と文字の位置を使い、8x8 ドットのタイル単位のドット絵として描く。
+//!
+//! 文字の位置が登録されていない(または登録時のパスワードより長い)場合は、`Password::display_pretty()` の
+//! 区切りを 1 行ずつ 1 文字おきに並べる。字形が登録されていない文字はブラウザの等幅フォントで代用する。
+//! これらの代用は実機の画面と照合したものではない。
+
+use std::fmt::Write as _;
+
+use momoden_password::*;
+
+use crate::ocr::{Glyph, GlyphTemplates, TilePos, TILE};
+use crate::util::data_url;
+
+/// 文字の位置が未登録のときの各行の文字数。
+const LINE_LENS: [usize; 6] = [5, 7, 5, 7, 7, 7];
+
+/// 文字の位置が未登録のときの文字の間隔(タイル)。
+const CHAR_PITCH: usize = 2;
+
+/// 文字の位置が未登録のときの行の間隔(タイル)。
+const LINE_PITCH: usize = 2;
+
+/// 周囲の余白(タイル)。
+const MARGIN: usize = 2;
+
+/// 描画時の拡大率。
+const SCALE: usize = 4;
+
+const COLOR_BACKGROUND: &str = "#000000";
+const COLOR_TEXT: &str = "#FFFFFF";
+
+/// 描画内容。座標は拡大前のドット単位。
+struct Screen {
+    width: usize,
+    height: usize,
+    /// 各文字の本体のタイルの左上の位置と、文字と、その字形(未登録なら `None`)。
+    chars: Vec<(usize, usize, PasswordChar, Option<Glyph>)>,
+}
+
+impl Screen {
+    fn new(password: &Password, templates: &GlyphTemplates) -> Self {
+        let positions: Vec<TilePos> = match templates.layout() {
+            layout if layout.len() >= password.len() => layout[..password.len()].to_vec(),
+            _ => default_positions(password),
+        };
+
+        // 濁点・半濁点のタイルのため、上は 1 タイル余分に空ける。文字が 0 行目にあってもその上に
+        // 1 タイル取れるよう、行は 1 つずらして扱う。
+        let col_min = positions.iter().map(|&(col, _)| col).min().unwrap();
+        let col_max = positions.iter().map(|&(col, _)| col).max().unwrap();
+        let row_min = positions.iter().map(|&(_, row)| row + 1).min().unwrap();
+        let row_max = positions.iter().map(|&(_, row)| row + 1).max().unwrap();
+        let left = col_min.saturating_sub(MARGIN);
+        let top = row_min.saturating_sub(MARGIN + 1);
+
+        let chars = positions
+            .into_iter()
+            .zip(password.iter())
+            .map(|((col, row), &pc)| {
+                let x = TILE * (col - left);
+                let y = TILE * (row + 1 - top);
+                (x, y, pc, templates.glyph(pc))
+            })
+            .collect();
+
+        Self {
+            width: TILE * (col_max + 1 + MARGIN - left),
+            height: TILE * (row_max + 1 + MARGIN - top),
+            chars,
+        }
+    }
+
+    /// 字形が登録されている文字のドットの位置を列挙する。
+    fn dots(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.chars
+            .iter()
+            .filter_map(|&(x, y, _, glyph)| glyph.map(|glyph| (x, y, glyph)))
+            .flat_map(|(x, y, glyph)| {
+                [(y - TILE, glyph.mark), (y, glyph.body)]
+                    .into_iter()
+                    .flat_map(move |(y, bits)| {
+                        (0..TILE * TILE)
+                            .filter(move |i| bits >> i & 1 != 0)
+                            .map(move |i| (x + i % TILE, y + i / TILE))
+                    })
+            })
+    }
+
+    /// 字形が登録されていない文字と、その本体のタイルの左上の位置を列挙する。
+    fn fallback_chars(&self) -> impl Iterator<Item = (usize, usize, PasswordChar)> + '_ {
+        self.chars
+            .iter()
+            .filter(|&&(_, _, _, glyph)| glyph.is_none())
+            .map(|&(x, y, pc, _)| (x, y, pc))
+    }
+}
+
+/// 文字の位置が未登録のときの各文字の位置を返す。上と左には余白と濁点・半濁点の分を空ける。
+fn default_positions(password: &Password) -> Vec<TilePos> {
+    let mut positions = vec![];
+    let mut rest = password.len();
+    for (r, len) in LINE_LENS.into_iter().enumerate() {
+        let len = len.min(rest);
+        positions.extend((0..len).map(|c| (MARGIN + CHAR_PITCH * c, MARGIN + 1 + LINE_PITCH * r)));
+        rest -= len;
+    }
+    positions
+}
+
+/// パスワードをゲーム画面の体裁で描いた SVG 文字列を返す。
+pub(crate) fn password_svg(password: &Password, templates: &GlyphTemplates) -> String {
+    let screen = Screen::new(password, templates);
+    let (w, h) = (screen.width, screen.height);
+
+    let mut svg = String::new();
+    write!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{}" viewBox="0 0 {w} {h}" shape-rendering="crispEdges">"#,
+        SCALE * w,
+        SCALE * h
+    )
+    .unwrap();
+    write!(
+        svg,
+        r#"<rect width="{w}" height="{h}" fill="{COLOR_BACKGROUND}"/>"#
+    )
+    .unwrap();
+
+    svg.push_str(r#"<path fill=""#);
+    svg.push_str(COLOR_TEXT);
+    svg.push_str(r#"" d=""#);
+    for (x, y) in screen.dots() {
+        write!(svg, "M{x} {y}h1v1h-1z").unwrap();
+    }
+    svg.push_str(r#""/>"#);
+
+    write!(
+        svg,
+        r#"<g fill="{COLOR_TEXT}" font-family="monospace" font-size="{TILE}" text-anchor="middle" dominant-baseline="central">"#
+    )
+    .unwrap();
+    for (x, y, pc) in screen.fallback_chars() {
+        write!(
+            svg,
+            r#"<text x="{}" y="{}">{}</text>"#,
+            x + TILE / 2,
+            y + TILE / 2,
+            pc.to_char()
+        )
+        .unwrap();
+    }
+    svg.push_str("</g></svg>");
+
+    svg
+}
+
+/// SVG 文字列を data URL に変換する。
+pub(crate) fn svg_data_url(svg: &str) -> String {
+    data_url("image/svg+xml;charset=utf-8", svg.as_bytes())
+}
+
+/// パスワードをゲーム画面の体裁で canvas に描き、PNG の data URL を返す。
+pub(crate) fn password_png_data_url(
+    password: &Password,
+    templates: &GlyphTemplates,
+) -> Option<String> {
+    use seed::prelude::JsCast as _;
+
+    let screen = Screen::new(password, templates);
+    let (w, h) = (SCALE * screen.width, SCALE * screen.height);
+    let size = SCALE * TILE;
+
+    let document = web_sys::window()?.document()?;
+    let canvas: web_sys::HtmlCanvasElement =
+        document.create_element("canvas").ok()?.dyn_into().ok()?;
+    canvas.set_width(u32::try_from(w).ok()?);
+    canvas.set_height(u32::try_from(h).ok()?);
+
+    let ctx: web_sys::CanvasRenderingContext2d = canvas.get_context("2d").ok()??.dyn_into().ok()?;
+    ctx.set_fill_style(&COLOR_BACKGROUND.into());
+    ctx.fill_rect(0.0, 0.0, w as f64, h as f64);
+    ctx.set_fill_style(&COLOR_TEXT.into());
+    for (x, y) in screen.dots() {
+        ctx.fill_rect(
+            (SCALE * x) as f64,
+            (SCALE * y) as f64,
+            SCALE as f64,
+            SCALE as f64,
+        );
+    }
+    ctx.set_font(&format!("{size}px monospace"));
+    ctx.set_text_align("center");
+    ctx.set_text_baseline("middle");
+    for (x, y, pc) in screen.fallback_chars() {
+        ctx.fill_text(
+            &pc.to_char().to_string(),
+            (SCALE * x + size / 2) as f64,
+            (SCALE * y + size / 2) as f64,
+        )
+        .ok()?;
+    }
+
+    canvas.to_data_url_with_type("image/png").ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn password(s: &str) -> Password {
+        let chars: Vec<PasswordChar> = s
+            .chars()
+            .map(|c| PasswordChar::from_char(c).unwrap())
+            .collect();
+        Password::new(&chars).unwrap()
+    }
+
+    #[test]
+    fn test_screen_top_row() {
+        // 「あ」の濁点タイルは空、本体タイルは左上の 1 ドットのみ。文字は 0 行目と 1 行目にある。
+        let templates: GlyphTemplates = "あ 0000000000000000 0000000000000001\nlayout 0,0 2,1\n"
+            .parse()
+            .unwrap();
+        let screen = Screen::new(&password("ああ"), &templates);
+
+        // 0 行目の文字の上にも濁点・半濁点の 1 タイルがある。
+        assert!(screen.chars.iter().all(|&(_, y, _, _)| y >= TILE));
+        let dots: Vec<_> = screen.dots().collect();
+        assert_eq!(dots, [(0, TILE), (2 * TILE, 2 * TILE)]);
+        assert_eq!(screen.width, TILE * (2 + 1 + MARGIN));
+        assert_eq!(screen.height, TILE * (2 + 1 + MARGIN));
+
+        assert!(password_svg(&password("ああ"), &templates).starts_with("<svg"));
+    }
+
+    #[test]
+    fn test_screen_default_layout() {
+        let screen = Screen::new(&password("あいうえおか"), &GlyphTemplates::default());
+
+        // 左と上は余白と濁点・半濁点の分を空け、2 行目は次の行に並べる。
+        let positions: Vec<_> = screen.chars.iter().map(|&(x, y, _, _)| (x, y)).collect();
+        let y0 = TILE * (MARGIN + 1);
+        let mut expected: Vec<_> = (0..5)
+            .map(|c| (TILE * (MARGIN + CHAR_PITCH * c), y0))
+            .collect();
+        expected.push((TILE * MARGIN, y0 + TILE * LINE_PITCH));
+        assert_eq!(positions, expected);
+        assert_eq!(screen.dots().count(), 0);
+        assert_eq!(screen.fallback_chars().count(), 6);
+    }
+}
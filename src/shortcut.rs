@@ -0,0 +1,205 @@
+//! セーブデータ編集フォームのキーボードショートカット。
+//!
+//! - `[` / `]`: 前/次の項目群(術、イベント、宝物、お供、ブックマーク、装備)を選ぶ
+//! - 数字キー: 選択中のフラグ群の n 番目のフラグを切り替える(0 は 10 番目)。装備では n 番目の枠を選ぶ
+//! - `↑` / `↓`: 装備の枠を選ぶ
+//! - `←` / `→`: 選択中の装備枠のインデックスを増減する
+//! - `Ctrl+Enter`: パスワードにセーブする(フォーカスがセーブデータ編集フォーム内にある場合のみ)
+//!
+//! テキストや数値の入力欄、セレクトボックスにフォーカスがある間は `Ctrl+Enter` 以外は無効。
+
+use crate::game::FlagGroup;
+
+/// ショートカットで選択する項目群。
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum SavedataSection {
+    Flags(FlagGroup),
+    Equipment,
+}
+
+impl SavedataSection {
+    pub(crate) fn all() -> impl Iterator<Item = Self> {
+        FlagGroup::all()
+            .map(Self::Flags)
+            .chain(std::iter::once(Self::Equipment))
+    }
+
+    /// `delta` 個先の項目群を返す(端では反対側に回り込む)。`section` が `None` なら先頭または末尾を返す。
+    pub(crate) fn step(section: Option<Self>, delta: isize) -> Self {
+        let sections: Vec<_> = Self::all().collect();
+        let len = sections.len() as isize;
+        let idx = match section {
+            Some(section) => {
+                let cur = sections.iter().position(|&s| s == section).unwrap() as isize;
+                (cur + delta).rem_euclid(len)
+            }
+            None if delta >= 0 => 0,
+            None => len - 1,
+        };
+        sections[idx as usize]
+    }
+}
+
+/// キーボードショートカットによる操作。
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum Shortcut {
+    /// パスワードにセーブする。
+    Submit,
+    /// 項目群を移る。
+    MoveSection(isize),
+    /// 項目群内の n 番目(0 始まり)を選ぶ。
+    Select(usize),
+    /// 項目群内の選択位置を移る。
+    MoveItem(isize),
+    /// 選択中の項目の値を増減する。
+    CycleValue(isize),
+}
+
+/// 修飾キーの状態。
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub(crate) struct Modifiers {
+    pub(crate) ctrl: bool,
+    pub(crate) alt: bool,
+    pub(crate) meta: bool,
+}
+
+/// キー `key` (`KeyboardEvent.key` の値)に対応するショートカットを返す。
+///
+/// `editing` は文字入力を受け付ける要素にフォーカスがあるかどうか。
+/// `in_savedata` はセーブデータ編集フォーム内にフォーカスがあるかどうか。
+pub(crate) fn shortcut_for_key(
+    key: &str,
+    modifiers: Modifiers,
+    editing: bool,
+    in_savedata: bool,
+) -> Option<Shortcut> {
+    if modifiers.ctrl && !modifiers.alt && !modifiers.meta && key == "Enter" {
+        // 他の入力欄(検索クエリや一括入力など)での Ctrl+Enter は奪わない。
+        return in_savedata.then_some(Shortcut::Submit);
+    }
+    if editing || modifiers.ctrl || modifiers.alt || modifiers.meta {
+        return None;
+    }
+
+    let shortcut = match key {
+        "[" => Shortcut::MoveSection(-1),
+        "]" => Shortcut::MoveSection(1),
+        "ArrowUp" => Shortcut::MoveItem(-1),
+        "ArrowDown" => Shortcut::MoveItem(1),
+        "ArrowLeft" => Shortcut::CycleValue(-1),
+        "ArrowRight" => Shortcut::CycleValue(1),
+        "0" => Shortcut::Select(9),
+        _ => {
+            let digit = key.parse::<usize>().ok().filter(|d| (1..=9).contains(d))?;
+            Shortcut::Select(digit - 1)
+        }
+    };
+
+    Some(shortcut)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shortcut_for_key() {
+        let none = Modifiers::default();
+        let ctrl = Modifiers {
+            ctrl: true,
+            ..Default::default()
+        };
+        let alt = Modifiers {
+            alt: true,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            shortcut_for_key("[", none, false, true),
+            Some(Shortcut::MoveSection(-1))
+        );
+        assert_eq!(
+            shortcut_for_key("]", none, false, false),
+            Some(Shortcut::MoveSection(1))
+        );
+        assert_eq!(
+            shortcut_for_key("ArrowUp", none, false, true),
+            Some(Shortcut::MoveItem(-1))
+        );
+        assert_eq!(
+            shortcut_for_key("ArrowRight", none, false, true),
+            Some(Shortcut::CycleValue(1))
+        );
+        assert_eq!(
+            shortcut_for_key("1", none, false, true),
+            Some(Shortcut::Select(0))
+        );
+        assert_eq!(
+            shortcut_for_key("9", none, false, true),
+            Some(Shortcut::Select(8))
+        );
+        assert_eq!(
+            shortcut_for_key("0", none, false, true),
+            Some(Shortcut::Select(9))
+        );
+        assert_eq!(shortcut_for_key("a", none, false, true), None);
+        assert_eq!(shortcut_for_key("10", none, false, true), None);
+
+        // 入力中や修飾キー付きでは無効。
+        assert_eq!(shortcut_for_key("1", none, true, true), None);
+        assert_eq!(shortcut_for_key("1", ctrl, false, true), None);
+        assert_eq!(shortcut_for_key("[", alt, false, true), None);
+
+        // Ctrl+Enter はセーブデータ編集フォーム内でのみ有効。入力中でも有効。
+        assert_eq!(
+            shortcut_for_key("Enter", ctrl, true, true),
+            Some(Shortcut::Submit)
+        );
+        assert_eq!(shortcut_for_key("Enter", ctrl, true, false), None);
+        assert_eq!(shortcut_for_key("Enter", ctrl, false, false), None);
+        assert_eq!(shortcut_for_key("Enter", none, false, true), None);
+        assert_eq!(
+            shortcut_for_key(
+                "Enter",
+                Modifiers {
+                    ctrl: true,
+                    meta: true,
+                    ..Default::default()
+                },
+                false,
+                true
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn test_savedata_section_step() {
+        let first = SavedataSection::Flags(FlagGroup::Spells);
+        let last = SavedataSection::Equipment;
+
+        // 未選択なら先頭または末尾。
+        assert_eq!(SavedataSection::step(None, 1), first);
+        assert_eq!(SavedataSection::step(None, -1), last);
+
+        assert_eq!(
+            SavedataSection::step(Some(first), 1),
+            SavedataSection::Flags(FlagGroup::Events)
+        );
+        assert_eq!(
+            SavedataSection::step(Some(SavedataSection::Flags(FlagGroup::Bookmarks)), 1),
+            last
+        );
+
+        // 端では回り込む。
+        assert_eq!(SavedataSection::step(Some(last), 1), first);
+        assert_eq!(SavedataSection::step(Some(first), -1), last);
+
+        // 全項目群を一周すると元に戻る。
+        let len = SavedataSection::all().count() as isize;
+        for section in SavedataSection::all() {
+            assert_eq!(SavedataSection::step(Some(section), len), section);
+            assert_eq!(SavedataSection::step(Some(section), -len), section);
+        }
+    }
+}
@@ -0,0 +1,260 @@
+//! 入力の手数が最小のパスワードの探索(TAS 用)。
+//!
+//! 手数はパスワード入力画面でのカーソル移動回数と、各文字の決定(A ボタン)および最後の確定(START ボタン)
+//! の押下回数の和とする。これは `movie::password_inputs()` の長さに等しい。
+
+use momoden_password::*;
+
+use crate::equivalent::*;
+use crate::keyboard::*;
+use crate::vanity::{free_field_masks, FreeField};
+
+const COST_INF: u16 = u16::MAX;
+
+/// 文字間のカーソル移動の手数表。
+#[derive(Debug)]
+struct CostTable {
+    /// 初期位置からの手数。
+    start: [u16; 64],
+    /// 文字間の手数。
+    between: [[u16; 64]; 64],
+}
+
+impl CostTable {
+    fn new(cursor: CursorModel) -> Self {
+        let pos = |inner: usize| keyboard_position(PasswordChar::from_inner(inner as u8).unwrap());
+        let cost = |from, to| cursor.path(from, to).len() as u16 + 1;
+
+        let start = std::array::from_fn(|to| cost((0, 0), pos(to)));
+        let between =
+            std::array::from_fn(|from| std::array::from_fn(|to| cost(pos(from), pos(to))));
+
+        Self { start, between }
+    }
+
+    /// パスワード全体の手数(最後の START ボタンを含む)を返す。
+    fn password_cost(&self, password: &Password) -> usize {
+        let chars: Vec<usize> = password
+            .iter()
+            .map(|pc| usize::from(pc.to_inner()))
+            .collect();
+        let moves: usize = chars
+            .windows(2)
+            .map(|w| usize::from(self.between[w[0]][w[1]]))
+            .sum();
+        usize::from(self.start[chars[0]]) + moves + 1
+    }
+}
+
+/// 入力の手数が最小のパスワードの探索。DP をデータ部 1 バイトずつ進められる。
+///
+/// 探索には時間がかかるので、UI からは `step()` を少しずつ呼んで進捗を表示する。
+#[derive(Debug)]
+pub(crate) struct ShortestSearch {
+    table: CostTable,
+    /// データ部の各バイトに対する制約。変えてもよいフィールドのビットは制約から外してある。
+    constraints: Vec<ByteConstraint>,
+    /// 終端してよいデータ部の長さの最小値。
+    data_len_min: usize,
+    /// cost[s]: 状態 s に至る最小手数。
+    cost: Vec<u16>,
+    /// choices[i][s]: i 番目のデータバイトを決めて状態 s に至ったときのバイト値。
+    choices: Vec<Vec<u8>>,
+    best: Option<(Password, usize)>,
+}
+
+impl ShortestSearch {
+    /// セーブデータ `savedata` から探索を始める。`free` に含まれるフィールドは任意の値になってよい。
+    ///
+    /// エンコードとデコードで元に戻らないセーブデータには `None` を返す。
+    pub(crate) fn new(
+        savedata: &Savedata,
+        free: &[FreeField],
+        cursor: CursorModel,
+    ) -> Option<Self> {
+        // データ部の各バイトに対する制約は長さによらない(長さによって変わるのは存在しうるかどうかのみ)。
+        // そこで最長の場合について 1 度だけ DP を行い、存在しうる各長さで終端した場合を全て考える。
+        let constraints = data_constraints(savedata, Password::MAX_LEN)?;
+        let constraints: Vec<ByteConstraint> = constraints
+            .iter()
+            .zip(free_field_masks(free, constraints.len()))
+            .map(|(c, free_mask)| ByteConstraint {
+                mask: c.mask & !free_mask,
+                value: c.value & !free_mask,
+            })
+            .collect();
+
+        // 切り捨てられたデータ部は全ビット 1 として読まれるので、それが制約を満たす長さでのみ終端できる。
+        let data_len_min = (0..=constraints.len())
+            .find(|&len| {
+                constraints[len..]
+                    .iter()
+                    .all(|c| c.accepts(SerializedByte::MAX.get()))
+            })
+            .unwrap();
+
+        let table = CostTable::new(cursor);
+        let mut this = Self {
+            table,
+            constraints,
+            data_len_min: data_len_min.max(1),
+            cost: vec![COST_INF; STATE_COUNT],
+            choices: Vec::with_capacity(Password::MAX_LEN - 2),
+            best: None,
+        };
+
+        // データ部のない長さ 2 以下のパスワード。
+        if data_len_min == 0 {
+            let max = SerializedByte::MAX;
+            let shorts: Vec<Password> = [&[max][..], &[max, max][..]]
+                .into_iter()
+                .map(|buf| SerializedBytes::new(buf).unwrap())
+                .filter(|bytes| bytes.to_savedata().is_some())
+                .map(|bytes| bytes.to_password())
+                .collect();
+            for password in shorts {
+                this.update_best(password);
+            }
+        }
+
+        // 先頭 2 バイト(チェックサム)を全通り試す。
+        for sum_add in 0..64 {
+            for sum_xor in 0..64 {
+                let c0 = encode_char(0, sum_add, FIRST_PREV);
+                let c1 = encode_char(1, sum_xor, c0);
+                let c = this.table.start[usize::from(c0)]
+                    + this.table.between[usize::from(c0)][usize::from(c1)];
+                let s = state(c1, sum_add, sum_xor);
+                this.cost[s] = this.cost[s].min(c);
+            }
+        }
+
+        Some(this)
+    }
+
+    /// DP を 1 バイト分進める。探索が終わったら真を返す。
+    pub(crate) fn step(&mut self) -> bool {
+        let i = self.choices.len();
+        let Some(&constraint) = self.constraints.get(i) else {
+            return true;
+        };
+
+        let mut next = vec![COST_INF; STATE_COUNT];
+        let mut choice = vec![0; STATE_COUNT];
+        let candidates: Vec<u8> = constraint.candidates().collect();
+
+        for (s, &c) in self.cost.iter().enumerate() {
+            if c == COST_INF {
+                continue;
+            }
+            let (prev, sum_add, sum_xor) = unpack_state(s);
+            for &b in &candidates {
+                let pc = encode_char(i + 2, b, prev);
+                let ns = state(pc, sum_add.wrapping_sub(b) & 0x3F, sum_xor ^ b);
+                let nc = c + self.table.between[usize::from(prev)][usize::from(pc)];
+                if nc < next[ns] {
+                    next[ns] = nc;
+                    choice[ns] = b;
+                }
+            }
+        }
+
+        self.cost = next;
+        self.choices.push(choice);
+
+        if i + 1 >= self.data_len_min {
+            if let Some(password) = restore(&self.cost, COST_INF, &self.choices) {
+                self.update_best(password);
+            }
+        }
+
+        self.choices.len() == self.constraints.len()
+    }
+
+    /// 進捗 (処理済みのバイト数, 全バイト数) を返す。
+    pub(crate) fn progress(&self) -> (usize, usize) {
+        (self.choices.len(), self.constraints.len())
+    }
+
+    /// これまでに見つかった最良のパスワードとその手数を返す。
+    pub(crate) fn result(&self) -> Option<&(Password, usize)> {
+        self.best.as_ref()
+    }
+
+    fn update_best(&mut self, password: Password) {
+        let cost = self.table.password_cost(&password);
+        if self
+            .best
+            .as_ref()
+            .is_none_or(|(_, best_cost)| cost < *best_cost)
+        {
+            self.best = Some((password, cost));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::movie::password_inputs;
+    use crate::preset::{preset_savedata, Preset};
+
+    fn decode(password: &Password) -> Option<Savedata> {
+        SerializedBytes::from_password(password).to_savedata()
+    }
+
+    /// 探索を最後まで進め、進捗の表示が正しいことも確かめる。
+    fn shortest_input_password(
+        savedata: &Savedata,
+        free: &[FreeField],
+        cursor: CursorModel,
+    ) -> (Password, usize) {
+        let mut search = ShortestSearch::new(savedata, free, cursor).unwrap();
+        assert_eq!(search.progress(), (0, Password::MAX_LEN - 2));
+
+        let mut steps = 1;
+        while !search.step() {
+            steps += 1;
+        }
+        assert_eq!(steps, Password::MAX_LEN - 2);
+        assert_eq!(search.progress(), (steps, steps));
+        assert!(search.step());
+
+        search.result().cloned().unwrap()
+    }
+
+    #[test]
+    fn test_shortest_input_password() {
+        let cursor = CursorModel::default();
+        for preset in [Preset::Start, Preset::Endgame] {
+            let savedata = preset_savedata(preset);
+            let (password, cost) = shortest_input_password(&savedata, &[], cursor);
+
+            assert_eq!(decode(&password), Some(savedata.clone()));
+            assert_eq!(cost, password_inputs(&password, cursor).len());
+
+            // 通常のエンコード結果より手数が多くなることはない。
+            let encoded = SerializedBytes::from_savedata(&savedata).to_password();
+            assert!(cost <= password_inputs(&encoded, cursor).len());
+        }
+    }
+
+    #[test]
+    fn test_shortest_input_password_free() {
+        let cursor = CursorModel::default();
+        let savedata = preset_savedata(Preset::Start);
+        let (_, cost_fixed) = shortest_input_password(&savedata, &[], cursor);
+
+        let free = [FreeField::Age, FreeField::Deposit];
+        let (password, cost) = shortest_input_password(&savedata, &free, cursor);
+        assert!(cost <= cost_fixed);
+        assert_eq!(cost, password_inputs(&password, cursor).len());
+
+        // 任意にしたフィールド以外は元のセーブデータと一致する。
+        let mut decoded = decode(&password).unwrap();
+        decoded.age = savedata.age;
+        decoded.deposit = savedata.deposit;
+        assert_eq!(decoded, savedata);
+    }
+}
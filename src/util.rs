@@ -1,5 +1,21 @@
+use std::fmt::Write as _;
+
 use momoden_password::BoundedU8;
 
+/// バイト列 `data` をパーセントエンコードした data URL を返す。
+pub(crate) fn data_url(mime: &str, data: &[u8]) -> String {
+    let mut url = format!("data:{mime},");
+    for &b in data {
+        if b.is_ascii_alphanumeric() || b"-_.~".contains(&b) {
+            url.push(char::from(b));
+        } else {
+            write!(url, "%{b:02X}").unwrap();
+        }
+    }
+
+    url
+}
+
 pub(crate) trait BoolExt {
     fn toggle(&mut self);
 }
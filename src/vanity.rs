@@ -0,0 +1,337 @@
+//! 指定した単語を含むパスワード(語呂合わせパスワード)の探索。
+//!
+//! 単語の位置の文字を固定し、利用者が選んだ「変えてもよいフィールド」のビットと、意味を持たないビットを
+//! 動かしてチェックサムを合わせる。変えるビット数が最小のものを探す。
+
+use momoden_password::*;
+
+use crate::equivalent::*;
+use crate::layout::*;
+
+/// 語呂合わせのために変えてもよいフィールド。
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum FreeField {
+    AgeTimerHi,
+    Age,
+    PurseLo,
+    XpLo,
+    Deposit,
+}
+
+impl FreeField {
+    pub(crate) fn all() -> impl Iterator<Item = Self> {
+        [
+            Self::AgeTimerHi,
+            Self::Age,
+            Self::PurseLo,
+            Self::XpLo,
+            Self::Deposit,
+        ]
+        .into_iter()
+    }
+
+    /// データ部におけるビット範囲。2 バイトの値は下位バイトのみ。
+    fn bit_range(self) -> std::ops::Range<usize> {
+        let field = match self {
+            Self::AgeTimerHi => SavedataField::AgeTimerHi,
+            Self::Age => SavedataField::Age,
+            Self::PurseLo => SavedataField::Purse,
+            Self::XpLo => SavedataField::Xp,
+            Self::Deposit => SavedataField::Deposit,
+        };
+        field_bit_ranges(field).last().unwrap().clone()
+    }
+
+    /// セーブデータにおけるこのフィールドの値。
+    pub(crate) fn value(self, savedata: &Savedata) -> u8 {
+        match self {
+            Self::AgeTimerHi => savedata.age_timer_hi,
+            Self::Age => savedata.age,
+            Self::PurseLo => savedata.purse as u8,
+            Self::XpLo => savedata.xp as u8,
+            Self::Deposit => savedata.deposit.get(),
+        }
+    }
+}
+
+pub(crate) fn free_field_name(field: FreeField) -> &'static str {
+    match field {
+        FreeField::AgeTimerHi => "タイマー",
+        FreeField::Age => "年齢",
+        FreeField::PurseLo => "所持金 (下位 8bit)",
+        FreeField::XpLo => "経験値 (下位 8bit)",
+        FreeField::Deposit => "預金",
+    }
+}
+
+/// フィールド `fields` のうち `old` と `new` で値が異なるものを、変更前後の値とともに返す。
+pub(crate) fn free_field_diff(
+    fields: &[FreeField],
+    old: &Savedata,
+    new: &Savedata,
+) -> Vec<(FreeField, u8, u8)> {
+    fields
+        .iter()
+        .map(|&field| (field, field.value(old), field.value(new)))
+        .filter(|&(_, old, new)| old != new)
+        .collect()
+}
+
+/// 語呂合わせパスワードの探索結果。
+#[derive(Debug)]
+pub(crate) struct VanityPassword {
+    pub(crate) password: Password,
+    pub(crate) savedata: Savedata,
+    /// 値が変わったフィールドと、その変更前後の値。
+    pub(crate) diff: Vec<(FreeField, u8, u8)>,
+}
+
+/// 語呂合わせパスワードの探索。DP をデータ部 1 バイトずつ進められる。
+///
+/// 探索には時間がかかるので、UI からは `step()` を少しずつ呼んで進捗を表示する。
+#[derive(Debug)]
+pub(crate) struct VanitySearch {
+    savedata: Savedata,
+    word: Vec<PasswordChar>,
+    /// 単語を置く位置(0 始まり)。
+    pos: usize,
+    free: Vec<FreeField>,
+    len_min: usize,
+    constraints: Vec<ByteConstraint>,
+    /// 各バイトのうち、変えてもよいフィールドに属するビットのマスク。
+    soft_masks: Vec<u8>,
+    /// cost[s]: 状態 s に至るまでに変えたビット数の最小値。
+    cost: Vec<u8>,
+    /// choices[i][s]: i 番目のデータバイトを決めて状態 s に至ったときのバイト値。
+    choices: Vec<Vec<u8>>,
+    best: Option<(u8, Password)>,
+    done: bool,
+}
+
+impl VanitySearch {
+    const COST_INF: u8 = u8::MAX;
+
+    /// セーブデータ `savedata` を元に、`pos` 文字目(0 始まり)から単語 `word` を含むパスワードの探索を始める。
+    ///
+    /// `free` に含まれるフィールドのビットを変えてもよい。
+    /// 単語が空か長すぎる場合や、セーブデータがパスワードで表せない場合は `None` を返す。
+    pub(crate) fn new(
+        savedata: &Savedata,
+        word: &[PasswordChar],
+        pos: usize,
+        free: &[FreeField],
+    ) -> Option<Self> {
+        if word.is_empty() || pos + word.len() > Password::MAX_LEN {
+            return None;
+        }
+
+        let len_min =
+            (3..=Password::MAX_LEN).find(|&len| data_constraints(savedata, len).is_some())?;
+        let constraints = data_constraints(savedata, Password::MAX_LEN)?;
+        let soft_masks = free_field_masks(free, constraints.len());
+
+        let mut this = Self {
+            savedata: savedata.clone(),
+            word: word.to_vec(),
+            pos,
+            free: free.to_vec(),
+            len_min,
+            constraints,
+            soft_masks,
+            cost: vec![Self::COST_INF; STATE_COUNT],
+            choices: Vec::with_capacity(Password::MAX_LEN - 2),
+            best: None,
+            done: false,
+        };
+
+        // 先頭 2 バイト(チェックサム)を全通り試す。
+        for sum_add in 0..64 {
+            for sum_xor in 0..64 {
+                let c0 = encode_char(0, sum_add, FIRST_PREV);
+                let c1 = encode_char(1, sum_xor, c0);
+                if this.word_char(0).is_some_and(|w| w != c0)
+                    || this.word_char(1).is_some_and(|w| w != c1)
+                {
+                    continue;
+                }
+                this.cost[state(c1, sum_add, sum_xor)] = 0;
+            }
+        }
+
+        Some(this)
+    }
+
+    /// i 文字目(0 始まり)が単語の範囲内なら、その文字(内部値)を返す。
+    fn word_char(&self, i: usize) -> Option<u8> {
+        i.checked_sub(self.pos)
+            .and_then(|j| self.word.get(j))
+            .map(|pc| pc.to_inner())
+    }
+
+    /// DP を 1 バイト分進める。探索が終わったら真を返す。
+    pub(crate) fn step(&mut self) -> bool {
+        let i = self.choices.len();
+        if self.done || i == self.constraints.len() {
+            self.done = true;
+            return true;
+        }
+
+        let constraint = self.constraints[i];
+        let soft = self.soft_masks[i];
+        let hard = ByteConstraint {
+            mask: constraint.mask & !soft,
+            value: constraint.value & !soft,
+        };
+        let candidates: Vec<u8> = hard.candidates().collect();
+        let w = self.word_char(i + 2);
+
+        let mut next = vec![Self::COST_INF; STATE_COUNT];
+        let mut choice = vec![0; STATE_COUNT];
+        for (s, &c) in self.cost.iter().enumerate() {
+            if c == Self::COST_INF {
+                continue;
+            }
+            let (prev, sum_add, sum_xor) = unpack_state(s);
+            for &b in &candidates {
+                let pc = encode_char(i + 2, b, prev);
+                if w.is_some_and(|w| w != pc) {
+                    continue;
+                }
+                let ns = state(pc, sum_add.wrapping_sub(b) & 0x3F, sum_xor ^ b);
+                let nc = c + ((b ^ constraint.value) & soft).count_ones() as u8;
+                if nc < next[ns] {
+                    next[ns] = nc;
+                    choice[ns] = b;
+                }
+            }
+        }
+        self.cost = next;
+        self.choices.push(choice);
+
+        // 長さ i + 3 で終端する場合。長さの昇順に見るので、コストが真に小さいときのみ更新する。
+        let len = i + 3;
+        if len >= self.len_min && len >= self.pos + self.word.len() {
+            let end_cost = (0..64).map(|pc| self.cost[state(pc, 0, 0)]).min().unwrap();
+            if end_cost != Self::COST_INF && self.best.as_ref().is_none_or(|&(c, _)| end_cost < c) {
+                let password = restore(&self.cost, Self::COST_INF, &self.choices).unwrap();
+                self.best = Some((end_cost, password));
+                // セーブデータを変えずに済んだなら、これより長いものを探す必要はない。
+                self.done = end_cost == 0;
+            }
+        }
+
+        self.done |= self.choices.len() == self.constraints.len();
+        self.done
+    }
+
+    /// 進捗 (処理済みのバイト数, 全バイト数) を返す。
+    pub(crate) fn progress(&self) -> (usize, usize) {
+        (self.choices.len(), self.constraints.len())
+    }
+
+    /// 変えるビット数が最小のもののうち、最も短いものを返す。
+    pub(crate) fn result(&self) -> Option<VanityPassword> {
+        let (_, password) = self.best.clone()?;
+        let savedata = SerializedBytes::from_password(&password).to_savedata()?;
+        let diff = free_field_diff(&self.free, &self.savedata, &savedata);
+
+        Some(VanityPassword {
+            password,
+            savedata,
+            diff,
+        })
+    }
+}
+
+/// 長さ `data_len` のデータ部の各バイトについて、フィールド `fields` に属するビットのマスクを返す。
+pub(crate) fn free_field_masks(fields: &[FreeField], data_len: usize) -> Vec<u8> {
+    let mut masks = vec![0; data_len];
+    for bit in fields.iter().flat_map(|field| field.bit_range()) {
+        if let Some(mask) = masks.get_mut(bit / 6) {
+            *mask |= 0x20 >> (bit % 6);
+        }
+    }
+    masks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::preset::{preset_savedata, Preset};
+
+    fn set_value(field: FreeField, savedata: &mut Savedata, value: u8) {
+        match field {
+            FreeField::AgeTimerHi => savedata.age_timer_hi = value,
+            FreeField::Age => savedata.age = value,
+            FreeField::PurseLo => savedata.purse = savedata.purse & 0xFF00 | u16::from(value),
+            FreeField::XpLo => savedata.xp = savedata.xp & 0xFF00 | u16::from(value),
+            FreeField::Deposit => savedata.deposit = Deposit::new(value).unwrap(),
+        }
+    }
+
+    fn search(
+        savedata: &Savedata,
+        word: &[PasswordChar],
+        pos: usize,
+        free: &[FreeField],
+    ) -> Option<VanityPassword> {
+        let mut search = VanitySearch::new(savedata, word, pos, free).unwrap();
+        while !search.step() {}
+        search.result()
+    }
+
+    fn word(s: &str) -> Vec<PasswordChar> {
+        s.chars()
+            .map(|c| PasswordChar::from_char(c).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_vanity_password() {
+        let free = [FreeField::AgeTimerHi, FreeField::Age, FreeField::Deposit];
+        let cases = [
+            (Preset::Start, word("もも"), 4, &free[..]),
+            (Preset::Hanasaka, word("も"), 3, &free[..]),
+            // デシリアライズ時に読まれない位置なら、フィールドを変えずに済む。
+            (Preset::Kintaro, word("ももたろう"), 30, &[][..]),
+        ];
+
+        for (preset, word, pos, free) in cases {
+            let savedata = preset_savedata(preset);
+            let vanity = search(&savedata, &word, pos, free).unwrap();
+            let password = &vanity.password;
+
+            assert!(password.is_valid());
+            assert_eq!(password[pos..pos + word.len()], word[..]);
+            assert_eq!(
+                SerializedBytes::from_password(password)
+                    .to_savedata()
+                    .as_ref(),
+                Some(&vanity.savedata)
+            );
+
+            // 変わるのは変えてもよいフィールドのみで、その変更内容が diff に入っている。
+            let mut restored = vanity.savedata.clone();
+            for &(field, old, new) in &vanity.diff {
+                assert!(free.contains(&field));
+                assert_eq!(field.value(&savedata), old);
+                assert_eq!(field.value(&vanity.savedata), new);
+                set_value(field, &mut restored, old);
+            }
+            assert_eq!(restored, savedata);
+        }
+    }
+
+    #[test]
+    fn test_vanity_password_invalid() {
+        let savedata = preset_savedata(Preset::Start);
+        let long = [PasswordChar::A; 4];
+        assert!(VanitySearch::new(&savedata, &[], 0, &[]).is_none());
+        assert!(VanitySearch::new(&savedata, &long, Password::MAX_LEN - 3, &[]).is_none());
+
+        // チェックサムの加算値と XOR 値は最下位ビットが常に一致するので、先頭 2 文字には取れない組がある。
+        let free: Vec<FreeField> = FreeField::all().collect();
+        assert!(search(&savedata, &word("もも"), 0, &free).is_none());
+    }
+}
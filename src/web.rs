@@ -3,17 +3,51 @@ use seed::{prelude::*, *};
 
 use momoden_password::*;
 
+use crate::bulk::*;
+use crate::csv::{passwords_csv, savedata_header, savedata_record, to_data_url};
+use crate::equivalent::{char_alternatives, equivalent_password_counts, equivalent_passwords};
+use crate::flags::*;
 use crate::game::*;
 use crate::generate::generate_passwords;
-use crate::query::{Pattern, PatternChar, Query, QueryParseError};
-use crate::util::{BoolExt as _, NewClampExt as _};
+use crate::inventory::*;
+use crate::keyboard::*;
+use crate::layout::*;
+use crate::memory::*;
+use crate::movie::*;
+use crate::ocr::*;
+use crate::plausibility::*;
+use crate::preset::*;
+use crate::query::{normalize_query_input, Pattern, PatternChar, Query, QueryParseError};
+use crate::ram_map::*;
+use crate::repair::*;
+use crate::screen::*;
+use crate::shortcut::*;
+use crate::shortest::ShortestSearch;
+use crate::util::{data_url, BoolExt as _, NewClampExt as _};
+use crate::vanity::*;
 
 const PASSWORD_COUNT_MAX: usize = 100;
 
+/// 文字テンプレートを保存する localStorage のキー。
+const STORAGE_KEY_GLYPH_TEMPLATES: &str = "glyph-templates";
+
+/// 学習した RAM 上の項目の位置を保存する localStorage のキー。
+const STORAGE_KEY_RAM_MAP: &str = "ram-map";
+
+/// アイテム選択の絞り込み入力欄。開いたときにフォーカスするため、ここで定義する。
+const ID_INPUT_ITEM_PICKER: &str = "input-item-picker";
+
 const CLASS_WARN: &str = "warn";
 const CLASS_HAS_TOOLTIP: &str = "has-tooltip";
 const CLASS_EQUIPMENT_LABEL: &str = "equipment-label";
 const CLASS_EQUIPMENT_INPUT: &str = "equipment-input";
+const CLASS_FLAG_OP: &str = "flag-op";
+const CLASS_SHORTCUT_SELECTED: &str = "shortcut-selected";
+const CLASS_INVENTORY_ITEM: &str = "inventory-item";
+const CLASS_INVENTORY_DRAGGING: &str = "inventory-dragging";
+const CLASS_INVENTORY_OP: &str = "inventory-op";
+const CLASS_ITEM_PICKER: &str = "item-picker";
+const CLASS_ITEM_PICKER_BUTTON: &str = "item-picker-button";
 
 #[wasm_bindgen(start)]
 pub fn start() {
@@ -23,28 +57,172 @@ pub fn start() {
 #[derive(Debug)]
 struct Model {
     query: String,
+    /// 文字パレットで編集する位置(文字単位)。
+    query_cursor: usize,
+    romaji: bool,
     normalize: bool,
     savedata: Savedata,
     passwords: Vec<Password>,
+    passwords_sort: Option<(PasswordsSortKey, bool)>,
+    passwords_filter: String,
+    bulk_input: String,
+    bulk_rows: Vec<BulkDecodeRow>,
+    glyph_templates: GlyphTemplates,
+    /// スクリーンショット読み取り・テンプレート登録の結果メッセージ。
+    ocr_message: Option<String>,
+    /// エミュレータのメモリダンプ。
+    memory_dump: MemoryDump,
+    /// RAM 内のパスワード領域のオフセット。
+    memory_offset: usize,
+    /// RAM 内のパスワード領域の文字数。
+    memory_len: usize,
+    memory_message: Option<String>,
+    /// 学習した RAM 上のセーブデータの各項目の位置。
+    ram_map: RamMap,
+    movie_config: MovieConfig,
+    /// 入力ムービーに埋め込む ROM のチェックサムとセーブステート。
+    movie_source: MovieSource,
+    movie_message: Option<String>,
+    vanity_word: String,
+    /// 単語を置く位置(1 始まり)。
+    vanity_pos: usize,
+    vanity_free: Vec<FreeField>,
+    /// 実行中の語呂合わせパスワードの探索。
+    vanity_search: Option<VanitySearch>,
+    vanity_message: Option<String>,
+    /// 最短入力の探索で任意の値にしてよいフィールド。
+    shortest_free: Vec<FreeField>,
+    /// 実行中の最短入力の探索。
+    shortest_search: Option<ShortestSearch>,
+    shortest_message: Option<String>,
+    /// チェックサム修復で変えてよい文字位置(1 始まり、空白かカンマ区切り)。
+    repair_positions: String,
+    repair_fields: Vec<FreeField>,
+    repair_message: Option<String>,
+    /// キーボードショートカットで選択中の項目群。
+    shortcut_section: Option<SavedataSection>,
+    /// キーボードショートカットで選択中の装備枠。
+    shortcut_slot: EquipmentSlot,
+    /// ドラッグ中のインベントリのアイテムの位置。
+    inventory_drag: Option<usize>,
+    /// アイテム選択を開いているインベントリの欄。
+    item_picker: Option<usize>,
+    item_picker_filter: String,
+    /// クエリから計算する重い表示内容。`refresh_query_cache()` で更新する。
+    query_cache: QueryCache,
+}
+
+/// クエリが有効なパスワードのときに、そのパスワードから計算しておく表示内容。
+#[derive(Debug, Default)]
+struct QueryCache {
+    /// 計算の元にしたパスワード。
+    password: Option<Password>,
+    /// 各文字の入れ替え候補 (`char_alternatives()` の結果)。
+    alternatives: Option<Vec<Vec<(PasswordChar, Password)>>>,
+    /// ゲーム画面の体裁で描いた SVG の data URL。
+    screen_svg_url: String,
 }
 
 impl Default for Model {
     fn default() -> Self {
         Self {
             query: Default::default(),
+            query_cursor: 0,
+            romaji: false,
             normalize: true,
             savedata: Default::default(),
             passwords: Default::default(),
+            passwords_sort: None,
+            passwords_filter: Default::default(),
+            bulk_input: Default::default(),
+            bulk_rows: Default::default(),
+            glyph_templates: Default::default(),
+            ocr_message: None,
+            memory_dump: Default::default(),
+            memory_offset: 0,
+            memory_len: Password::MAX_LEN,
+            memory_message: None,
+            ram_map: Default::default(),
+            movie_config: Default::default(),
+            movie_source: Default::default(),
+            movie_message: None,
+            vanity_word: Default::default(),
+            vanity_pos: 3,
+            vanity_free: vec![FreeField::AgeTimerHi],
+            vanity_search: None,
+            vanity_message: None,
+            shortest_free: vec![],
+            shortest_search: None,
+            shortest_message: None,
+            repair_positions: Default::default(),
+            repair_fields: vec![FreeField::AgeTimerHi],
+            repair_message: None,
+            shortcut_section: None,
+            shortcut_slot: EquipmentSlot::Helm,
+            inventory_drag: None,
+            item_picker: None,
+            item_picker_filter: Default::default(),
+            query_cache: Default::default(),
+        }
+    }
+}
+
+impl Model {
+    /// カタカナやローマ字を変換した後のクエリ文字列を返す。
+    fn query_normalized(&self) -> String {
+        normalize_query_input(&self.query, self.romaji)
+    }
+
+    /// クエリ文字列を置き換え、カーソルを末尾に移す。
+    fn set_query(&mut self, query: String) {
+        self.query_cursor = query.chars().count();
+        self.query = query;
+    }
+
+    /// クエリ(または文字テンプレート)が変わっていれば、それに依存する重い計算をやり直す。
+    fn refresh_query_cache(&mut self) {
+        let password = match Query::parse(&self.query_normalized()) {
+            Ok(Query::Password(password)) => Some(password),
+            _ => None,
+        };
+        if password == self.query_cache.password {
+            return;
         }
+
+        self.query_cache = QueryCache {
+            alternatives: password.as_ref().and_then(char_alternatives),
+            screen_svg_url: password.as_ref().map_or_else(String::new, |password| {
+                svg_data_url(&password_svg(password, &self.glyph_templates))
+            }),
+            password,
+        };
+    }
+
+    /// クエリ文字列の長さを超えないように補正したカーソル位置を返す。
+    fn query_cursor(&self) -> usize {
+        self.query_cursor.min(self.query.chars().count())
     }
 }
 
 #[derive(Debug)]
 enum Msg {
+    ToggleRomaji,
     ToggleNormalize,
     QueryUpdate(String),
     QuerySubmit,
+    ExportPasswordPng(Password),
+    ExportMovieFm2(Password),
+    ExportMovieBk2(Password),
+    KeyboardInsert(char),
+    KeyboardBackspace,
+    KeyboardMoveCursor(isize),
+    KeyboardSetCursor(usize),
     PickPassword(usize),
+    /// パスワードの `idx` 文字目(0 始まり)の入れ替え候補をパターン検索結果の欄に表示する。
+    ShowAlternatives(usize),
+    PasswordsSort(PasswordsSortKey),
+    PasswordsFilterUpdate(String),
+    PasswordsDownloadCsv,
     SavedataUpdateXp(u16),
     SavedataUpdatePurse(u16),
     SavedataUpdateDeposit(Deposit),
@@ -65,19 +243,137 @@ enum Msg {
     SavedataUpdateAccessory2(Accessory2Index),
     SavedataUpdateAccessory3(Accessory3Index),
     SavedataUpdateInventory(usize, Option<ItemId>),
+    SavedataInsertInventoryItem(usize),
+    SavedataDuplicateInventoryItem(usize),
+    SavedataClearInventory,
+    InventoryDragStart(usize),
+    InventoryDrop(usize),
+    InventoryDragEnd,
+    ItemPickerOpen(usize),
+    ItemPickerClose,
+    ItemPickerFilterUpdate(String),
+    /// 開いているアイテム選択でアイテムを選ぶ。
+    ItemPickerSelect(Option<ItemId>),
+    /// 開いているアイテム選択で、絞り込み結果の先頭のアイテムを選ぶ。
+    ItemPickerSelectFirst,
+    SavedataLoadPreset(Preset),
+    SavedataUpdateFlags(FlagGroup, FlagOp),
+    SavedataMaxOut,
+    KeyDown(web_sys::KeyboardEvent),
+    ShortcutSelectSection(SavedataSection),
+    ShortcutSelectSlot(EquipmentSlot),
     SavedataNormalize,
     SavedataToPassword,
+    SavedataToShortestPassword,
+    ShortestSearchStep,
+    ShortestSearchCancel,
+    ToggleShortestFree(FreeField),
+    SavedataEquivalentPasswords,
+    VanityWordUpdate(String),
+    VanityPosUpdate(String),
+    ToggleVanityFree(FreeField),
+    VanitySearch,
+    VanitySearchStep,
+    VanitySearchCancel,
+    RepairPositionsUpdate(String),
+    ToggleRepairField(FreeField),
+    RepairByPositions,
+    RepairByFields,
+    BulkUpdate(String),
+    BulkLoadFile(web_sys::File),
+    BulkSubmit,
+    BulkDownloadCsv,
+    ScreenshotLoad(web_sys::File),
+    ScreenshotRecognize(GrayImage),
+    GlyphTemplatesLoad(web_sys::File),
+    GlyphTemplatesLearn(GrayImage),
+    GlyphTemplatesClear,
+    MemoryHexUpdate(String),
+    MemoryLoadFile(web_sys::File),
+    MemoryDumpLoaded(Vec<u8>),
+    MemoryOffsetUpdate(String),
+    MemoryLenUpdate(String),
+    MemoryFind,
+    MemoryImport,
+    MemoryDownload,
+    RamMapLearn,
+    RamMapRead,
+    RamMapDownload,
+    RamMapClear,
+    ToggleMovieWrap,
+    ToggleMovieSkipGaps,
+    MovieFramesUpdate(String),
+    MovieLoadFile(web_sys::File),
+    MovieFileLoaded(Vec<u8>),
 }
 
-fn init(_url: Url, _orders: &mut impl Orders<Msg>) -> Model {
-    Model::default()
+/// パターン検索結果テーブルのソートキー。
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum PasswordsSortKey {
+    Implausibility,
+    Password,
+    Xp,
+    Purse,
+    Deposit,
+    Age,
+    Weapon,
+    Armor,
 }
 
-fn update(msg: Msg, model: &mut Model, _orders: &mut impl Orders<Msg>) {
+impl PasswordsSortKey {
+    const ALL: [Self; 8] = [
+        Self::Implausibility,
+        Self::Password,
+        Self::Xp,
+        Self::Purse,
+        Self::Deposit,
+        Self::Age,
+        Self::Weapon,
+        Self::Armor,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Implausibility => "不自然さ",
+            Self::Password => "パスワード",
+            Self::Xp => "経験値",
+            Self::Purse => "所持金",
+            Self::Deposit => "預金",
+            Self::Age => "年齢",
+            Self::Weapon => "武器",
+            Self::Armor => "鎧",
+        }
+    }
+}
+
+fn init(_url: Url, orders: &mut impl Orders<Msg>) -> Model {
+    let mut model = Model::default();
+
+    orders.stream(streams::window_event(Ev::KeyDown, |event| {
+        Msg::KeyDown(event.unchecked_into())
+    }));
+
+    if let Some(templates) = storage_get(STORAGE_KEY_GLYPH_TEMPLATES) {
+        model.glyph_templates = templates.parse().unwrap();
+    }
+    if let Some(map) = storage_get(STORAGE_KEY_RAM_MAP) {
+        model.ram_map = map.parse().unwrap();
+    }
+
+    model
+}
+
+fn update(msg: Msg, model: &mut Model, orders: &mut impl Orders<Msg>) {
+    update_model(msg, model, orders);
+    model.refresh_query_cache();
+}
+
+fn update_model(msg: Msg, model: &mut Model, orders: &mut impl Orders<Msg>) {
     match msg {
+        Msg::ToggleRomaji => model.romaji.toggle(),
         Msg::ToggleNormalize => model.normalize.toggle(),
-        Msg::QueryUpdate(query) => model.query = query,
-        Msg::QuerySubmit => match Query::parse(&model.query) {
+        Msg::QueryUpdate(query) => model.set_query(query),
+        Msg::QuerySubmit => match Query::parse(&model.query_normalized()) {
             Ok(Query::Password(password)) => {
                 let Some(savedata) = load_from_password(&password, model.normalize) else {
                     return;
@@ -85,11 +381,67 @@ fn update(msg: Msg, model: &mut Model, _orders: &mut impl Orders<Msg>) {
                 model.savedata = savedata;
             }
             Ok(Query::Pattern(pattern)) => {
-                model.passwords = generate_passwords(&pattern, PASSWORD_COUNT_MAX)
+                // '?' は高々 3 個なので、全候補を列挙してから並べ替えても十分速い。
+                model.passwords = generate_passwords(&pattern, usize::MAX);
+                sort_by_plausibility(&mut model.passwords);
+                model.passwords.truncate(PASSWORD_COUNT_MAX);
             }
             #[allow(clippy::needless_return)]
             Err(_) => return,
         },
+        Msg::ExportMovieFm2(password) => {
+            match to_fm2(&password, model.movie_config, &model.movie_source) {
+                Ok(fm2) => download(
+                    &data_url("text/plain;charset=utf-8", fm2.as_bytes()),
+                    &format!("{}.fm2", password.display_pretty()),
+                ),
+                Err(err) => model.movie_message = Some(format!("FM2: {err}")),
+            }
+        }
+        Msg::ExportMovieBk2(password) => {
+            match to_bk2(&password, model.movie_config, &model.movie_source) {
+                Ok(bk2) => download(
+                    &data_url("application/octet-stream", &bk2),
+                    &format!("{}.bk2", password.display_pretty()),
+                ),
+                Err(err) => model.movie_message = Some(format!("BK2: {err}")),
+            }
+        }
+        Msg::ExportPasswordPng(password) => {
+            if let Some(url) = password_png_data_url(&password, &model.glyph_templates) {
+                download(&url, &format!("{}.png", password.display_pretty()));
+            }
+        }
+        Msg::KeyboardInsert(c) => {
+            let cursor = model.query_cursor();
+            let idx = char_to_byte_index(&model.query, cursor);
+            model.query.insert(idx, c);
+            model.query_cursor = cursor + 1;
+        }
+        Msg::KeyboardBackspace => {
+            let cursor = model.query_cursor();
+            if cursor == 0 {
+                return;
+            }
+            let idx = char_to_byte_index(&model.query, cursor - 1);
+            model.query.remove(idx);
+            model.query_cursor = cursor - 1;
+        }
+        Msg::KeyboardMoveCursor(delta) => {
+            let cursor = model.query_cursor().saturating_add_signed(delta);
+            model.query_cursor = cursor.min(model.query.chars().count());
+        }
+        Msg::KeyboardSetCursor(cursor) => model.query_cursor = cursor,
+        Msg::ShowAlternatives(idx) => {
+            if let Some(alts) = model
+                .query_cache
+                .alternatives
+                .as_ref()
+                .and_then(|alternatives| alternatives.get(idx))
+            {
+                model.passwords = alts.iter().map(|(_, password)| password.clone()).collect();
+            }
+        }
         Msg::PickPassword(idx) => {
             let Some(password) = model.passwords.get(idx) else {
                 return;
@@ -97,19 +449,31 @@ fn update(msg: Msg, model: &mut Model, _orders: &mut impl Orders<Msg>) {
             let Some(savedata) = load_from_password(password, model.normalize) else {
                 return;
             };
-            model.query = password.display_pretty().to_string();
+            model.set_query(password.display_pretty().to_string());
             model.savedata = savedata;
         }
+        Msg::PasswordsSort(key) => {
+            // 同じ列が再度選ばれたら昇順/降順を切り替える。
+            model.passwords_sort = match model.passwords_sort {
+                Some((key_cur, descending)) if key_cur == key => Some((key, !descending)),
+                _ => Some((key, false)),
+            };
+        }
+        Msg::PasswordsFilterUpdate(filter) => model.passwords_filter = filter,
+        Msg::PasswordsDownloadCsv => {
+            let csv = passwords_csv(&model.passwords, model.normalize);
+            download(&to_data_url(&csv), "passwords.csv");
+        }
         Msg::SavedataUpdateXp(xp) => model.savedata.xp = xp,
         Msg::SavedataUpdatePurse(purse) => model.savedata.purse = purse,
         Msg::SavedataUpdateDeposit(deposit) => model.savedata.deposit = deposit,
         Msg::SavedataUpdateAge(age) => model.savedata.age = age,
         Msg::SavedataUpdateAgeTimerHi(age_timer_hi) => model.savedata.age_timer_hi = age_timer_hi,
-        Msg::SavedataToggleSpell(spell) => model.savedata.spells[spell].toggle(),
-        Msg::SavedataToggleEvent(event) => model.savedata.events[event].toggle(),
-        Msg::SavedataToggleTreasure(treasure) => model.savedata.treasures[treasure].toggle(),
-        Msg::SavedataToggleMinion(minion) => model.savedata.minions[minion].toggle(),
-        Msg::SavedataToggleBookmark(bookmark) => model.savedata.bookmarks[bookmark].toggle(),
+        Msg::SavedataToggleSpell(spell) => toggle_flag(&mut model.savedata, spell),
+        Msg::SavedataToggleEvent(event) => toggle_flag(&mut model.savedata, event),
+        Msg::SavedataToggleTreasure(treasure) => toggle_flag(&mut model.savedata, treasure),
+        Msg::SavedataToggleMinion(minion) => toggle_flag(&mut model.savedata, minion),
+        Msg::SavedataToggleBookmark(bookmark) => toggle_flag(&mut model.savedata, bookmark),
         Msg::SavedataUpdateRespawn(respawn) => model.savedata.respawn = respawn,
         Msg::SavedataUpdateHelm(helm) => model.savedata.equipment.helm = helm,
         Msg::SavedataUpdateWeapon(weapon) => model.savedata.equipment.weapon = weapon,
@@ -139,28 +503,592 @@ fn update(msg: Msg, model: &mut Model, _orders: &mut impl Orders<Msg>) {
                 (true, Some(item_id)) => inventory[idx] = item_id,
             }
         }
+        Msg::SavedataInsertInventoryItem(idx) => {
+            insert_item(&mut model.savedata.inventory, idx, ItemId::MIN);
+        }
+        Msg::SavedataDuplicateInventoryItem(idx) => {
+            duplicate_item(&mut model.savedata.inventory, idx);
+        }
+        Msg::SavedataClearInventory => model.savedata.inventory.clear(),
+        Msg::InventoryDragStart(idx) => model.inventory_drag = Some(idx),
+        Msg::InventoryDrop(to) => {
+            if let Some(from) = model.inventory_drag.take() {
+                move_item(&mut model.savedata.inventory, from, to);
+            }
+        }
+        Msg::InventoryDragEnd => model.inventory_drag = None,
+        Msg::ItemPickerOpen(idx) => {
+            model.item_picker = Some(idx);
+            model.item_picker_filter.clear();
+            orders.after_next_render(|_| {
+                if let Some(input) = document().get_element_by_id(ID_INPUT_ITEM_PICKER) {
+                    input.unchecked_into::<web_sys::HtmlElement>().focus().ok();
+                }
+            });
+        }
+        Msg::ItemPickerClose => model.item_picker = None,
+        Msg::ItemPickerFilterUpdate(filter) => model.item_picker_filter = filter,
+        Msg::ItemPickerSelect(item_id) => {
+            if let Some(idx) = model.item_picker.take() {
+                orders.send_msg(Msg::SavedataUpdateInventory(idx, item_id));
+            }
+        }
+        Msg::ItemPickerSelectFirst => {
+            if let Some(item_id) = picker_items(model).next() {
+                orders.send_msg(Msg::ItemPickerSelect(item_id));
+            }
+        }
+        Msg::SavedataLoadPreset(preset) => model.savedata = preset_savedata(preset),
+        Msg::SavedataUpdateFlags(group, op) => group.update(&mut model.savedata, op),
+        Msg::SavedataMaxOut => max_out(&mut model.savedata),
+        Msg::KeyDown(event) => {
+            let modifiers = Modifiers {
+                ctrl: event.ctrl_key(),
+                alt: event.alt_key(),
+                meta: event.meta_key(),
+            };
+            let Some(shortcut) = shortcut_for_key(
+                &event.key(),
+                modifiers,
+                is_editing(&event),
+                is_in_savedata(&event),
+            ) else {
+                return;
+            };
+            if let Some(msg) = shortcut_msg(model, shortcut) {
+                event.prevent_default();
+                orders.send_msg(msg);
+            }
+        }
+        Msg::ShortcutSelectSection(section) => model.shortcut_section = Some(section),
+        Msg::ShortcutSelectSlot(slot) => model.shortcut_slot = slot,
         Msg::SavedataNormalize => model.savedata = model.savedata.normalize(),
         Msg::SavedataToPassword => {
             let password = save_to_password(&model.savedata);
-            model.query = password.display_pretty().to_string();
+            model.set_query(password.display_pretty().to_string());
+        }
+        Msg::SavedataToShortestPassword => {
+            // 入力ムービーと同じカーソルの動き方で手数を数える。
+            model.shortest_search = ShortestSearch::new(
+                &model.savedata,
+                &model.shortest_free,
+                model.movie_config.cursor,
+            );
+            if model.shortest_search.is_some() {
+                model.shortest_message = None;
+                orders.after_next_render(|_| Msg::ShortestSearchStep);
+            } else {
+                model.shortest_message = Some("このセーブデータはパスワードで表せない".to_owned());
+            }
+        }
+        Msg::ShortestSearchStep => {
+            let Some(search) = &mut model.shortest_search else {
+                return;
+            };
+            // 1 バイト分ずつ進め、その都度描画させて UI が固まらないようにする。
+            if !search.step() {
+                orders.after_next_render(|_| Msg::ShortestSearchStep);
+                return;
+            }
+
+            let search = model.shortest_search.take().unwrap();
+            let Some((password, cost)) = search.result().cloned() else {
+                model.shortest_message = Some("見つからない".to_owned());
+                return;
+            };
+            let savedata = SerializedBytes::from_password(&password)
+                .to_savedata()
+                .expect("search result must be valid");
+            let diff = free_field_diff(&model.shortest_free, &model.savedata, &savedata);
+            model.shortest_message =
+                Some(format!("手数 {cost} ({})", format_free_field_diff(&diff)));
+            model.set_query(password.display_pretty().to_string());
+            model.savedata = savedata;
+        }
+        Msg::ShortestSearchCancel => model.shortest_search = None,
+        Msg::ToggleShortestFree(field) => {
+            if let Some(i) = model.shortest_free.iter().position(|&f| f == field) {
+                model.shortest_free.remove(i);
+            } else {
+                model.shortest_free.push(field);
+            }
+        }
+        Msg::SavedataEquivalentPasswords => {
+            model.passwords = equivalent_passwords(&model.savedata)
+                .take(PASSWORD_COUNT_MAX)
+                .collect();
+        }
+        Msg::VanityWordUpdate(word) => model.vanity_word = word,
+        Msg::VanityPosUpdate(s) => {
+            if let Ok(pos) = s.parse::<usize>() {
+                model.vanity_pos = pos.clamp(1, Password::MAX_LEN);
+            }
+        }
+        Msg::ToggleVanityFree(field) => {
+            if let Some(i) = model.vanity_free.iter().position(|&f| f == field) {
+                model.vanity_free.remove(i);
+            } else {
+                model.vanity_free.push(field);
+            }
+        }
+        Msg::VanitySearch => {
+            let word: Option<Vec<PasswordChar>> =
+                normalize_query_input(&model.vanity_word, model.romaji)
+                    .chars()
+                    .filter(|c| !c.is_whitespace())
+                    .map(PasswordChar::from_char)
+                    .collect();
+            let Some(word) = word.filter(|word| !word.is_empty()) else {
+                model.vanity_message =
+                    Some("単語にパスワードに使えない文字が含まれている".to_owned());
+                return;
+            };
+            model.vanity_search = VanitySearch::new(
+                &model.savedata,
+                &word,
+                model.vanity_pos - 1,
+                &model.vanity_free,
+            );
+            if model.vanity_search.is_some() {
+                model.vanity_message = None;
+                orders.after_next_render(|_| Msg::VanitySearchStep);
+            } else {
+                model.vanity_message = Some("見つからない".to_owned());
+            }
+        }
+        Msg::VanitySearchStep => {
+            let Some(search) = &mut model.vanity_search else {
+                return;
+            };
+            // 1 バイト分ずつ進め、その都度描画させて UI が固まらないようにする。
+            if !search.step() {
+                orders.after_next_render(|_| Msg::VanitySearchStep);
+                return;
+            }
+
+            let search = model.vanity_search.take().unwrap();
+            match search.result() {
+                Some(vanity) => {
+                    model.vanity_message = Some(format_free_field_diff(&vanity.diff));
+                    model.set_query(vanity.password.display_pretty().to_string());
+                    model.savedata = vanity.savedata;
+                }
+                None => model.vanity_message = Some("見つからない".to_owned()),
+            }
+        }
+        Msg::VanitySearchCancel => model.vanity_search = None,
+        Msg::RepairPositionsUpdate(s) => model.repair_positions = s,
+        Msg::ToggleRepairField(field) => {
+            if let Some(i) = model.repair_fields.iter().position(|&f| f == field) {
+                model.repair_fields.remove(i);
+            } else {
+                model.repair_fields.push(field);
+            }
+        }
+        Msg::RepairByPositions | Msg::RepairByFields => {
+            let Ok(Query::Password(password)) = Query::parse(&model.query_normalized()) else {
+                return;
+            };
+            let repaired = if matches!(msg, Msg::RepairByPositions) {
+                let positions: Vec<usize> = model
+                    .repair_positions
+                    .split(|c: char| c == ',' || c.is_whitespace())
+                    .filter_map(|s| s.parse::<usize>().ok())
+                    .filter_map(|pos| pos.checked_sub(1))
+                    .collect();
+                repair_by_positions(&password, &positions)
+            } else {
+                repair_by_fields(&password, &model.repair_fields)
+            };
+            match repaired {
+                Some(repaired) => {
+                    model.repair_message = None;
+                    model.set_query(repaired.display_pretty().to_string());
+                }
+                None => model.repair_message = Some("修復できない".to_owned()),
+            }
+        }
+        Msg::BulkUpdate(input) => model.bulk_input = input,
+        Msg::BulkLoadFile(file) => {
+            orders.perform_cmd(async move {
+                let text = JsFuture::from(file.text()).await.ok()?.as_string()?;
+                Some(Msg::BulkUpdate(text))
+            });
+        }
+        Msg::BulkSubmit => {
+            model.bulk_rows = bulk_decode(&model.bulk_input, model.romaji, model.normalize);
+        }
+        Msg::BulkDownloadCsv => {
+            download(
+                &to_data_url(&bulk_decode_csv(&model.bulk_rows)),
+                "passwords.csv",
+            );
+        }
+        Msg::ScreenshotLoad(file) => {
+            orders.perform_cmd(async move {
+                let image = load_gray_image(file).await?;
+                Some(Msg::ScreenshotRecognize(image))
+            });
+        }
+        Msg::ScreenshotRecognize(image) => match model.glyph_templates.recognize(&image) {
+            Ok(pcs) => {
+                let unknowns: Vec<String> = pcs
+                    .iter()
+                    .enumerate()
+                    .filter(|&(_, &e)| e == PatternChar::Any)
+                    .map(|(i, _)| (i + 1).to_string())
+                    .collect();
+                model.ocr_message = Some(if unknowns.is_empty() {
+                    format!("{} 文字を読み取った", pcs.len())
+                } else {
+                    format!(
+                        "{} 文字を読み取った。判別できない文字 ('?' にした): {} 文字目",
+                        pcs.len(),
+                        unknowns.join(", ")
+                    )
+                });
+                model.set_query(pcs.into_iter().map(PatternChar::to_char).collect());
+                orders.send_msg(Msg::QuerySubmit);
+            }
+            Err(err) => model.ocr_message = Some(err.to_string()),
+        },
+        Msg::GlyphTemplatesLoad(file) => {
+            orders.perform_cmd(async move {
+                let image = load_gray_image(file).await?;
+                Some(Msg::GlyphTemplatesLearn(image))
+            });
+        }
+        Msg::GlyphTemplatesLearn(image) => {
+            let Ok(Query::Password(password)) = Query::parse(&model.query_normalized()) else {
+                model.ocr_message = Some(
+                    "テンプレート登録の前に、画像のパスワードを入力欄に入力すること".to_owned(),
+                );
+                return;
+            };
+            match model.glyph_templates.learn(&image, &password) {
+                Ok(()) => {
+                    storage_set(
+                        STORAGE_KEY_GLYPH_TEMPLATES,
+                        &model.glyph_templates.to_string(),
+                    );
+                    model.ocr_message = Some("テンプレートを登録した".to_owned());
+                    model.query_cache = Default::default();
+                }
+                Err(err) => model.ocr_message = Some(err.to_string()),
+            }
+        }
+        Msg::MemoryHexUpdate(s) => match parse_hex_dump(&s) {
+            Ok(dump) => {
+                model.memory_dump = MemoryDump::raw(dump);
+                model.memory_message = None;
+            }
+            Err(err) => model.memory_message = Some(err.to_string()),
+        },
+        Msg::MemoryLoadFile(file) => {
+            orders.perform_cmd(async move {
+                let buf = JsFuture::from(file.array_buffer()).await.ok()?;
+                Some(Msg::MemoryDumpLoaded(
+                    js_sys::Uint8Array::new(&buf).to_vec(),
+                ))
+            });
+        }
+        Msg::MemoryDumpLoaded(file) => match MemoryDump::from_file(file) {
+            Ok(dump) => {
+                let format = match dump.format() {
+                    DumpFormat::Raw => "RAM ダンプ",
+                    DumpFormat::Fceux { .. } => "FCEUX のセーブステート",
+                };
+                model.memory_message = Some(format!(
+                    "{format}を読み込んだ (RAM {} バイト)",
+                    dump.ram().len()
+                ));
+                model.memory_dump = dump;
+            }
+            Err(err) => model.memory_message = Some(err.to_string()),
+        },
+        Msg::MemoryOffsetUpdate(s) => {
+            let s = s.trim().trim_start_matches('$').trim_start_matches("0x");
+            if let Ok(offset) = usize::from_str_radix(s, 16) {
+                model.memory_offset = offset;
+            }
+        }
+        Msg::MemoryLenUpdate(s) => {
+            if let Ok(len) = s.parse::<usize>() {
+                model.memory_len = len.clamp(Password::MIN_LEN, Password::MAX_LEN);
+            }
+        }
+        Msg::MemoryFind => {
+            let password = save_to_password(&model.savedata);
+            match find_password(model.memory_dump.ram(), &password) {
+                Some(offset) => {
+                    model.memory_offset = offset;
+                    model.memory_len = password.len();
+                    model.memory_message = Some(format!(
+                        "オフセット {offset:X} に {} 文字で見つかった",
+                        password.len()
+                    ));
+                }
+                None => {
+                    model.memory_message =
+                        Some("現在のセーブデータのパスワードは見つからなかった".to_owned())
+                }
+            }
+        }
+        Msg::MemoryImport => match read_password(
+            model.memory_dump.ram(),
+            model.memory_offset,
+            model.memory_len,
+        ) {
+            Ok(password) => {
+                model.memory_message = None;
+                model.set_query(password.display_pretty().to_string());
+                orders.send_msg(Msg::QuerySubmit);
+            }
+            Err(err) => model.memory_message = Some(err.to_string()),
+        },
+        Msg::MemoryDownload => {
+            let password = save_to_password(&model.savedata);
+            let mut dump = model.memory_dump.clone();
+            match write_password(dump.ram_mut(), model.memory_offset, &password) {
+                Ok(()) => download(
+                    &data_url("application/octet-stream", &dump.to_file()),
+                    dump.file_name(),
+                ),
+                Err(err) => model.memory_message = Some(err.to_string()),
+            }
+        }
+        Msg::RamMapLearn => {
+            model
+                .ram_map
+                .learn(model.memory_dump.ram(), &model.savedata);
+            storage_set(STORAGE_KEY_RAM_MAP, &model.ram_map.to_string());
+            model.memory_message = Some(format!(
+                "現在のセーブデータで学習した ({} 回目)",
+                model.ram_map.sample_count()
+            ));
+        }
+        Msg::RamMapRead => {
+            let (savedata, unread) = model
+                .ram_map
+                .read_savedata(model.memory_dump.ram(), &model.savedata);
+            model.memory_message = Some(if unread.is_empty() {
+                "RAM からセーブデータを読み込んだ".to_owned()
+            } else {
+                format!(
+                    "RAM からセーブデータを読み込んだ。読めなかった項目 (元の値のまま): {}",
+                    ram_fields_text(&unread)
+                )
+            });
+            model.savedata = savedata;
+        }
+        Msg::RamMapDownload => {
+            let mut dump = model.memory_dump.clone();
+            let unwritten = model
+                .ram_map
+                .write_savedata(dump.ram_mut(), &model.savedata);
+            if !unwritten.is_empty() {
+                model.memory_message = Some(format!(
+                    "書き込めなかった項目 (ファイルの値のまま): {}",
+                    ram_fields_text(&unwritten)
+                ));
+            }
+            download(
+                &data_url("application/octet-stream", &dump.to_file()),
+                dump.file_name(),
+            );
+        }
+        Msg::RamMapClear => {
+            model.ram_map = Default::default();
+            storage_set(STORAGE_KEY_RAM_MAP, "");
+            model.memory_message = None;
+        }
+        Msg::ToggleMovieWrap => model.movie_config.cursor.wrap.toggle(),
+        Msg::ToggleMovieSkipGaps => model.movie_config.cursor.skip_gaps.toggle(),
+        Msg::MovieFramesUpdate(s) => {
+            if let Ok(frames) = s.parse::<usize>() {
+                model.movie_config.frames_per_input = frames.clamp(
+                    MovieConfig::FRAMES_PER_INPUT_MIN,
+                    MovieConfig::FRAMES_PER_INPUT_MAX,
+                );
+            }
+        }
+        Msg::MovieLoadFile(file) => {
+            orders.perform_cmd(async move {
+                let buf = JsFuture::from(file.array_buffer()).await.ok()?;
+                Some(Msg::MovieFileLoaded(js_sys::Uint8Array::new(&buf).to_vec()))
+            });
+        }
+        Msg::MovieFileLoaded(file) => {
+            model.movie_message = Some(match model.movie_source.load(&file) {
+                Ok(MovieFileKind::Rom) => "ROM を読み込んだ".to_owned(),
+                Ok(MovieFileKind::FceuxState) => "FCEUX のセーブステートを読み込んだ".to_owned(),
+                Ok(MovieFileKind::BizHawkState) => {
+                    "BizHawk のセーブステートを読み込んだ".to_owned()
+                }
+                Err(err) => err.to_string(),
+            });
+        }
+        Msg::GlyphTemplatesClear => {
+            model.glyph_templates = Default::default();
+            model.query_cache = Default::default();
+            storage_set(STORAGE_KEY_GLYPH_TEMPLATES, "");
+            model.ocr_message = None;
         }
     }
 }
 
-fn load_from_password(password: &Password, normalize: bool) -> Option<Savedata> {
-    let bytes = SerializedBytes::from_password(password);
-    let savedata = bytes.to_savedata()?;
+/// 画像ファイルを読み込み、グレースケール画像に変換する。
+async fn load_gray_image(file: web_sys::File) -> Option<GrayImage> {
+    let bitmap: web_sys::ImageBitmap =
+        JsFuture::from(window().create_image_bitmap_with_blob(&file).ok()?)
+            .await
+            .ok()?
+            .dyn_into()
+            .ok()?;
+    let (w, h) = (bitmap.width(), bitmap.height());
+
+    let canvas: web_sys::HtmlCanvasElement =
+        document().create_element("canvas").ok()?.dyn_into().ok()?;
+    canvas.set_width(w);
+    canvas.set_height(h);
+
+    let ctx: web_sys::CanvasRenderingContext2d = canvas.get_context("2d").ok()??.dyn_into().ok()?;
+    ctx.draw_image_with_image_bitmap(&bitmap, 0.0, 0.0).ok()?;
+    let data = ctx
+        .get_image_data(0.0, 0.0, f64::from(w), f64::from(h))
+        .ok()?;
+
+    Some(GrayImage::from_rgba(w as usize, h as usize, &data.data()))
+}
 
-    Some(if normalize {
-        savedata.normalize()
-    } else {
-        savedata
-    })
+fn storage_get(key: &str) -> Option<String> {
+    window().local_storage().ok()??.get_item(key).ok()?
+}
+
+fn storage_set(key: &str, value: &str) {
+    if let Ok(Some(storage)) = window().local_storage() {
+        let _ = storage.set_item(key, value);
+    }
+}
+
+/// `url` の内容をファイル名 `filename` としてダウンロードさせる。
+fn download(url: &str, filename: &str) {
+    let Ok(a) = document().create_element("a") else {
+        return;
+    };
+    let a: web_sys::HtmlAnchorElement = a.unchecked_into();
+    a.set_href(url);
+    a.set_download(filename);
+    a.click();
+}
+
+/// セーブデータの項目名を "、" 区切りで並べる。
+fn ram_fields_text(fields: &[SavedataField]) -> String {
+    fields
+        .iter()
+        .map(|&field| savedata_field_name(field))
+        .collect::<Vec<_>>()
+        .join("、")
+}
+
+/// キーボードイベントの対象の要素を返す。
+fn event_target_element(event: &web_sys::KeyboardEvent) -> Option<web_sys::Element> {
+    event
+        .target()
+        .and_then(|target| target.dyn_into::<web_sys::Element>().ok())
+}
+
+/// キーボードイベントの対象が文字入力を受け付ける要素かどうかを返す。
+fn is_editing(event: &web_sys::KeyboardEvent) -> bool {
+    let Some(elem) = event_target_element(event) else {
+        return false;
+    };
+
+    match elem.tag_name().as_str() {
+        "TEXTAREA" | "SELECT" => true,
+        "INPUT" => !matches!(
+            elem.get_attribute("type").as_deref(),
+            Some("checkbox" | "radio" | "button" | "submit" | "file")
+        ),
+        _ => false,
+    }
+}
+
+/// キーボードイベントの対象がセーブデータ編集フォーム内の要素かどうかを返す。
+fn is_in_savedata(event: &web_sys::KeyboardEvent) -> bool {
+    event_target_element(event)
+        .and_then(|elem| elem.closest("#savedata-container").ok().flatten())
+        .is_some()
+}
+
+/// ショートカット `shortcut` に対応するメッセージを返す。何もしない場合は `None` を返す。
+fn shortcut_msg(model: &Model, shortcut: Shortcut) -> Option<Msg> {
+    fn flag_msg<F: Flag>(idx: usize, to_msg: fn(F) -> Msg) -> Option<Msg> {
+        F::ALL.get(idx).map(|&flag| to_msg(flag))
+    }
+
+    let slots = EquipmentSlot::all();
+    let slot = model.shortcut_slot;
+
+    match (shortcut, model.shortcut_section) {
+        (Shortcut::Submit, _) => Some(Msg::SavedataToPassword),
+        (Shortcut::MoveSection(delta), section) => Some(Msg::ShortcutSelectSection(
+            SavedataSection::step(section, delta),
+        )),
+        (Shortcut::Select(idx), Some(SavedataSection::Flags(group))) => match group {
+            FlagGroup::Spells => flag_msg(idx, Msg::SavedataToggleSpell),
+            FlagGroup::Events => flag_msg(idx, Msg::SavedataToggleEvent),
+            FlagGroup::Treasures => flag_msg(idx, Msg::SavedataToggleTreasure),
+            FlagGroup::Minions => flag_msg(idx, Msg::SavedataToggleMinion),
+            FlagGroup::Bookmarks => flag_msg(idx, Msg::SavedataToggleBookmark),
+        },
+        (Shortcut::Select(idx), Some(SavedataSection::Equipment)) => {
+            slots.get(idx).map(|&slot| Msg::ShortcutSelectSlot(slot))
+        }
+        (Shortcut::MoveItem(delta), Some(SavedataSection::Equipment)) => {
+            let idx = (slot.int_value() as isize + delta).rem_euclid(slots.len() as isize);
+            Some(Msg::ShortcutSelectSlot(slots[idx as usize]))
+        }
+        (Shortcut::CycleValue(delta), Some(SavedataSection::Equipment)) => {
+            let count = isize::from(equipment_index_max(slot)) + 1;
+            let cur = isize::from(equipment_index(&model.savedata.equipment, slot));
+            let index = (cur + delta).rem_euclid(count) as u8;
+            Some(equipment_update_msg(slot, index))
+        }
+        _ => None,
+    }
+}
+
+/// 装備枠 `slot` のインデックスを `index` にするメッセージを返す。`index` は範囲内でなければならない。
+fn equipment_update_msg(slot: EquipmentSlot, index: u8) -> Msg {
+    match slot {
+        EquipmentSlot::Helm => Msg::SavedataUpdateHelm(HelmIndex::new(index).unwrap()),
+        EquipmentSlot::Weapon => Msg::SavedataUpdateWeapon(WeaponIndex::new(index).unwrap()),
+        EquipmentSlot::Armor => Msg::SavedataUpdateArmor(ArmorIndex::new(index).unwrap()),
+        EquipmentSlot::Shoes => Msg::SavedataUpdateShoes(ShoesIndex::new(index).unwrap()),
+        EquipmentSlot::Accessory0 => {
+            Msg::SavedataUpdateAccessory0(Accessory0Index::new(index).unwrap())
+        }
+        EquipmentSlot::Accessory1 => {
+            Msg::SavedataUpdateAccessory1(Accessory1Index::new(index).unwrap())
+        }
+        EquipmentSlot::Accessory2 => {
+            Msg::SavedataUpdateAccessory2(Accessory2Index::new(index).unwrap())
+        }
+        EquipmentSlot::Accessory3 => {
+            Msg::SavedataUpdateAccessory3(Accessory3Index::new(index).unwrap())
+        }
+    }
+}
+
+/// セーブデータのフラグ `flag` を反転する。
+fn toggle_flag<F: Flag>(savedata: &mut Savedata, flag: F) {
+    F::group_mut(savedata)[flag].toggle();
 }
 
-fn save_to_password(savedata: &Savedata) -> Password {
-    let bytes = SerializedBytes::from_savedata(savedata);
-    bytes.to_password()
+/// 文字列 `s` の `n` 文字目のバイト位置を返す。`n` が文字数以上なら `s.len()` を返す。
+fn char_to_byte_index(s: &str, n: usize) -> usize {
+    s.char_indices().nth(n).map_or(s.len(), |(i, _)| i)
 }
 
 // <select> 要素の扱いについて:
@@ -168,11 +1096,14 @@ fn save_to_password(savedata: &Savedata) -> Password {
 //
 // ref: https://github.com/seed-rs/seed/issues/558
 
-fn view(model: &Model) -> Node<Msg> {
-    div![
-        id!("app-container"),
-        view_query_passwords(model),
-        view_savedata(model),
+fn view(model: &Model) -> Vec<Node<Msg>> {
+    nodes![
+        div![
+            id!("app-container"),
+            view_query_passwords(model),
+            view_savedata(model),
+        ],
+        view_bulk(model),
     ]
 }
 
@@ -180,7 +1111,9 @@ fn view_query_passwords(model: &Model) -> Node<Msg> {
     div![
         id!("query-passwords-container"),
         view_query(model),
-        h2![format!("パターン検索結果 ({PASSWORD_COUNT_MAX} 個まで)")],
+        h2![format!(
+            "パターン検索結果 ({PASSWORD_COUNT_MAX} 個まで、もっともらしい順)"
+        )],
         view_passwords(model),
     ]
 }
@@ -196,6 +1129,7 @@ fn view_query(model: &Model) -> Node<Msg> {
                     St::Display => "flex",
                     St::JustifyContent => "flex-end",
                 },
+                view_query_romaji(model),
                 view_query_normalize(model),
             ],
             div![input![
@@ -204,9 +1138,13 @@ fn view_query(model: &Model) -> Node<Msg> {
                     At::Type => "text",
                     At::Value => &model.query,
                     At::Placeholder => "パスワード ('?' は 3 個まで。空白は無視される)",
+                    At::Title => password_layout_desc(),
                 },
                 input_ev(Ev::Input, Msg::QueryUpdate)
             ]],
+            view_keyboard(model),
+            view_screenshot(model),
+            view_memory(model),
             view_query_ui(model),
             ev(Ev::Submit, |ev| {
                 ev.prevent_default();
@@ -217,14 +1155,21 @@ fn view_query(model: &Model) -> Node<Msg> {
 }
 
 fn view_query_ui(model: &Model) -> Node<Msg> {
-    match Query::parse(&model.query) {
+    let query = model.query_normalized();
+
+    // 入力が変換された場合、変換後の文字列を示す。
+    let preview = (query != model.query).then(|| div![format!("→ {query}")]);
+
+    let ui = match Query::parse(&query) {
         Ok(Query::Password(password)) => view_query_ui_password(model, &password),
         Ok(Query::Pattern(pattern)) => view_query_ui_pattern(model, &pattern),
-        Err(err) => view_query_ui_error(model, &err),
-    }
+        Err(err) => view_query_ui_error(model, &query, &err),
+    };
+
+    div![preview, ui]
 }
 
-fn view_query_ui_password(_model: &Model, password: &Password) -> Node<Msg> {
+fn view_query_ui_password(model: &Model, password: &Password) -> Node<Msg> {
     if let Some(&pc) = password.get(1) {
         if Password::is_invalid_second_char(pc) {
             return div![
@@ -234,7 +1179,7 @@ fn view_query_ui_password(_model: &Model, password: &Password) -> Node<Msg> {
         }
     }
 
-    if password.is_valid() {
+    let ui = if password.is_valid() {
         div![
             style! {
                 St::Display => "flex",
@@ -249,91 +1194,883 @@ fn view_query_ui_password(_model: &Model, password: &Password) -> Node<Msg> {
             ]
         ]
     } else {
-        div![C!(CLASS_WARN), "パスワードが無効"]
-    }
-}
-
-fn view_query_ui_pattern(_model: &Model, pattern: &Pattern) -> Node<Msg> {
-    if let Some(&PatternChar::Password(pc)) = pattern.get(1) {
-        if Password::is_invalid_second_char(pc) {
-            return div![
-                C!(CLASS_WARN),
-                format!("2 文字目が '{}' のパスワードは無効", pc.to_char())
-            ];
-        }
-    }
-
-    div![
-        style! {
-            St::Display => "flex",
-            St::JustifyContent => "flex-end",
-        },
-        button![
-            attrs! {
-                At::Type => "submit",
-            },
-            "パターン検索",
-            ev(Ev::Click, |_| Msg::QuerySubmit)
+        div![
+            div![C!(CLASS_WARN), "パスワードが無効"],
+            view_query_ui_repair(model),
         ]
-    ]
-}
-
-fn view_query_ui_error(_model: &Model, err: &QueryParseError) -> Node<Msg> {
-    match err {
-        QueryParseError::Empty => div![],
-        _ => div![C!(CLASS_WARN), err.to_string()],
-    }
-}
-
-fn view_query_normalize(model: &Model) -> Node<Msg> {
-    const ID_INPUT: &str = "input-normalize";
-    const DESC: &str =
-        "パスワードに記録された装備が不正な場合、ロード後に装備が変化する。\nチェックを外すと装備変化を行わない。";
+    };
 
     div![
-        input![
-            id!(ID_INPUT),
-            attrs! {
-                At::Type => "checkbox",
-                At::Checked => model.normalize.as_at_value(),
-            },
-            ev(Ev::Change, |_| Msg::ToggleNormalize)
-        ],
-        label![
-            C!(CLASS_HAS_TOOLTIP),
-            attrs! {
-                At::For => ID_INPUT,
-                At::Title => DESC,
-            },
-            "ロード時に正規化を行う"
-        ],
+        view_query_ui_alternatives(model, password),
+        view_query_ui_screen(model, password),
+        view_query_ui_movie(model, password),
+        ui
     ]
 }
 
-fn view_passwords(model: &Model) -> Node<Msg> {
-    const CLASS_ITEM: &str = "passwords-item";
+fn view_query_ui_repair(model: &Model) -> Node<Msg> {
+    const ID_INPUT_POSITIONS: &str = "input-repair-positions";
 
-    let passwords = model.passwords.iter().enumerate().map(|(i, password)| {
-        li![
-            C![CLASS_ITEM],
-            password.display_pretty().to_string(),
-            ev(Ev::Click, move |_| Msg::PickPassword(i))
+    let checkboxes = FreeField::all().map(|field| {
+        label![
+            input![
+                attrs! {
+                    At::Type => "checkbox",
+                    At::Checked => model.repair_fields.contains(&field).as_at_value(),
+                },
+                ev(Ev::Change, move |_| Msg::ToggleRepairField(field))
+            ],
+            free_field_name(field),
         ]
     });
 
-    div![id!("passwords-container"), ul![passwords]]
-}
-
-fn view_savedata(model: &Model) -> Node<Msg> {
-    div![
-        id!("savedata-container"),
-        form![
-            div![
-                style! {
-                    St::Display => "flex",
-                    St::JustifyContent => "flex-end",
-                },
-                button![
+    details![
+        summary!["チェックサムを修復"],
+        div![
+            label![
+                attrs! {
+                    At::For => ID_INPUT_POSITIONS,
+                },
+                "変えてよい文字位置: "
+            ],
+            input![
+                id!(ID_INPUT_POSITIONS),
+                attrs! {
+                    At::Type => "text",
+                    At::Value => &model.repair_positions,
+                    At::Placeholder => "例: 1 2 10",
+                },
+                input_ev(Ev::Input, Msg::RepairPositionsUpdate)
+            ],
+            button![
+                attrs! {
+                    At::Type => "button",
+                },
+                "文字を変えて修復",
+                ev(Ev::Click, |_| Msg::RepairByPositions)
+            ],
+        ],
+        div![
+            "変えてよいフィールド: ",
+            checkboxes,
+            button![
+                attrs! {
+                    At::Type => "button",
+                },
+                "フィールドを変えて修復",
+                ev(Ev::Click, |_| Msg::RepairByFields)
+            ],
+        ],
+        model
+            .repair_message
+            .as_ref()
+            .map(|msg| div![C!(CLASS_WARN), msg]),
+    ]
+}
+
+/// 同じセーブデータのまま入れ替えられる文字を強調表示する。
+fn view_query_ui_alternatives(model: &Model, password: &Password) -> Option<Node<Msg>> {
+    const CLASS_CHAR: &str = "alternative-char";
+    const CLASS_REPLACEABLE: &str = "alternative-char-replaceable";
+
+    let alternatives = model.query_cache.alternatives.as_ref()?;
+
+    let chars = password
+        .iter()
+        .zip(alternatives)
+        .enumerate()
+        .map(|(idx, (pc, alts))| {
+            if alts.is_empty() {
+                return span![C![CLASS_CHAR], pc.to_char().to_string()];
+            }
+            let title: String = alts.iter().map(|(alt, _)| alt.to_char()).collect();
+            span![
+                C![CLASS_CHAR, CLASS_REPLACEABLE],
+                attrs! {
+                    At::Title => format!("入れ替え可能: {title}"),
+                },
+                pc.to_char().to_string(),
+                ev(Ev::Click, move |_| Msg::ShowAlternatives(idx))
+            ]
+        });
+
+    Some(div![
+        id!("alternatives-container"),
+        span!["入れ替え可能な文字 (クリックで候補を表示): "],
+        chars,
+    ])
+}
+
+fn view_query_ui_movie(model: &Model, password: &Password) -> Node<Msg> {
+    const ID_INPUT_FRAMES: &str = "input-movie-frames";
+    const ID_INPUT_FILE: &str = "input-movie-file";
+
+    let config = model.movie_config;
+    let cursor = config.cursor;
+    let input_count = password_inputs(password, cursor).len();
+
+    let checkbox = |text: &str, checked: bool, msg: fn() -> Msg| {
+        label![
+            input![
+                attrs! {
+                    At::Type => "checkbox",
+                    At::Checked => checked.as_at_value(),
+                },
+                ev(Ev::Change, move |_| msg())
+            ],
+            text,
+        ]
+    };
+
+    let loaded = |loaded: bool| {
+        if loaded {
+            "読み込み済み"
+        } else {
+            "未読み込み"
+        }
+    };
+    let source = &model.movie_source;
+
+    details![
+        summary!["入力ムービーを生成"],
+        div![
+            "ムービーは電源投入からではなく、セーブステートから再生する。",
+            "パスワード入力画面でカーソルが「あ」にある状態のセーブステートと、チェックサム計算用の ROM を読み込むこと。",
+        ],
+        div![
+            label![
+                attrs! {
+                    At::For => ID_INPUT_FILE,
+                },
+                "ROM / セーブステートファイル: "
+            ],
+            input![
+                id!(ID_INPUT_FILE),
+                attrs! {
+                    At::Type => "file",
+                },
+                ev(Ev::Change, |ev| {
+                    let input = ev.target()?.dyn_into::<web_sys::HtmlInputElement>().ok()?;
+                    let file = input.files()?.get(0)?;
+                    input.set_value("");
+                    Some(Msg::MovieLoadFile(file))
+                })
+            ],
+        ],
+        div![format!(
+            "ROM: {}、FCEUX のセーブステート (.fcs): {}、BizHawk のセーブステート (.State): {}",
+            loaded(source.has_rom()),
+            loaded(source.has_fceux_state()),
+            loaded(source.has_bizhawk_state())
+        )],
+        div![
+            checkbox("カーソルが端で回り込む", cursor.wrap, || {
+                Msg::ToggleMovieWrap
+            }),
+            checkbox("空きマスを飛ばす", cursor.skip_gaps, || {
+                Msg::ToggleMovieSkipGaps
+            }),
+        ],
+        div![
+            label![
+                attrs! {
+                    At::For => ID_INPUT_FRAMES,
+                },
+                "入力 1 回あたりのフレーム数: "
+            ],
+            input![
+                id!(ID_INPUT_FRAMES),
+                attrs! {
+                    At::Type => "number",
+                    At::Min => MovieConfig::FRAMES_PER_INPUT_MIN,
+                    At::Max => MovieConfig::FRAMES_PER_INPUT_MAX,
+                    At::Value => config.frames_per_input,
+                },
+                input_ev(Ev::Input, Msg::MovieFramesUpdate)
+            ],
+        ],
+        div![format!(
+            "入力 {input_count} 回 ({} フレーム)",
+            input_count.saturating_mul(config.frames_per_input)
+        )],
+        div![
+            a![
+                attrs! {
+                    At::Href => "#",
+                },
+                "FM2 (FCEUX) で保存",
+                ev(Ev::Click, {
+                    let password = password.clone();
+                    move |ev| {
+                        ev.prevent_default();
+                        Msg::ExportMovieFm2(password)
+                    }
+                })
+            ],
+            " ",
+            a![
+                attrs! {
+                    At::Href => "#",
+                },
+                "BK2 (BizHawk) で保存",
+                ev(Ev::Click, {
+                    let password = password.clone();
+                    move |ev| {
+                        ev.prevent_default();
+                        Msg::ExportMovieBk2(password)
+                    }
+                })
+            ],
+        ],
+        model.movie_message.as_ref().map(|msg| div![msg]),
+    ]
+}
+
+fn view_query_ui_screen(model: &Model, password: &Password) -> Node<Msg> {
+    let svg_url = &model.query_cache.screen_svg_url;
+    let filename = password.display_pretty().to_string();
+    let password = password.clone();
+
+    details![
+        summary!["ゲーム画面の体裁で表示"],
+        div![
+            "字形と文字の位置は「スクリーンショットから読み取る」で登録した文字テンプレートのものを使う。",
+            "未登録の文字は等幅フォントで代用する。",
+        ],
+        div![
+            id!("screen-container"),
+            img![attrs! {
+                At::Src => svg_url,
+                At::Alt => filename,
+            }],
+        ],
+        div![
+            a![
+                attrs! {
+                    At::Href => svg_url,
+                    At::Download => format!("{filename}.svg"),
+                },
+                "SVG で保存",
+            ],
+            " ",
+            a![
+                attrs! {
+                    At::Href => "#",
+                },
+                "PNG で保存",
+                ev(Ev::Click, move |ev| {
+                    ev.prevent_default();
+                    Msg::ExportPasswordPng(password)
+                })
+            ],
+        ],
+    ]
+}
+
+fn view_query_ui_pattern(_model: &Model, pattern: &Pattern) -> Node<Msg> {
+    if let Some(&PatternChar::Password(pc)) = pattern.get(1) {
+        if Password::is_invalid_second_char(pc) {
+            return div![
+                C!(CLASS_WARN),
+                format!("2 文字目が '{}' のパスワードは無効", pc.to_char())
+            ];
+        }
+    }
+
+    div![
+        style! {
+            St::Display => "flex",
+            St::JustifyContent => "flex-end",
+        },
+        button![
+            attrs! {
+                At::Type => "submit",
+            },
+            "パターン検索",
+            ev(Ev::Click, |_| Msg::QuerySubmit)
+        ]
+    ]
+}
+
+fn view_query_ui_error(_model: &Model, query: &str, err: &QueryParseError) -> Node<Msg> {
+    const CLASS_ERROR_SPAN: &str = "query-error-span";
+
+    if let QueryParseError::Empty = err {
+        return div![];
+    }
+
+    let diags = Query::diagnose(query);
+
+    // 入力文字列を、問題のある箇所に下線を引いて表示する。
+    // 下線の有無が同じ文字の連続をまとめて 1 個の <span> とする。
+    let mut runs: Vec<(bool, String)> = vec![];
+    for (i, c) in query.chars().enumerate() {
+        let marked = diags.iter().any(|diag| diag.span.contains(&i));
+        match runs.last_mut() {
+            Some((marked_last, run)) if *marked_last == marked => run.push(c),
+            _ => runs.push((marked, c.to_string())),
+        }
+    }
+    let runs = runs
+        .into_iter()
+        .map(|(marked, run)| span![C![IF!(marked => CLASS_ERROR_SPAN)], run]);
+
+    // 同じメッセージは 1 回だけ表示する。
+    let mut msgs: Vec<String> = vec![];
+    for diag in &diags {
+        let msg = diag.error.to_string();
+        if !msgs.contains(&msg) {
+            msgs.push(msg);
+        }
+    }
+
+    div![
+        div![id!("query-diagnostics-text"), runs],
+        ul![C!(CLASS_WARN), msgs.into_iter().map(|msg| li![msg])],
+    ]
+}
+
+fn view_keyboard(model: &Model) -> Node<Msg> {
+    const CLASS_KEY: &str = "keyboard-key";
+    const CLASS_CHAR: &str = "keyboard-char";
+    const CLASS_CURSOR: &str = "keyboard-cursor";
+
+    let cursor = model.query_cursor();
+
+    // 入力中の文字列。カーソル位置を示し、クリックでカーソルを移動できる。
+    let chars = model.query.chars().enumerate().map(|(i, c)| {
+        span![
+            C![CLASS_CHAR, IF!(i == cursor => CLASS_CURSOR)],
+            c.to_string(),
+            ev(Ev::Click, move |_| Msg::KeyboardSetCursor(i))
+        ]
+    });
+    let chars_end = span![
+        C![
+            CLASS_CHAR,
+            IF!(cursor == model.query.chars().count() => CLASS_CURSOR)
+        ],
+        "\u{00A0}",
+        ev(Ev::Click, move |_| Msg::KeyboardSetCursor(usize::MAX))
+    ];
+
+    let keys = KEYBOARD.iter().flatten().map(|&cell| match cell {
+        Some(pc) => button![
+            C![CLASS_KEY],
+            attrs! {
+                At::Type => "button",
+            },
+            pc.to_char().to_string(),
+            ev(Ev::Click, move |_| Msg::KeyboardInsert(pc.to_char()))
+        ],
+        None => div![],
+    });
+
+    let button = |text: &str, msg: fn() -> Msg| {
+        button![
+            attrs! {
+                At::Type => "button",
+            },
+            text,
+            ev(Ev::Click, move |_| msg())
+        ]
+    };
+
+    details![
+        summary!["文字パレット"],
+        div![id!("keyboard-text"), chars, chars_end],
+        div![
+            id!("keyboard-container"),
+            div![
+                id!("keyboard"),
+                style! {
+                    St::GridTemplateColumns => format!("repeat({KEYBOARD_WIDTH}, 1fr)"),
+                },
+                keys
+            ],
+            div![
+                id!("keyboard-controls"),
+                button("←", || Msg::KeyboardMoveCursor(-1)),
+                button("→", || Msg::KeyboardMoveCursor(1)),
+                button("?", || Msg::KeyboardInsert('?')),
+                button("1 文字消す", || Msg::KeyboardBackspace),
+            ],
+        ],
+    ]
+}
+
+fn view_screenshot(model: &Model) -> Node<Msg> {
+    const ID_INPUT_SCREENSHOT: &str = "input-screenshot";
+    const ID_INPUT_TEMPLATE: &str = "input-glyph-template";
+
+    fn file_input(id: &str, msg: fn(web_sys::File) -> Msg) -> Node<Msg> {
+        input![
+            id!(id),
+            attrs! {
+                At::Type => "file",
+                At::Accept => "image/png",
+            },
+            ev(Ev::Change, move |ev| {
+                let input = ev.target()?.dyn_into::<web_sys::HtmlInputElement>().ok()?;
+                let file = input.files()?.get(0)?;
+                input.set_value("");
+                Some(msg(file))
+            })
+        ]
+    }
+
+    details![
+        summary!["スクリーンショットから読み取る"],
+        div![
+            "エミュレータの画面全体のスクリーンショット (拡大してあってもよい) を使う。",
+            "ゲームのフォントは同梱していないので、読み取りの前に、パスワードが分かっている画像で文字テンプレートを登録しておくこと。",
+        ],
+        div![
+            label![
+                attrs! {
+                    At::For => ID_INPUT_SCREENSHOT,
+                },
+                "読み取る画像: "
+            ],
+            file_input(ID_INPUT_SCREENSHOT, Msg::ScreenshotLoad),
+        ],
+        div![
+            label![
+                attrs! {
+                    At::For => ID_INPUT_TEMPLATE,
+                },
+                "入力欄のパスワードの画像をテンプレートとして登録: "
+            ],
+            file_input(ID_INPUT_TEMPLATE, Msg::GlyphTemplatesLoad),
+        ],
+        div![
+            format!(
+                "登録済み: {} / {} 文字 ",
+                model.glyph_templates.char_count(),
+                PasswordChar::all().len()
+            ),
+            button![
+                attrs! {
+                    At::Type => "button",
+                },
+                "テンプレートを消去",
+                ev(Ev::Click, |_| Msg::GlyphTemplatesClear)
+            ],
+        ],
+        model.ocr_message.as_ref().map(|msg| div![msg]),
+    ]
+}
+
+fn view_memory(model: &Model) -> Node<Msg> {
+    const ID_INPUT_HEX: &str = "input-memory-hex";
+    const ID_INPUT_FILE: &str = "input-memory-file";
+    const ID_INPUT_OFFSET: &str = "input-memory-offset";
+    const ID_INPUT_LEN: &str = "input-memory-len";
+
+    let password = save_to_password(&model.savedata);
+
+    details![
+        summary!["エミュレータのメモリから読み込む"],
+        div![
+            "パスワード入力画面での RAM のパスワード領域を読み書きする。",
+            "パスワード領域のアドレスと文字数は自分で指定すること。",
+            "それ以外の場面の RAM は、下の学習した位置を使って読み書きする。",
+        ],
+        div![
+            "RAM ダンプと FCEUX のセーブステート (.fcs) に対応している。",
+            "Mesen などそれ以外のセーブステートは、RAM をダンプしてから読み込むこと。",
+        ],
+        div![
+            label![
+                attrs! {
+                    At::For => ID_INPUT_HEX,
+                },
+                "16 進ダンプ: "
+            ],
+            input![
+                id!(ID_INPUT_HEX),
+                attrs! {
+                    At::Type => "text",
+                    At::Placeholder => "00 36 3F ...",
+                },
+                input_ev(Ev::Input, Msg::MemoryHexUpdate)
+            ],
+        ],
+        div![
+            label![
+                attrs! {
+                    At::For => ID_INPUT_FILE,
+                },
+                "RAM ダンプ / セーブステートファイル: "
+            ],
+            input![
+                id!(ID_INPUT_FILE),
+                attrs! {
+                    At::Type => "file",
+                },
+                ev(Ev::Change, |ev| {
+                    let input = ev.target()?.dyn_into::<web_sys::HtmlInputElement>().ok()?;
+                    let file = input.files()?.get(0)?;
+                    Some(Msg::MemoryLoadFile(file))
+                })
+            ],
+        ],
+        div![
+            label![
+                attrs! {
+                    At::For => ID_INPUT_OFFSET,
+                },
+                "パスワード領域のオフセット (16 進): "
+            ],
+            input![
+                id!(ID_INPUT_OFFSET),
+                attrs! {
+                    At::Type => "text",
+                    At::Value => format!("{:X}", model.memory_offset),
+                },
+                input_ev(Ev::Input, Msg::MemoryOffsetUpdate)
+            ],
+            label![
+                attrs! {
+                    At::For => ID_INPUT_LEN,
+                },
+                " 文字数: "
+            ],
+            input![
+                id!(ID_INPUT_LEN),
+                attrs! {
+                    At::Type => "number",
+                    At::Min => Password::MIN_LEN,
+                    At::Max => Password::MAX_LEN,
+                    At::Value => model.memory_len,
+                },
+                input_ev(Ev::Input, Msg::MemoryLenUpdate)
+            ],
+            button![
+                attrs! {
+                    At::Type => "button",
+                },
+                "パスワードを読み込む",
+                ev(Ev::Click, |_| Msg::MemoryImport)
+            ],
+        ],
+        div![button![
+            attrs! {
+                At::Type => "button",
+            },
+            "現在のセーブデータのパスワードの位置を探す",
+            ev(Ev::Click, |_| Msg::MemoryFind)
+        ],],
+        div![format!(
+            "現在のセーブデータのパスワード (メモリエディタ用): {}",
+            password.display_hex()
+        )],
+        IF!(!model.memory_dump.ram().is_empty() => div![a![
+            attrs! {
+                At::Href => "#",
+            },
+            "現在のセーブデータのパスワードを書き込んだファイルを保存",
+            ev(Ev::Click, |ev| {
+                ev.prevent_default();
+                Msg::MemoryDownload
+            })
+        ]]),
+        view_ram_map(model),
+        model.memory_message.as_ref().map(|msg| div![msg]),
+    ]
+}
+
+fn view_ram_map(model: &Model) -> Node<Msg> {
+    let has_ram = !model.memory_dump.ram().is_empty();
+    let action_button = |text: &str, disabled: bool, msg: fn() -> Msg| {
+        button![
+            attrs! {
+                At::Type => "button",
+                At::Disabled => disabled.as_at_value(),
+            },
+            text,
+            ev(Ev::Click, move |_| msg())
+        ]
+    };
+
+    div![
+        div![
+            "パスワード入力画面以外の RAM からも、各項目を直接読み書きする。",
+            "項目の位置は公開された資料がないので、RAM と現在のセーブデータの組から学習する。",
+            "パスワードを読み込んだ直後の RAM など、セーブデータの分かっている RAM を何度か学習させて位置を定めること。",
+        ],
+        div![
+            action_button(
+                "現在のセーブデータで学習",
+                !has_ram,
+                || Msg::RamMapLearn
+            ),
+            action_button(
+                "RAM からセーブデータを読み込む",
+                !has_ram,
+                || Msg::RamMapRead
+            ),
+            action_button(
+                "現在のセーブデータを書き込んだファイルを保存",
+                !has_ram,
+                || Msg::RamMapDownload
+            ),
+            action_button(
+                "学習結果を消去",
+                model.ram_map.sample_count() == 0,
+                || Msg::RamMapClear
+            ),
+        ],
+        div![format!("学習済み: {} 回", model.ram_map.sample_count())],
+        IF!(model.ram_map.sample_count() > 0 => ul![SavedataField::all().map(|field| {
+            let status = match model.ram_map.status(field) {
+                RamFieldStatus::Unlearned => "未学習".to_owned(),
+                RamFieldStatus::Located(loc) => loc.to_string(),
+                RamFieldStatus::Ambiguous(n) => format!("候補 {n} 個"),
+                RamFieldStatus::NotFound => "見つからない".to_owned(),
+            };
+            li![format!("{}: {status}", savedata_field_name(field))]
+        })]),
+    ]
+}
+
+fn view_query_romaji(model: &Model) -> Node<Msg> {
+    const ID_INPUT: &str = "input-romaji";
+    const DESC: &str = "ローマ字をひらがなに変換する (例: \"shi\" -> 'し')。\nカタカナや半角カナは常にひらがなに変換される。";
+
+    div![
+        style! {
+            St::MarginRight => "8px",
+        },
+        input![
+            id!(ID_INPUT),
+            attrs! {
+                At::Type => "checkbox",
+                At::Checked => model.romaji.as_at_value(),
+            },
+            ev(Ev::Change, |_| Msg::ToggleRomaji)
+        ],
+        label![
+            C!(CLASS_HAS_TOOLTIP),
+            attrs! {
+                At::For => ID_INPUT,
+                At::Title => DESC,
+            },
+            "ローマ字入力"
+        ],
+    ]
+}
+
+fn view_query_normalize(model: &Model) -> Node<Msg> {
+    const ID_INPUT: &str = "input-normalize";
+    const DESC: &str =
+        "パスワードに記録された装備が不正な場合、ロード後に装備が変化する。\nチェックを外すと装備変化を行わない。";
+
+    div![
+        input![
+            id!(ID_INPUT),
+            attrs! {
+                At::Type => "checkbox",
+                At::Checked => model.normalize.as_at_value(),
+            },
+            ev(Ev::Change, |_| Msg::ToggleNormalize)
+        ],
+        label![
+            C!(CLASS_HAS_TOOLTIP),
+            attrs! {
+                At::For => ID_INPUT,
+                At::Title => DESC,
+            },
+            "ロード時に正規化を行う"
+        ],
+    ]
+}
+
+fn view_passwords(model: &Model) -> Vec<Node<Msg>> {
+    const ID_INPUT_FILTER: &str = "input-passwords-filter";
+    const CLASS_ITEM: &str = "passwords-item";
+    const CLASS_SORTABLE: &str = "sortable";
+
+    struct Row<'a> {
+        /// `model.passwords` 内のインデックス。
+        idx: usize,
+        password: &'a Password,
+        savedata: Savedata,
+        implausibilities: Vec<Implausibility>,
+        score: u32,
+        /// 各列の表示文字列。
+        cells: [String; 8],
+    }
+
+    let mut rows: Vec<_> = model
+        .passwords
+        .iter()
+        .enumerate()
+        .filter_map(|(idx, password)| {
+            // 不自然さは正規化前のセーブデータで判定する。
+            let savedata_raw = load_from_password(password, false)?;
+            let implausibilities = implausibilities(&savedata_raw);
+            let score: u32 = implausibilities.iter().map(|e| e.weight()).sum();
+            let savedata = if model.normalize {
+                savedata_raw.normalize()
+            } else {
+                savedata_raw
+            };
+            let cells = [
+                score.to_string(),
+                password.display_pretty().to_string(),
+                savedata.xp.to_string(),
+                savedata.purse.to_string(),
+                (u32::from(savedata.deposit.get()) * 1000).to_string(),
+                savedata.age.to_string(),
+                weapon_index_name(savedata.equipment.weapon).to_owned(),
+                armor_index_name(savedata.equipment.armor).to_owned(),
+            ];
+            Some(Row {
+                idx,
+                password,
+                savedata,
+                implausibilities,
+                score,
+                cells,
+            })
+        })
+        .filter(|row| {
+            // 空白区切りの各語がいずれかの列に含まれるものを残す。
+            model.passwords_filter.split_whitespace().all(|word| {
+                row.cells
+                    .iter()
+                    .any(|cell| cell.replace(' ', "").contains(word))
+            })
+        })
+        .collect();
+
+    if let Some((key, descending)) = model.passwords_sort {
+        rows.sort_by(|a, b| {
+            let (sa, sb) = (&a.savedata, &b.savedata);
+            let ord = match key {
+                PasswordsSortKey::Implausibility => a.score.cmp(&b.score),
+                PasswordsSortKey::Password => a.password.cmp(b.password),
+                PasswordsSortKey::Xp => sa.xp.cmp(&sb.xp),
+                PasswordsSortKey::Purse => sa.purse.cmp(&sb.purse),
+                PasswordsSortKey::Deposit => sa.deposit.cmp(&sb.deposit),
+                PasswordsSortKey::Age => sa.age.cmp(&sb.age),
+                PasswordsSortKey::Weapon => sa.equipment.weapon.cmp(&sb.equipment.weapon),
+                PasswordsSortKey::Armor => sa.equipment.armor.cmp(&sb.equipment.armor),
+            };
+            if descending {
+                ord.reverse()
+            } else {
+                ord
+            }
+        });
+    }
+
+    let header = PasswordsSortKey::ALL.map(|key| {
+        let mark = match model.passwords_sort {
+            Some((key_cur, false)) if key_cur == key => " ▲",
+            Some((key_cur, true)) if key_cur == key => " ▼",
+            _ => "",
+        };
+        th![
+            C![CLASS_SORTABLE],
+            format!("{}{mark}", key.name()),
+            ev(Ev::Click, move |_| Msg::PasswordsSort(key))
+        ]
+    });
+
+    let rows = rows.into_iter().map(|row| {
+        let idx = row.idx;
+        let [score, cells @ ..] = row.cells;
+        let desc = row
+            .implausibilities
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join("\n");
+        tr![
+            C![CLASS_ITEM],
+            td![
+                C![IF!(!desc.is_empty() => CLASS_HAS_TOOLTIP)],
+                attrs! {
+                    At::Title => desc,
+                },
+                score
+            ],
+            cells.map(|cell| td![cell]),
+            ev(Ev::Click, move |_| Msg::PickPassword(idx))
+        ]
+    });
+
+    nodes![
+        IF!(!model.passwords.is_empty() => div![
+            style! {
+                St::Display => "flex",
+                St::JustifyContent => "space-between",
+            },
+            div![
+                label![
+                    attrs! {
+                        At::For => ID_INPUT_FILTER,
+                    },
+                    "絞り込み: "
+                ],
+                input![
+                    id!(ID_INPUT_FILTER),
+                    attrs! {
+                        At::Type => "text",
+                        At::Value => &model.passwords_filter,
+                        At::Placeholder => "空白区切りで複数指定可",
+                    },
+                    input_ev(Ev::Input, Msg::PasswordsFilterUpdate)
+                ],
+            ],
+            a![
+                attrs! {
+                    At::Href => "#",
+                },
+                "デコード結果を CSV でダウンロード",
+                ev(Ev::Click, |ev| {
+                    ev.prevent_default();
+                    Msg::PasswordsDownloadCsv
+                })
+            ],
+        ]),
+        div![
+            id!("passwords-container"),
+            IF!(!model.passwords.is_empty() => table![tr![header], rows]),
+        ],
+    ]
+}
+
+fn view_savedata(model: &Model) -> Node<Msg> {
+    const DESC_SHORTCUT: &str = "[ / ]: 術・イベント・宝物・お供・ブックマーク・装備を選ぶ\n\
+        数字キー: n 番目のフラグを切り替える (0 は 10 番目)。装備では n 番目の枠を選ぶ\n\
+        ↑ / ↓: 装備の枠を選ぶ\n\
+        ← / →: 装備のインデックスを増減する\n\
+        Ctrl+Enter: パスワードにセーブ";
+
+    div![
+        id!("savedata-container"),
+        form![
+            div![
+                style! {
+                    St::Display => "flex",
+                    St::JustifyContent => "flex-end",
+                    St::AlignItems => "center",
+                },
+                span![
+                    C!(CLASS_HAS_TOOLTIP),
+                    style! {
+                        St::MarginRight => "auto",
+                    },
+                    attrs! {
+                        At::Title => DESC_SHORTCUT,
+                    },
+                    "キー操作"
+                ],
+                view_savedata_preset(model),
+                button![
                     style! {
                         St::MarginRight => "4px",
                     },
@@ -343,6 +2080,17 @@ fn view_savedata(model: &Model) -> Node<Msg> {
                     "正規化",
                     ev(Ev::Click, |_| Msg::SavedataNormalize)
                 ],
+                button![
+                    style! {
+                        St::MarginRight => "4px",
+                    },
+                    attrs! {
+                        At::Type => "button",
+                        At::Title => "経験値・所持金・預金を最大にし、全てのフラグを立て、最強の装備にする",
+                    },
+                    "全て最大",
+                    ev(Ev::Click, |_| Msg::SavedataMaxOut)
+                ],
                 button![
                     style! {
                         St::MarginLeft => "4px",
@@ -353,6 +2101,9 @@ fn view_savedata(model: &Model) -> Node<Msg> {
                     "パスワードにセーブ"
                 ],
             ],
+            view_savedata_equivalent(model),
+            view_savedata_shortest(model),
+            view_savedata_vanity(model),
             table![
                 view_savedata_xp(model),
                 view_savedata_money(model),
@@ -364,6 +2115,7 @@ fn view_savedata(model: &Model) -> Node<Msg> {
                 view_savedata_respawn(model),
                 view_savedata_events(model),
                 view_savedata_equipment(model),
+                view_savedata_equipment_loaded(model),
                 view_savedata_inventory(model),
             ],
             ev(Ev::Submit, |ev| {
@@ -374,10 +2126,228 @@ fn view_savedata(model: &Model) -> Node<Msg> {
     ]
 }
 
+fn view_savedata_equivalent(model: &Model) -> Node<Msg> {
+    let counts = equivalent_password_counts(&model.savedata);
+    let Some(&(len_min, _)) = counts.first() else {
+        return div![C!(CLASS_WARN), "このセーブデータはパスワードで表せない"];
+    };
+    let total: u128 = counts.iter().map(|&(_, count)| count).sum();
+
+    let detail = counts
+        .iter()
+        .map(|(len, count)| format!("{len} 文字: {count} 個"))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    div![
+        style! {
+            St::Display => "flex",
+            St::JustifyContent => "flex-end",
+            St::AlignItems => "center",
+        },
+        span![
+            C!(CLASS_HAS_TOOLTIP),
+            attrs! {
+                At::Title => detail,
+            },
+            format!("同じセーブデータになるパスワード: {total} 個 (最短 {len_min} 文字)"),
+        ],
+        button![
+            style! {
+                St::MarginLeft => "4px",
+            },
+            attrs! {
+                At::Type => "button",
+            },
+            "列挙",
+            ev(Ev::Click, |_| Msg::SavedataEquivalentPasswords)
+        ],
+    ]
+}
+
+fn view_savedata_shortest(model: &Model) -> Node<Msg> {
+    let checkboxes = FreeField::all().map(|field| {
+        label![
+            input![
+                attrs! {
+                    At::Type => "checkbox",
+                    At::Checked => model.shortest_free.contains(&field).as_at_value(),
+                },
+                ev(Ev::Change, move |_| Msg::ToggleShortestFree(field))
+            ],
+            free_field_name(field),
+        ]
+    });
+
+    let status = match &model.shortest_search {
+        Some(search) => view_search_progress(search.progress(), || Msg::ShortestSearchCancel),
+        None => span![model.shortest_message.as_ref().map(|msg| format!(" {msg}"))],
+    };
+
+    details![
+        summary!["最短入力のパスワード (TAS 用)"],
+        div![
+            "入力の手数(カーソル移動とボタン押下の回数)が最小のパスワードを探す。",
+            "カーソルの動き方は入力ムービーの設定に従う。",
+        ],
+        div!["任意の値にしてよいフィールド: ", checkboxes],
+        div![
+            button![
+                attrs! {
+                    At::Type => "button",
+                    At::Disabled => model.shortest_search.is_some().as_at_value(),
+                },
+                "最短入力でセーブ",
+                ev(Ev::Click, |_| Msg::SavedataToShortestPassword)
+            ],
+            status,
+        ],
+    ]
+}
+
+/// 探索の進捗 (処理済みのバイト数, 全バイト数) と中止ボタン。
+fn view_search_progress((done, total): (usize, usize), cancel: fn() -> Msg) -> Node<Msg> {
+    span![
+        format!(" 探索中 ({done}/{total})"),
+        button![
+            style! {
+                St::MarginLeft => "4px",
+            },
+            attrs! {
+                At::Type => "button",
+            },
+            "中止",
+            ev(Ev::Click, move |_| cancel())
+        ],
+    ]
+}
+
+/// フィールドの変更内容を "年齢: 12 → 40" のように列挙した文字列を返す。
+fn format_free_field_diff(diff: &[(FreeField, u8, u8)]) -> String {
+    if diff.is_empty() {
+        return "セーブデータの変更なし".to_owned();
+    }
+
+    diff.iter()
+        .map(|&(field, old, new)| format!("{}: {old} → {new}", free_field_name(field)))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn view_savedata_vanity(model: &Model) -> Node<Msg> {
+    const ID_INPUT_WORD: &str = "input-vanity-word";
+    const ID_INPUT_POS: &str = "input-vanity-pos";
+
+    let checkboxes = FreeField::all().map(|field| {
+        label![
+            input![
+                attrs! {
+                    At::Type => "checkbox",
+                    At::Checked => model.vanity_free.contains(&field).as_at_value(),
+                },
+                ev(Ev::Change, move |_| Msg::ToggleVanityFree(field))
+            ],
+            free_field_name(field),
+        ]
+    });
+
+    details![
+        summary!["語呂合わせパスワード"],
+        div![
+            label![
+                attrs! {
+                    At::For => ID_INPUT_WORD,
+                },
+                "単語: "
+            ],
+            input![
+                id!(ID_INPUT_WORD),
+                attrs! {
+                    At::Type => "text",
+                    At::Value => &model.vanity_word,
+                },
+                input_ev(Ev::Input, Msg::VanityWordUpdate)
+            ],
+            label![
+                attrs! {
+                    At::For => ID_INPUT_POS,
+                },
+                " 位置: "
+            ],
+            input![
+                id!(ID_INPUT_POS),
+                attrs! {
+                    At::Type => "number",
+                    At::Min => 1,
+                    At::Max => Password::MAX_LEN,
+                    At::Value => model.vanity_pos,
+                },
+                input_ev(Ev::Input, Msg::VanityPosUpdate)
+            ],
+            " 文字目から",
+        ],
+        div!["変えてもよいフィールド: ", checkboxes],
+        div![
+            button![
+                attrs! {
+                    At::Type => "button",
+                    At::Disabled => model.vanity_search.is_some().as_at_value(),
+                },
+                "探す",
+                ev(Ev::Click, |_| Msg::VanitySearch)
+            ],
+            match &model.vanity_search {
+                Some(search) => view_search_progress(search.progress(), || Msg::VanitySearchCancel),
+                None => span![model.vanity_message.as_ref().map(|msg| format!(" {msg}"))],
+            },
+        ],
+    ]
+}
+
+fn view_savedata_preset(_model: &Model) -> Node<Msg> {
+    const ID_INPUT: &str = "input-preset";
+
+    let options = Preset::all().map(|preset| {
+        option![
+            attrs! {
+                At::Value => preset.int_value(),
+            },
+            preset_name(preset)
+        ]
+    });
+
+    // 選択後は常に先頭の項目に戻す。
+    select![
+        id!(ID_INPUT),
+        style! {
+            St::MarginRight => "auto",
+        },
+        attrs! {
+            At::Value => "",
+        },
+        option![
+            attrs! {
+                At::Value => "",
+                At::Disabled => AtValue::None,
+            },
+            "プリセットをロード..."
+        ],
+        options,
+        input_ev(Ev::Change, |s| s
+            .parse::<u8>()
+            .ok()
+            .and_then(|x| Preset::from_int(x).ok())
+            .map(Msg::SavedataLoadPreset))
+    ]
+}
+
 fn view_savedata_xp(model: &Model) -> Node<Msg> {
     const ID_INPUT: &str = "input-xp";
 
     tr![
+        attrs! {
+            At::Title => field_layout_desc(SavedataField::Xp),
+        },
         th![label![
             attrs! {
                 At::For => ID_INPUT,
@@ -416,6 +2386,9 @@ fn view_savedata_purse(model: &Model) -> Node<Msg> {
     const ID_INPUT: &str = "input-purse";
 
     div![
+        attrs! {
+            At::Title => field_layout_desc(SavedataField::Purse),
+        },
         label![
             attrs! {
                 At::For => ID_INPUT,
@@ -443,6 +2416,9 @@ fn view_savedata_deposit(model: &Model) -> Node<Msg> {
     const ID_INPUT: &str = "input-deposit";
 
     div![
+        attrs! {
+            At::Title => field_layout_desc(SavedataField::Deposit),
+        },
         label![
             attrs! {
                 At::For => ID_INPUT,
@@ -482,6 +2458,9 @@ fn view_savedata_age(model: &Model) -> Node<Msg> {
     const ID_INPUT: &str = "input-age";
 
     div![
+        attrs! {
+            At::Title => field_layout_desc(SavedataField::Age),
+        },
         input![
             id!(ID_INPUT),
             attrs! {
@@ -510,6 +2489,9 @@ fn view_savedata_age_timer_hi(model: &Model) -> Node<Msg> {
     const DESC: &str = "約 25 秒で 1 増え、255 -> 0 のとき加齢する。";
 
     div![
+        attrs! {
+            At::Title => field_layout_desc(SavedataField::AgeTimerHi),
+        },
         label![
             C!(CLASS_HAS_TOOLTIP),
             attrs! {
@@ -536,147 +2518,117 @@ fn view_savedata_age_timer_hi(model: &Model) -> Node<Msg> {
 }
 
 fn view_savedata_spells(model: &Model) -> Node<Msg> {
-    const ID_INPUT_BASE: &str = "input-spell";
-
-    let inputs = Spell::all().map(|spell| {
-        let id = format!("{ID_INPUT_BASE}-{}", spell.int_value());
-        div![
-            input![
-                id!(&id),
-                attrs! {
-                    At::Type => "checkbox",
-                    At::Checked => model.savedata.spells[spell].as_at_value(),
-                },
-                ev(Ev::Change, move |_| Msg::SavedataToggleSpell(spell))
-            ],
-            label![
-                attrs! {
-                    At::For => &id,
-                },
-                spell_name(spell)
-            ],
-        ]
-    });
-
-    tr![th!["術"], td![div![id!("spells-input-container"), inputs]]]
+    view_savedata_flags::<Spell>(model, "input-spell", Some("spells-input-container"))
 }
 
 fn view_savedata_events(model: &Model) -> Node<Msg> {
-    const ID_INPUT_BASE: &str = "input-event";
+    view_savedata_flags::<Event>(model, "input-event", None)
+}
 
-    let events = Event::all().map(|event| {
-        let id = format!("{ID_INPUT_BASE}-{}", event.int_value());
-        div![
-            input![
-                id!(&id),
-                attrs! {
-                    At::Type => "checkbox",
-                    At::Checked => model.savedata.events[event].as_at_value(),
-                },
-                ev(Ev::Change, move |_| Msg::SavedataToggleEvent(event))
-            ],
-            label![
-                attrs! {
-                    At::For => &id,
-                },
-                event_name(event)
-            ],
-        ]
-    });
+fn view_savedata_treasures(model: &Model) -> Node<Msg> {
+    view_savedata_flags::<Treasure>(model, "input-treasure", Some("treasures-input-container"))
+}
 
-    tr![
-        th!["イベント"],
-        td![
-            events
-            //
-        ]
-    ]
+fn view_savedata_minions(model: &Model) -> Node<Msg> {
+    view_savedata_flags::<Minion>(model, "input-minion", Some("minions-input-container"))
 }
 
-fn view_savedata_treasures(model: &Model) -> Node<Msg> {
-    const ID_INPUT_BASE: &str = "input-treasure";
+fn view_savedata_bookmarks(model: &Model) -> Node<Msg> {
+    view_savedata_flags::<Bookmark>(model, "input-bookmark", Some("bookmarks-input-container"))
+}
 
-    let treasures = Treasure::all().map(|treasure| {
-        let id = format!("{ID_INPUT_BASE}-{}", treasure.int_value());
-        div![
-            input![
-                id!(&id),
-                attrs! {
-                    At::Type => "checkbox",
-                    At::Checked => model.savedata.treasures[treasure].as_at_value(),
-                },
-                ev(Ev::Change, move |_| Msg::SavedataToggleTreasure(treasure))
-            ],
-            label![
-                attrs! {
-                    At::For => &id,
-                },
-                treasure_name(treasure)
-            ],
-        ]
-    });
+/// フラグ `self` を反転するメッセージを返す。
+trait ToggleMsg: GroupFlag {
+    fn toggle_msg(self) -> Msg;
+}
 
-    tr![
-        th!["宝物"],
-        td![div![id!("treasures-input-container"), treasures]]
-    ]
+impl ToggleMsg for Spell {
+    fn toggle_msg(self) -> Msg {
+        Msg::SavedataToggleSpell(self)
+    }
 }
 
-fn view_savedata_minions(model: &Model) -> Node<Msg> {
-    const ID_INPUT_BASE: &str = "input-minion";
+impl ToggleMsg for Event {
+    fn toggle_msg(self) -> Msg {
+        Msg::SavedataToggleEvent(self)
+    }
+}
 
-    let minions = Minion::all().map(|minion| {
-        let id = format!("{ID_INPUT_BASE}-{}", minion.int_value());
-        div![
-            input![
-                id!(&id),
-                attrs! {
-                    At::Type => "checkbox",
-                    At::Checked => model.savedata.minions[minion].as_at_value(),
-                },
-                ev(Ev::Change, move |_| Msg::SavedataToggleMinion(minion))
-            ],
-            label![
-                attrs! {
-                    At::For => &id,
-                },
-                minion_name(minion)
-            ],
-        ]
-    });
+impl ToggleMsg for Treasure {
+    fn toggle_msg(self) -> Msg {
+        Msg::SavedataToggleTreasure(self)
+    }
+}
 
-    tr![
-        th!["お供"],
-        td![div![id!("minions-input-container"), minions]]
-    ]
+impl ToggleMsg for Minion {
+    fn toggle_msg(self) -> Msg {
+        Msg::SavedataToggleMinion(self)
+    }
 }
 
-fn view_savedata_bookmarks(model: &Model) -> Node<Msg> {
-    const ID_INPUT_BASE: &str = "input-bookmark";
+impl ToggleMsg for Bookmark {
+    fn toggle_msg(self) -> Msg {
+        Msg::SavedataToggleBookmark(self)
+    }
+}
 
-    let bookmarks = Bookmark::all().map(|bookmark| {
-        let id = format!("{ID_INPUT_BASE}-{}", bookmark.int_value());
+/// フラグ群 `F` のチェックボックスを並べた行。見出しには立っているフラグの個数と一括操作のボタンを添える。
+fn view_savedata_flags<F: ToggleMsg>(
+    model: &Model,
+    id_input_base: &str,
+    id_container: Option<&str>,
+) -> Node<Msg> {
+    let group = F::GROUP;
+    let field = SavedataField::from(group);
+    let flags = FlagSet::<F>::from_savedata(&model.savedata);
+
+    let inputs = F::ALL.iter().map(|&flag| {
+        let id = format!("{id_input_base}-{}", flag.int_value());
         div![
             input![
                 id!(&id),
                 attrs! {
                     At::Type => "checkbox",
-                    At::Checked => model.savedata.bookmarks[bookmark].as_at_value(),
+                    At::Checked => flags.contains(flag).as_at_value(),
                 },
-                ev(Ev::Change, move |_| Msg::SavedataToggleBookmark(bookmark))
+                ev(Ev::Change, move |_| flag.toggle_msg())
             ],
             label![
                 attrs! {
                     At::For => &id,
                 },
-                bookmark_name(bookmark),
+                flag.name()
             ],
         ]
     });
 
+    let ops = FlagOp::all().map(|op| {
+        button![
+            C!(CLASS_FLAG_OP),
+            attrs! {
+                At::Type => "button",
+            },
+            flag_op_name(op),
+            ev(Ev::Click, move |_| Msg::SavedataUpdateFlags(group, op))
+        ]
+    });
+
+    let selected = model.shortcut_section == Some(SavedataSection::Flags(group));
+
     tr![
-        th!["ブックマーク"],
-        td![div![id!("bookmarks-input-container"), bookmarks]]
+        C![IF!(selected => CLASS_SHORTCUT_SELECTED)],
+        attrs! {
+            At::Title => field_layout_desc(field),
+        },
+        th![
+            savedata_field_name(field),
+            div![format!("({}/{})", flags.count(), F::ALL.len())],
+            div![ops],
+        ],
+        match id_container {
+            Some(id_container) => td![div![id!(id_container), inputs]],
+            None => td![inputs],
+        }
     ]
 }
 
@@ -694,6 +2646,9 @@ fn view_savedata_respawn(model: &Model) -> Node<Msg> {
     });
 
     tr![
+        attrs! {
+            At::Title => field_layout_desc(SavedataField::Respawn),
+        },
         th![label![
             attrs! {
                 At::For => ID_INPUT,
@@ -715,7 +2670,10 @@ fn view_savedata_respawn(model: &Model) -> Node<Msg> {
 }
 
 fn view_savedata_equipment(model: &Model) -> Node<Msg> {
+    let selected = model.shortcut_section == Some(SavedataSection::Equipment);
+
     tr![
+        C![IF!(selected => CLASS_SHORTCUT_SELECTED)],
         th!["装備"],
         td![div![
             id!("equipment-input-container"),
@@ -731,6 +2689,55 @@ fn view_savedata_equipment(model: &Model) -> Node<Msg> {
     ]
 }
 
+/// 装備枠 `slot` がキーボードショートカットで選択中かどうかを返す。
+fn shortcut_slot_selected(model: &Model, slot: EquipmentSlot) -> bool {
+    model.shortcut_section == Some(SavedataSection::Equipment) && model.shortcut_slot == slot
+}
+
+fn view_savedata_equipment_loaded(model: &Model) -> Node<Msg> {
+    const DESC: &str = "セーブデータ内の装備インデックスが不正な場合、ロード時に別の枠へ移ったり無視されたりする。\n左: 各枠のインデックスの行き先\n右: ロード後の装備";
+
+    let equipment = &model.savedata.equipment;
+    let equipment_loaded = equipment.normalize();
+
+    let rows = EquipmentSlot::all().map(|slot| {
+        let fate = equipment_fate(equipment, slot);
+        let fate_text = match fate {
+            EquipmentFate::Kept => "そのまま".to_owned(),
+            EquipmentFate::Ignored => "無視される".to_owned(),
+            EquipmentFate::Moved(dst) => format!("{}枠に装備", equipment_slot_name(dst)),
+            EquipmentFate::Overwritten(dst) => {
+                format!("{}枠に移るが上書きされる", equipment_slot_name(dst))
+            }
+        };
+        let changed =
+            equipment_index_name(equipment, slot) != equipment_index_name(&equipment_loaded, slot);
+
+        nodes![
+            div![C!(CLASS_EQUIPMENT_LABEL), equipment_slot_name(slot)],
+            div![
+                C![IF!(fate != EquipmentFate::Kept => CLASS_WARN)],
+                fate_text
+            ],
+            div![
+                C![IF!(changed => CLASS_WARN)],
+                equipment_index_name(&equipment_loaded, slot)
+            ],
+        ]
+    });
+
+    tr![
+        th![span![
+            C!(CLASS_HAS_TOOLTIP),
+            attrs! {
+                At::Title => DESC,
+            },
+            "ロード後の装備"
+        ]],
+        td![div![id!("equipment-loaded-container"), rows]],
+    ]
+}
+
 fn view_savedata_helm(model: &Model) -> Vec<Node<Msg>> {
     const ID_INPUT: &str = "input-helm";
 
@@ -746,7 +2753,13 @@ fn view_savedata_helm(model: &Model) -> Vec<Node<Msg>> {
 
     nodes![
         div![
-            C!(CLASS_EQUIPMENT_LABEL),
+            C![
+                CLASS_EQUIPMENT_LABEL,
+                IF!(shortcut_slot_selected(model, EquipmentSlot::Helm) => CLASS_SHORTCUT_SELECTED)
+            ],
+            attrs! {
+                At::Title => field_layout_desc(SavedataField::Equipment(EquipmentSlot::Helm)),
+            },
             label![
                 attrs! {
                     At::For => ID_INPUT,
@@ -784,7 +2797,13 @@ fn view_savedata_weapon(model: &Model) -> Vec<Node<Msg>> {
 
     nodes![
         div![
-            C!(CLASS_EQUIPMENT_LABEL),
+            C![
+                CLASS_EQUIPMENT_LABEL,
+                IF!(shortcut_slot_selected(model, EquipmentSlot::Weapon) => CLASS_SHORTCUT_SELECTED)
+            ],
+            attrs! {
+                At::Title => field_layout_desc(SavedataField::Equipment(EquipmentSlot::Weapon)),
+            },
             label![
                 attrs! {
                     At::For => ID_INPUT,
@@ -822,7 +2841,13 @@ fn view_savedata_armor(model: &Model) -> Vec<Node<Msg>> {
 
     nodes![
         div![
-            C!(CLASS_EQUIPMENT_LABEL),
+            C![
+                CLASS_EQUIPMENT_LABEL,
+                IF!(shortcut_slot_selected(model, EquipmentSlot::Armor) => CLASS_SHORTCUT_SELECTED)
+            ],
+            attrs! {
+                At::Title => field_layout_desc(SavedataField::Equipment(EquipmentSlot::Armor)),
+            },
             label![
                 attrs! {
                     At::For => ID_INPUT,
@@ -860,7 +2885,13 @@ fn view_savedata_shoes(model: &Model) -> Vec<Node<Msg>> {
 
     nodes![
         div![
-            C!(CLASS_EQUIPMENT_LABEL),
+            C![
+                CLASS_EQUIPMENT_LABEL,
+                IF!(shortcut_slot_selected(model, EquipmentSlot::Shoes) => CLASS_SHORTCUT_SELECTED)
+            ],
+            attrs! {
+                At::Title => field_layout_desc(SavedataField::Equipment(EquipmentSlot::Shoes)),
+            },
             label![
                 attrs! {
                     At::For => ID_INPUT,
@@ -898,7 +2929,13 @@ fn view_savedata_accessory0(model: &Model) -> Vec<Node<Msg>> {
 
     nodes![
         div![
-            C!(CLASS_EQUIPMENT_LABEL),
+            C![
+                CLASS_EQUIPMENT_LABEL,
+                IF!(shortcut_slot_selected(model, EquipmentSlot::Accessory0) => CLASS_SHORTCUT_SELECTED)
+            ],
+            attrs! {
+                At::Title => field_layout_desc(SavedataField::Equipment(EquipmentSlot::Accessory0)),
+            },
             label![
                 attrs! {
                     At::For => ID_INPUT,
@@ -936,7 +2973,13 @@ fn view_savedata_accessory1(model: &Model) -> Vec<Node<Msg>> {
 
     nodes![
         div![
-            C!(CLASS_EQUIPMENT_LABEL),
+            C![
+                CLASS_EQUIPMENT_LABEL,
+                IF!(shortcut_slot_selected(model, EquipmentSlot::Accessory1) => CLASS_SHORTCUT_SELECTED)
+            ],
+            attrs! {
+                At::Title => field_layout_desc(SavedataField::Equipment(EquipmentSlot::Accessory1)),
+            },
             label![
                 attrs! {
                     At::For => ID_INPUT,
@@ -974,7 +3017,13 @@ fn view_savedata_accessory2(model: &Model) -> Vec<Node<Msg>> {
 
     nodes![
         div![
-            C!(CLASS_EQUIPMENT_LABEL),
+            C![
+                CLASS_EQUIPMENT_LABEL,
+                IF!(shortcut_slot_selected(model, EquipmentSlot::Accessory2) => CLASS_SHORTCUT_SELECTED)
+            ],
+            attrs! {
+                At::Title => field_layout_desc(SavedataField::Equipment(EquipmentSlot::Accessory2)),
+            },
             label![
                 attrs! {
                     At::For => ID_INPUT,
@@ -1012,7 +3061,13 @@ fn view_savedata_accessory3(model: &Model) -> Vec<Node<Msg>> {
 
     nodes![
         div![
-            C!(CLASS_EQUIPMENT_LABEL),
+            C![
+                CLASS_EQUIPMENT_LABEL,
+                IF!(shortcut_slot_selected(model, EquipmentSlot::Accessory3) => CLASS_SHORTCUT_SELECTED)
+            ],
+            attrs! {
+                At::Title => field_layout_desc(SavedataField::Equipment(EquipmentSlot::Accessory3)),
+            },
             label![
                 attrs! {
                     At::For => ID_INPUT,
@@ -1036,32 +3091,286 @@ fn view_savedata_accessory3(model: &Model) -> Vec<Node<Msg>> {
 }
 
 fn view_savedata_inventory(model: &Model) -> Node<Msg> {
-    let items = (0..8).map(|i| div![view_savedata_inventory_item(model, i)]);
+    let inventory = &model.savedata.inventory;
+    let items = (0..inventory.capacity()).map(|i| {
+        nodes![
+            view_savedata_inventory_item(model, i),
+            IF!(model.item_picker == Some(i) => view_item_picker(model)),
+        ]
+    });
 
-    tr![th!["インベントリ"], td![items]]
+    tr![
+        attrs! {
+            At::Title => field_layout_desc(SavedataField::Inventory),
+        },
+        th![
+            "インベントリ",
+            div![format!("({}/{})", inventory.len(), inventory.capacity())],
+            div![button![
+                C!(CLASS_INVENTORY_OP),
+                attrs! {
+                    At::Type => "button",
+                },
+                "空にする",
+                ev(Ev::Click, |_| Msg::SavedataClearInventory)
+            ]],
+        ],
+        td![items]
+    ]
 }
 
+/// インベントリの `idx` 番目の欄。アイテムがある欄はドラッグで並べ替えられる。
 fn view_savedata_inventory_item(model: &Model, idx: usize) -> Node<Msg> {
-    let options = (0..=ItemId::MAX_VALUE).map(|i| {
-        let id = ItemId::new(i);
-        let text = format!("0x{i:02X}: {}", id.map_or("(空欄)", item_name));
-        option![
-            attrs! {
-                At::Value => i,
-            },
-            text
+    let inventory = &model.savedata.inventory;
+    let filled = idx < inventory.len();
+
+    let ops = filled.then(|| {
+        let disabled = inventory.is_full().as_at_value();
+        nodes![
+            button![
+                C!(CLASS_INVENTORY_OP),
+                attrs! {
+                    At::Type => "button",
+                    At::Title => "この位置にアイテムを挿入する",
+                    At::Disabled => disabled,
+                },
+                "挿入",
+                ev(Ev::Click, move |_| Msg::SavedataInsertInventoryItem(idx))
+            ],
+            button![
+                C!(CLASS_INVENTORY_OP),
+                attrs! {
+                    At::Type => "button",
+                    At::Title => "このアイテムを複製する",
+                    At::Disabled => disabled,
+                },
+                "複製",
+                ev(Ev::Click, move |_| Msg::SavedataDuplicateInventoryItem(idx))
+            ],
         ]
     });
 
-    div![select![
-        options,
+    div![
+        C![
+            CLASS_INVENTORY_ITEM,
+            IF!(model.inventory_drag == Some(idx) => CLASS_INVENTORY_DRAGGING)
+        ],
         attrs! {
-            At::Value => model.savedata.inventory.get(idx).map_or(0, |id| id.get()),
+            At::Draggable => filled.as_at_value(),
         },
-        input_ev(Ev::Change, move |s| s
-            .parse::<u8>()
-            .ok()
-            .and_then(|x| matches!(x, 0..=ItemId::MAX_VALUE)
-                .then(|| Msg::SavedataUpdateInventory(idx, ItemId::new(x)))))
-    ]]
+        drag_ev(Ev::DragStart, move |event| {
+            // Firefox はデータを設定しないとドラッグを開始しない。
+            if let Some(data) = event.data_transfer() {
+                data.set_data("text/plain", &idx.to_string()).ok();
+            }
+            Msg::InventoryDragStart(idx)
+        }),
+        drag_ev(Ev::DragOver, |event| event.prevent_default()),
+        drag_ev(Ev::Drop, move |event| {
+            event.prevent_default();
+            Msg::InventoryDrop(idx)
+        }),
+        drag_ev(Ev::DragEnd, |_| Msg::InventoryDragEnd),
+        // 空欄にも幅を確保して、アイテム選択ボタンの位置を揃える。
+        span![
+            IF!(filled => attrs! {
+                At::Title => "ドラッグで並べ替え",
+            }),
+            IF!(filled => "≡"),
+        ],
+        button![
+            C!(CLASS_ITEM_PICKER_BUTTON),
+            attrs! {
+                At::Type => "button",
+            },
+            view_item_label(inventory.get(idx).copied()),
+            ev(Ev::Click, move |_| Msg::ItemPickerOpen(idx))
+        ],
+        ops,
+    ]
+}
+
+/// アイテム選択で、絞り込み文字列に合うアイテム(`None` は空欄)を列挙する。
+fn picker_items(model: &Model) -> impl Iterator<Item = Option<ItemId>> + '_ {
+    (0..=ItemId::MAX_VALUE)
+        .map(ItemId::new)
+        .filter(|&id| item_matches(id, &model.item_picker_filter, model.romaji))
+}
+
+/// 分類アイコン付きのアイテム名。
+fn view_item_label(id: Option<ItemId>) -> Vec<Node<Msg>> {
+    let icon = id.map(|id| {
+        let category = item_category(id);
+        span![
+            attrs! {
+                At::Title => item_category_name(category),
+            },
+            item_category_icon(category)
+        ]
+    });
+    let text = format!(
+        "0x{:02X}: {}",
+        id.map_or(0, |id| id.get()),
+        id.map_or("(空欄)", item_name)
+    );
+
+    nodes![span![icon], span![text]]
+}
+
+fn item_category_icon(category: ItemCategory) -> &'static str {
+    match category {
+        ItemCategory::Food => "🍙",
+        ItemCategory::Equipment => "⚔",
+        ItemCategory::Tool => "🎒",
+        ItemCategory::Key => "🔑",
+    }
+}
+
+/// 絞り込み入力付きのアイテム選択。
+fn view_item_picker(model: &Model) -> Node<Msg> {
+    let items = picker_items(model).map(|id| {
+        li![
+            view_item_label(id),
+            ev(Ev::Click, move |_| Msg::ItemPickerSelect(id))
+        ]
+    });
+
+    div![
+        C!(CLASS_ITEM_PICKER),
+        div![
+            input![
+                id!(ID_INPUT_ITEM_PICKER),
+                attrs! {
+                    At::Type => "text",
+                    At::Value => &model.item_picker_filter,
+                    At::Placeholder => "名前または ID で絞り込み (Enter で先頭を選択)",
+                },
+                input_ev(Ev::Input, Msg::ItemPickerFilterUpdate),
+                keyboard_ev(Ev::KeyDown, |event| match event.key().as_str() {
+                    "Enter" => {
+                        // フォームの送信(パスワードにセーブ)を防ぐ。
+                        event.prevent_default();
+                        Some(Msg::ItemPickerSelectFirst)
+                    }
+                    "Escape" => Some(Msg::ItemPickerClose),
+                    _ => None,
+                })
+            ],
+            button![
+                attrs! {
+                    At::Type => "button",
+                },
+                "閉じる",
+                ev(Ev::Click, |_| Msg::ItemPickerClose)
+            ],
+        ],
+        ul![items],
+    ]
+}
+
+fn view_bulk(model: &Model) -> Node<Msg> {
+    const ID_INPUT: &str = "input-bulk";
+    const ID_INPUT_FILE: &str = "input-bulk-file";
+
+    div![
+        id!("bulk-container"),
+        h2!["一括デコード"],
+        form![
+            div![textarea![
+                id!(ID_INPUT),
+                attrs! {
+                    At::Value => &model.bulk_input,
+                    At::Placeholder => "1 行に 1 個ずつパスワードを入力 (空白は無視される)",
+                },
+                input_ev(Ev::Input, Msg::BulkUpdate)
+            ]],
+            div![
+                style! {
+                    St::Display => "flex",
+                    St::JustifyContent => "space-between",
+                },
+                div![
+                    label![
+                        attrs! {
+                            At::For => ID_INPUT_FILE,
+                        },
+                        "ファイルから読み込む: "
+                    ],
+                    input![
+                        id!(ID_INPUT_FILE),
+                        attrs! {
+                            At::Type => "file",
+                            At::Accept => ".txt,.csv,text/plain,text/csv",
+                        },
+                        ev(Ev::Change, |ev| {
+                            let input =
+                                ev.target()?.dyn_into::<web_sys::HtmlInputElement>().ok()?;
+                            let file = input.files()?.get(0)?;
+                            Some(Msg::BulkLoadFile(file))
+                        })
+                    ],
+                ],
+                button![
+                    attrs! {
+                        At::Type => "submit",
+                    },
+                    "一括デコード"
+                ],
+            ],
+            ev(Ev::Submit, |ev| {
+                ev.prevent_default();
+                Msg::BulkSubmit
+            })
+        ],
+        view_bulk_result(model),
+    ]
+}
+
+fn view_bulk_result(model: &Model) -> Node<Msg> {
+    if model.bulk_rows.is_empty() {
+        return div![];
+    }
+
+    let count_ok = model
+        .bulk_rows
+        .iter()
+        .filter(|row| row.result.is_ok())
+        .count();
+
+    let header = ["パスワード".to_owned(), "状態".to_owned()]
+        .into_iter()
+        .chain(savedata_header())
+        .map(|name| th![name]);
+
+    let rows = model.bulk_rows.iter().map(|row| match &row.result {
+        Ok(savedata) => tr![
+            td![&row.input],
+            td!["OK"],
+            savedata_record(savedata)
+                .into_iter()
+                .map(|field| td![field]),
+        ],
+        Err(err) => tr![td![&row.input], td![C!(CLASS_WARN), err.to_string()]],
+    });
+
+    div![
+        div![
+            style! {
+                St::Display => "flex",
+                St::JustifyContent => "space-between",
+            },
+            format!("{} 件中 {count_ok} 件が有効", model.bulk_rows.len()),
+            a![
+                attrs! {
+                    At::Href => "#",
+                },
+                "CSV をダウンロード",
+                ev(Ev::Click, |ev| {
+                    ev.prevent_default();
+                    Msg::BulkDownloadCsv
+                })
+            ],
+        ],
+        div![id!("bulk-result-container"), table![tr![header], rows]],
+    ]
 }